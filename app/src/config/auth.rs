@@ -1,11 +1,18 @@
 use derivative::Derivative;
 use serde_derive::{Deserialize, Serialize};
+
+pub const DEFAULT_PASSWORD: &str = "password";
+
 #[derive(Serialize, Deserialize, Debug, Derivative, Clone)]
 #[derivative(Default)]
 pub struct AuthConfigs {
-    #[derivative(Default(value = "Some(\"password\".to_string())"))]
+    /// Plain password, it's ignored when `password_hash` is set
+    #[derivative(Default(value = "Some(DEFAULT_PASSWORD.to_string())"))]
     password: Option<String>,
 
+    /// argon2 hash of the password, written by `vns auth set-password`
+    password_hash: Option<String>,
+
     #[derivative(Default(value = "Some(2)"))]
     key_expire_duration: Option<i64>,
 }
@@ -14,14 +21,29 @@ impl AuthConfigs {
     pub fn from(password: &'static str, key_expire_duration: i64) -> Self {
         Self {
             password: Some(password.to_owned()),
+            password_hash: None,
             key_expire_duration: Some(key_expire_duration.to_owned()),
         }
     }
 
     pub fn password(&self) -> String {
-        self.password.clone().unwrap_or("password".to_owned())
+        self.password.clone().unwrap_or(DEFAULT_PASSWORD.to_owned())
+    }
+    pub fn password_hash(&self) -> Option<String> {
+        self.password_hash.clone().filter(|h| !h.is_empty())
     }
     pub fn key_expire_duration(&self) -> i64 {
         self.key_expire_duration.unwrap_or(2)
     }
+
+    /// Whether logging in still works by the default password
+    pub fn uses_default_password(&self) -> bool {
+        self.password_hash().is_none() && self.password() == DEFAULT_PASSWORD
+    }
+
+    /// Replace the password by its hash, the plain password is removed
+    pub fn set_password_hash(&mut self, hash: &str) {
+        self.password = None;
+        self.password_hash = Some(hash.to_owned());
+    }
 }
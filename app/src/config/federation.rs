@@ -0,0 +1,50 @@
+use derivative::Derivative;
+use serde_derive::{Deserialize, Serialize};
+
+/// An upstream vnsd instance queried in federation mode
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FederationHost {
+    /// Name used in `/api/federation/{host}/...` and to tag merged results
+    pub name: String,
+    /// Base URL of the instance, e.g. `http://10.0.0.2:8080`
+    pub url: String,
+    /// Bearer key issued by the instance
+    pub key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Derivative, Clone)]
+#[derivative(Default)]
+pub struct FederationConfigs {
+    #[derivative(Default(value = "Some(false)"))]
+    enabled: Option<bool>,
+
+    /// Seconds to wait for an upstream instance
+    #[derivative(Default(value = "Some(10)"))]
+    timeout: Option<u64>,
+
+    #[derivative(Default(value = "Some(vec![])"))]
+    hosts: Option<Vec<FederationHost>>,
+}
+
+impl FederationConfigs {
+    pub fn from(enabled: bool, timeout: u64, hosts: Vec<FederationHost>) -> Self {
+        Self {
+            enabled: Some(enabled),
+            timeout: Some(timeout),
+            hosts: Some(hosts),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+    pub fn timeout(&self) -> u64 {
+        self.timeout.unwrap_or(10).max(1)
+    }
+    pub fn hosts(&self) -> Vec<FederationHost> {
+        self.hosts.clone().unwrap_or_default()
+    }
+    pub fn host(&self, name: &str) -> Option<FederationHost> {
+        self.hosts().into_iter().find(|h| h.name == name)
+    }
+}
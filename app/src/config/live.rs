@@ -0,0 +1,37 @@
+use derivative::Derivative;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Derivative, Clone)]
+#[derivative(Default)]
+pub struct LiveConfigs {
+    #[derivative(Default(value = "Some(true)"))]
+    enabled: Option<bool>,
+
+    /// Seconds between two samples
+    #[derivative(Default(value = "Some(1)"))]
+    interval: Option<u64>,
+
+    /// Streamed interfaces, all interfaces (except loopback) if it's empty
+    #[derivative(Default(value = "Some(vec![])"))]
+    interfaces: Option<Vec<String>>,
+}
+
+impl LiveConfigs {
+    pub fn from(enabled: bool, interval: u64, interfaces: Vec<String>) -> Self {
+        Self {
+            enabled: Some(enabled),
+            interval: Some(interval),
+            interfaces: Some(interfaces),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+    pub fn interval(&self) -> u64 {
+        self.interval.unwrap_or(1).max(1)
+    }
+    pub fn interfaces(&self) -> Vec<String> {
+        self.interfaces.clone().unwrap_or_default()
+    }
+}
@@ -0,0 +1,22 @@
+use derivative::Derivative;
+use log::LevelFilter;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Derivative, Clone)]
+#[derivative(Default)]
+pub struct LogConfigs {
+    /// `off`, `error`, `warn`, `info`, `debug` or `trace`, `RUST_LOG` takes precedence over it
+    #[derivative(Default(value = "Some(\"info\".to_string())"))]
+    level: Option<String>,
+}
+
+impl LogConfigs {
+    pub fn level(&self) -> String {
+        self.level.clone().unwrap_or("info".to_owned())
+    }
+    pub fn level_filter(&self) -> Result<LevelFilter, String> {
+        self.level()
+            .parse()
+            .map_err(|_| format!("Invalid log level '{}'", self.level()))
+    }
+}
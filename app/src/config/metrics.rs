@@ -0,0 +1,46 @@
+use derivative::Derivative;
+use serde_derive::{Deserialize, Serialize};
+
+/// How `/metrics` requests are authenticated
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricsAuth {
+    /// Same bearer keys issued by `/api/auth/login`
+    Bearer,
+    /// No authentication
+    Open,
+    /// A separate static token sent as bearer token, see `MetricsConfigs::token`
+    Token,
+}
+
+#[derive(Serialize, Deserialize, Debug, Derivative, Clone)]
+#[derivative(Default)]
+pub struct MetricsConfigs {
+    #[derivative(Default(value = "Some(true)"))]
+    enabled: Option<bool>,
+
+    #[derivative(Default(value = "Some(MetricsAuth::Bearer)"))]
+    auth: Option<MetricsAuth>,
+
+    token: Option<String>,
+}
+
+impl MetricsConfigs {
+    pub fn from(enabled: bool, auth: MetricsAuth, token: Option<String>) -> Self {
+        Self {
+            enabled: Some(enabled),
+            auth: Some(auth),
+            token,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+    pub fn auth(&self) -> MetricsAuth {
+        self.auth.unwrap_or(MetricsAuth::Bearer)
+    }
+    pub fn token(&self) -> Option<String> {
+        self.token.clone().filter(|t| !t.is_empty())
+    }
+}
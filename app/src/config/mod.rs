@@ -1,20 +1,32 @@
 use anyhow::Result;
 use derivative::Derivative;
 use serde_derive::{Deserialize, Serialize};
-use std::fs;
+use std::{
+    fs,
+    sync::{Arc, RwLock},
+};
 use toml::value::Value;
 use utils::file::File;
 
 use crate::MainDirectory;
 
 pub mod auth;
+pub mod federation;
+pub mod live;
+pub mod logging;
+pub mod metrics;
 pub mod security;
 pub mod server;
 pub mod vnstat;
 
-use self::{auth::*, security::*, server::*, vnstat::*};
+use self::{
+    auth::*, federation::*, live::*, logging::*, metrics::*, security::*, server::*, vnstat::*,
+};
 
-#[derive(Serialize, Deserialize, Debug, Derivative)]
+/// Configs in force, see `Configs::current`
+static CURRENT: RwLock<Option<Arc<Configs>>> = RwLock::new(None);
+
+#[derive(Serialize, Deserialize, Debug, Derivative, Clone)]
 pub struct Configs {
     server: Option<ServerConfigs>,
 
@@ -22,20 +34,33 @@ pub struct Configs {
 
     vnstat: Option<VnstatConfigs>,
     security: Option<SecurityConfigs>,
+    live: Option<LiveConfigs>,
+    metrics: Option<MetricsConfigs>,
+    federation: Option<FederationConfigs>,
+    log: Option<LogConfigs>,
 }
 
 impl Configs {
+    #[allow(clippy::too_many_arguments)]
     pub fn from(
         server: Option<ServerConfigs>,
         auth: Option<AuthConfigs>,
         vnstat: Option<VnstatConfigs>,
         security: Option<SecurityConfigs>,
+        live: Option<LiveConfigs>,
+        metrics: Option<MetricsConfigs>,
+        federation: Option<FederationConfigs>,
+        log: Option<LogConfigs>,
     ) -> Self {
         Self {
             server,
             auth,
             vnstat,
             security,
+            live,
+            metrics,
+            federation,
+            log,
         }
     }
 
@@ -45,6 +70,10 @@ impl Configs {
             Some(AuthConfigs::default()),
             Some(VnstatConfigs::default()),
             Some(SecurityConfigs::default()),
+            Some(LiveConfigs::default()),
+            Some(MetricsConfigs::default()),
+            Some(FederationConfigs::default()),
+            Some(LogConfigs::default()),
         )
     }
 
@@ -58,6 +87,21 @@ impl Configs {
         Ok(toml::from_str(fs::read_to_string(path)?.as_str())?)
     }
 
+    /// Configs in force, the file is read the first time only. Changes of the file aren't
+    /// applied until they're put in force by `set_current`, e.g. when vnsd is reloaded.
+    pub fn current() -> Result<Arc<Self>> {
+        if let Some(configs) = CURRENT.read().unwrap().clone() {
+            return Ok(configs);
+        }
+        let configs = Arc::new(Self::init()?);
+        *CURRENT.write().unwrap() = Some(configs.clone());
+        Ok(configs)
+    }
+
+    pub fn set_current(configs: Self) {
+        *CURRENT.write().unwrap() = Some(Arc::new(configs));
+    }
+
     pub fn reset() -> Result<()> {
         let path = Self::get_file_path()?;
         fs::remove_file(&path)?;
@@ -65,6 +109,11 @@ impl Configs {
         Ok(())
     }
 
+    /// Overwrite the configuration file by these configs
+    pub fn save(&self) -> Result<()> {
+        File::new(Self::get_file_path()?).create(self.to_string()?)
+    }
+
     pub fn to_string(&self) -> Result<String> {
         Ok(toml::to_string(self)?)
     }
@@ -123,6 +172,21 @@ impl Configs {
     pub fn auth(&self) -> AuthConfigs {
         self.auth.clone().unwrap_or_default()
     }
+    pub fn set_auth(&mut self, auth: AuthConfigs) {
+        self.auth = Some(auth);
+    }
+    pub fn live(&self) -> LiveConfigs {
+        self.live.clone().unwrap_or_default()
+    }
+    pub fn metrics(&self) -> MetricsConfigs {
+        self.metrics.clone().unwrap_or_default()
+    }
+    pub fn federation(&self) -> FederationConfigs {
+        self.federation.clone().unwrap_or_default()
+    }
+    pub fn log(&self) -> LogConfigs {
+        self.log.clone().unwrap_or_default()
+    }
 }
 
 #[test]
@@ -157,6 +221,16 @@ pub fn test_get_prop_from_configs() {
     assert!(true)
 }
 
+#[test]
+fn test_default_configs_round_trip() {
+    // Scalars of a section must be emitted before its tables
+    let file = Configs::default().to_string().unwrap();
+    let configs: Configs = toml::from_str(&file).unwrap();
+
+    assert_eq!(configs.to_string().unwrap(), file);
+    assert_eq!(configs.server().shutdown_timeout(), 30);
+}
+
 #[test]
 pub fn test_build_configuration_file() {
     Configs::init().unwrap();
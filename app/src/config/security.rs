@@ -5,16 +5,54 @@ use serde_derive::{Deserialize, Serialize};
 pub struct SecurityConfigs {
     #[derivative(Default(value = "Some(true)"))]
     read_only: Option<bool>,
+
+    /// Failed logins and invalid keys of an IP address in `failures_window`,
+    /// after which its requests are refused by "429 Too Many Requests". 0 disables it.
+    #[derivative(Default(value = "Some(5)"))]
+    max_failures: Option<u32>,
+
+    /// Failures of an IP address in `failures_window` after which it's blocked. 0 disables it.
+    #[derivative(Default(value = "Some(10)"))]
+    block_after_failures: Option<u32>,
+
+    /// Seconds in which failures are counted
+    #[derivative(Default(value = "Some(300)"))]
+    failures_window: Option<u64>,
+
+    /// Seconds an IP address is blocked for after too many failures
+    #[derivative(Default(value = "Some(3600)"))]
+    block_duration: Option<u64>,
+
+    /// Addresses and CIDR ranges that are only allowed to connect, e.g. `["10.0.0.0/8", "::1"]`.
+    /// Everyone is allowed when it's empty.
+    #[derivative(Default(value = "Some(vec![])"))]
+    allow_list: Option<Vec<String>>,
 }
 
 impl SecurityConfigs {
     pub fn from(read_only: bool) -> Self {
         Self {
             read_only: Some(read_only),
+            ..Default::default()
         }
     }
 
     pub fn read_only(&self) -> bool {
         self.read_only.clone().unwrap_or(true)
     }
+    pub fn max_failures(&self) -> u32 {
+        self.max_failures.unwrap_or(5)
+    }
+    pub fn block_after_failures(&self) -> u32 {
+        self.block_after_failures.unwrap_or(10)
+    }
+    pub fn failures_window(&self) -> u64 {
+        self.failures_window.unwrap_or(300).max(1)
+    }
+    pub fn block_duration(&self) -> u64 {
+        self.block_duration.unwrap_or(3600).max(1)
+    }
+    pub fn allow_list(&self) -> Vec<String> {
+        self.allow_list.clone().unwrap_or_default()
+    }
 }
@@ -9,6 +9,21 @@ pub struct ServerConfigs {
 
     #[derivative(Default(value = "Some(8080)"))]
     port: Option<u16>,
+
+    /// Seconds open connections are drained for when the server is stopped, the remaining
+    /// ones are closed after it
+    #[derivative(Default(value = "Some(30)"))]
+    shutdown_timeout: Option<u64>,
+
+    /// Addresses the server listens on, it listens on `ip` and `port` when it's empty
+    #[derivative(Default(value = "Some(vec![])"))]
+    listeners: Option<Vec<ListenerConfigs>>,
+
+    #[derivative(Default(value = "Some(AdminConfigs::default())"))]
+    admin: Option<AdminConfigs>,
+
+    #[derivative(Default(value = "Some(TlsConfigs::default())"))]
+    tls: Option<TlsConfigs>,
 }
 
 impl ServerConfigs {
@@ -16,6 +31,7 @@ impl ServerConfigs {
         Self {
             ip: Some(ip.to_owned()),
             port: Some(port),
+            ..Default::default()
         }
     }
 
@@ -25,4 +41,133 @@ impl ServerConfigs {
     pub fn port(&self) -> u16 {
         self.port.unwrap_or(8080)
     }
+    /// `listeners`, or a mutating listener of `ip` and `port` when there isn't any
+    pub fn listeners(&self) -> Vec<ListenerConfigs> {
+        match self.listeners.clone().unwrap_or_default() {
+            listeners if listeners.is_empty() => {
+                vec![ListenerConfigs::tcp(&self.ip(), self.port(), true)]
+            }
+            listeners => listeners,
+        }
+    }
+    pub fn admin(&self) -> AdminConfigs {
+        self.admin.clone().unwrap_or_default()
+    }
+    pub fn tls(&self) -> TlsConfigs {
+        self.tls.clone().unwrap_or_default()
+    }
+    pub fn shutdown_timeout(&self) -> u64 {
+        self.shutdown_timeout.unwrap_or(30)
+    }
+}
+
+/// A separate server of every endpoint, while it's enabled the server of `listeners`
+/// serves the read endpoints only
+#[derive(Serialize, Deserialize, Debug, Derivative, Clone)]
+#[derivative(Default)]
+pub struct AdminConfigs {
+    #[derivative(Default(value = "Some(false)"))]
+    enabled: Option<bool>,
+
+    /// Addresses of the admin server, they should be reachable from trusted hosts only
+    #[derivative(Default(value = "Some(vec![ListenerConfigs::tcp(\"127.0.0.1\", 8081, true)])"))]
+    listeners: Option<Vec<ListenerConfigs>>,
+}
+
+impl AdminConfigs {
+    pub fn from(listeners: Vec<ListenerConfigs>) -> Self {
+        Self {
+            enabled: Some(true),
+            listeners: Some(listeners),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+    /// `listeners`, or a mutating listener of `127.0.0.1:8081` when there isn't any
+    pub fn listeners(&self) -> Vec<ListenerConfigs> {
+        match self.listeners.clone().unwrap_or_default() {
+            listeners if listeners.is_empty() => {
+                vec![ListenerConfigs::tcp("127.0.0.1", 8081, true)]
+            }
+            listeners => listeners,
+        }
+    }
+}
+
+/// An address the server listens on
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ListenerConfigs {
+    /// `<ip>:<port>`, e.g. `0.0.0.0:8080` or `[::]:8080`, or `unix:<path>` of a unix socket
+    pub address: String,
+    /// Whether requests that change vnStat (`config` and `daemon` scopes) are accepted
+    #[serde(default)]
+    pub mutating: bool,
+}
+
+impl ListenerConfigs {
+    pub fn tcp(ip: &str, port: u16, mutating: bool) -> Self {
+        Self {
+            address: match ip.contains(':') {
+                true => format!("[{ip}]:{port}"),
+                false => format!("{ip}:{port}"),
+            },
+            mutating,
+        }
+    }
+}
+
+/// HTTPS of the server, HTTP/2 is negotiated with clients that support it
+#[derive(Serialize, Deserialize, Debug, Derivative, Clone, PartialEq)]
+#[derivative(Default)]
+pub struct TlsConfigs {
+    #[derivative(Default(value = "Some(false)"))]
+    enabled: Option<bool>,
+
+    /// PEM file of the certificate chain, the server certificate first
+    #[derivative(Default(value = "Some(String::new())"))]
+    cert: Option<String>,
+
+    /// PEM file of the private key (PKCS#8, PKCS#1 or SEC1)
+    #[derivative(Default(value = "Some(String::new())"))]
+    key: Option<String>,
+
+    /// PEM file of CA certificates, clients must present a certificate signed by one of them.
+    /// Client certificates aren't requested when it's empty.
+    #[derivative(Default(value = "Some(String::new())"))]
+    client_ca: Option<String>,
+
+    /// Seconds between two checks of the certificate and key files, they're reloaded
+    /// when they change. 0 disables it.
+    #[derivative(Default(value = "Some(60)"))]
+    reload_interval: Option<u64>,
+}
+
+impl TlsConfigs {
+    pub fn from(cert: &str, key: &str, client_ca: Option<&str>) -> Self {
+        Self {
+            enabled: Some(true),
+            cert: Some(cert.to_owned()),
+            key: Some(key.to_owned()),
+            client_ca: client_ca.map(str::to_owned),
+            ..Default::default()
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+    pub fn cert(&self) -> String {
+        self.cert.clone().unwrap_or_default()
+    }
+    pub fn key(&self) -> String {
+        self.key.clone().unwrap_or_default()
+    }
+    pub fn client_ca(&self) -> Option<String> {
+        self.client_ca.clone().filter(|path| !path.is_empty())
+    }
+    pub fn reload_interval(&self) -> u64 {
+        self.reload_interval.unwrap_or(60)
+    }
 }
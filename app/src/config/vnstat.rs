@@ -6,12 +6,26 @@ use serde_derive::{Deserialize, Serialize};
 pub struct VnstatConfigs {
     #[derivative(Default(value = "Some(\"/etc/vnstat.conf\".to_owned())"))]
     config_file: Option<String>,
+
+    /// Path of vnStat database, it's taken from `DatabaseDir` of vnStat config file if it's unset
+    database_path: Option<String>,
+
+    /// Maximum number of connections opened to vnStat database
+    #[derivative(Default(value = "Some(4)"))]
+    pool_size: Option<u32>,
+
+    /// Milliseconds to wait for vnstatd when it's writing to the database
+    #[derivative(Default(value = "Some(5000)"))]
+    busy_timeout: Option<u64>,
 }
 
 impl VnstatConfigs {
-    pub fn from(config_file: &'static str) -> Self {
+    pub fn from(config_file: &'static str, database_path: Option<&'static str>) -> Self {
         Self {
             config_file: Some(config_file.to_owned()),
+            database_path: database_path.map(|p| p.to_owned()),
+            pool_size: Some(4),
+            busy_timeout: Some(5000),
         }
     }
     pub fn config_file(&self) -> String {
@@ -19,4 +33,13 @@ impl VnstatConfigs {
             .clone()
             .unwrap_or("/etc/vnstat.conf".to_owned())
     }
+    pub fn database_path(&self) -> Option<String> {
+        self.database_path.clone().filter(|p| !p.trim().is_empty())
+    }
+    pub fn pool_size(&self) -> u32 {
+        self.pool_size.unwrap_or(4).max(1)
+    }
+    pub fn busy_timeout(&self) -> u64 {
+        self.busy_timeout.unwrap_or(5000)
+    }
 }
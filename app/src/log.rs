@@ -1,5 +1,5 @@
 use env_logger::{self, fmt::Color, Builder, Target::Stdout};
-use log::Level;
+use log::{Level, LevelFilter};
 use std::io::Write;
 
 use colorful::{Colorful, RGB};
@@ -7,7 +7,7 @@ pub struct Logger;
 
 impl Logger {
     pub fn init() {
-        let mut log_builder = Builder::from_env(env_logger::Env::new().default_filter_or("info"));
+        let mut log_builder = Builder::from_env(env_logger::Env::new().default_filter_or("trace"));
         log_builder
             .format(|buf, record| {
                 let logging_style =
@@ -74,5 +74,20 @@ impl Logger {
             })
             .target(Stdout)
             .init();
+        // Everything passes the filter of the logger, so the level can be raised later
+        Self::set_level(LevelFilter::Info);
+    }
+
+    /// Write logs that are still buffered, e.g. before the process exits
+    pub fn flush() {
+        log::logger().flush();
+        let _ = std::io::stdout().flush();
+    }
+
+    /// Change the level of logs, it's ignored when `RUST_LOG` is set
+    pub fn set_level(level: LevelFilter) {
+        if std::env::var("RUST_LOG").is_err() {
+            log::set_max_level(level);
+        }
     }
 }
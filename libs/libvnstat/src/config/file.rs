@@ -0,0 +1,287 @@
+use anyhow::Result;
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// A line of vnStat configuration file
+#[derive(Debug, Clone, PartialEq)]
+enum Line {
+    /// `Key value` line, `raw` is written back as it was read until the option is changed
+    Option {
+        key: String,
+        value: String,
+        raw: String,
+    },
+    /// Comments, commented out options and blank lines are kept verbatim
+    Other(String),
+}
+
+impl Line {
+    fn parse(raw: &str) -> Self {
+        let line = raw.trim_start();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            return Self::Other(raw.to_owned());
+        }
+        let (key, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim_start();
+        let value = match rest.strip_prefix('"') {
+            // Quoted values may contain spaces, quotes are kept as vnStat writes them
+            Some(quoted) => match quoted.split_once('"') {
+                Some((value, _)) => format!("\"{value}\""),
+                None => rest.trim_end().to_owned(),
+            },
+            None => rest
+                .split(|c: char| c.is_whitespace() || c == '#')
+                .next()
+                .unwrap_or_default()
+                .to_owned(),
+        };
+        Self::Option {
+            key: key.to_owned(),
+            value,
+            raw: raw.to_owned(),
+        }
+    }
+
+    /// Whether the line is `key` commented out, e.g. `;UseUTC 0`
+    fn is_commented(&self, key: &str) -> bool {
+        match self {
+            Self::Other(raw) => raw
+                .trim_start()
+                .strip_prefix(['#', ';'])
+                .and_then(|line| line.split_whitespace().next())
+                .is_some_and(|k| k.eq_ignore_ascii_case(key)),
+            _ => false,
+        }
+    }
+}
+
+/// vnStat configuration file, edited in place without losing comments or the order of options
+#[derive(Debug, Clone, PartialEq)]
+pub struct VnStatConfigFile {
+    lines: Vec<Line>,
+    trailing_newline: bool,
+}
+
+impl VnStatConfigFile {
+    pub fn parse(content: &str) -> Self {
+        Self {
+            lines: content.lines().map(Line::parse).collect(),
+            trailing_newline: content.is_empty() || content.ends_with('\n'),
+        }
+    }
+
+    pub fn read(path: &str) -> Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    /// Value of `key` as written in the file, vnStat uses the last occurrence of an option
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter().rev().find_map(|line| match line {
+            Line::Option { key: k, value, .. } if k.eq_ignore_ascii_case(key) => {
+                Some(value.as_str())
+            }
+            _ => None,
+        })
+    }
+
+    pub fn props(&self) -> HashMap<String, String> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                Line::Option { key, value, .. } => Some((key.to_owned(), value.to_owned())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Set `key` to an already formatted `value`, its previous value is returned.
+    ///
+    /// Existing occurrences are replaced, otherwise the option is added below its
+    /// commented out default, or at the end of the file.
+    pub fn set(&mut self, key: &str, value: &str) -> Option<String> {
+        let previous = self.get(key).map(str::to_owned);
+        if previous.is_some() {
+            for line in self.lines.iter_mut() {
+                if let Line::Option {
+                    key: k,
+                    value: v,
+                    raw,
+                } = line
+                {
+                    if k.eq_ignore_ascii_case(key) {
+                        *v = value.to_owned();
+                        *raw = format!("{k} {value}");
+                    }
+                }
+            }
+            return previous;
+        }
+
+        let line = Line::Option {
+            key: key.to_owned(),
+            value: value.to_owned(),
+            raw: format!("{key} {value}"),
+        };
+        match self.lines.iter().rposition(|l| l.is_commented(key)) {
+            Some(i) => self.lines.insert(i + 1, line),
+            None => self.lines.push(line),
+        }
+        None
+    }
+
+    /// Remove every occurrence of `key` so vnStat uses its default value, its previous value is returned.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let previous = self.get(key).map(str::to_owned);
+        self.lines.retain(
+            |line| !matches!(line, Line::Option { key: k, .. } if k.eq_ignore_ascii_case(key)),
+        );
+        previous
+    }
+
+    /// Replace the file at `path` atomically, the previous content is kept in `<path>.bak`
+    pub fn write(&self, path: &str) -> Result<()> {
+        let path = Path::new(path);
+        let backup = sibling(path, "bak");
+        let tmp = sibling(path, "tmp");
+
+        let permissions = match path.exists() {
+            true => {
+                fs::copy(path, &backup)?;
+                Some(fs::metadata(path)?.permissions())
+            }
+            false => None,
+        };
+
+        let written = (|| -> Result<()> {
+            let mut file = fs::File::create(&tmp)?;
+            file.write_all(self.to_string().as_bytes())?;
+            file.sync_all()?;
+            if let Some(permissions) = permissions {
+                fs::set_permissions(&tmp, permissions)?;
+            }
+            fs::rename(&tmp, path)?;
+            Ok(())
+        })();
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        written
+    }
+}
+
+impl fmt::Display for VnStatConfigFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            match line {
+                Line::Option { raw, .. } | Line::Other(raw) => write!(f, "{raw}")?,
+            }
+        }
+        if self.trailing_newline && !self.lines.is_empty() {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    const CONTENT: &str = "# vnStat configuration file\n\
+        \n\
+        Interface \"\"\n\
+        DatabaseDir \"/var/lib/vnstat dir\"\n\
+        \n\
+        # how many days should be shown in list\n\
+        ;ListDays 30\n\
+        List5Mins     24   # five minutes\n\
+        UseUTC 0\n";
+
+    #[test]
+    fn parse_config_file() {
+        let config = VnStatConfigFile::parse(CONTENT);
+        assert_eq!(config.get("DatabaseDir"), Some("\"/var/lib/vnstat dir\""));
+        assert_eq!(config.get("list5mins"), Some("24"));
+        assert_eq!(config.get("ListDays"), None);
+        assert_eq!(config.props().len(), 4);
+        assert_eq!(config.to_string(), CONTENT);
+    }
+
+    #[test]
+    fn tokenize_config_lines() {
+        let config = VnStatConfigFile::parse(
+            "   Interface \"eth0\"   # indented\n\
+            DayFormat\t\"%Y-%m-%d #%H\"\n\
+            UseUTC 1# no space before comment\n\
+            Locale\n\
+            TopFormat \"unterminated\n",
+        );
+        assert_eq!(config.get("Interface"), Some("\"eth0\""));
+        assert_eq!(config.get("DayFormat"), Some("\"%Y-%m-%d #%H\""));
+        assert_eq!(config.get("UseUTC"), Some("1"));
+        // A key without a value
+        assert_eq!(config.get("Locale"), Some(""));
+        assert_eq!(config.get("TopFormat"), Some("\"unterminated"));
+    }
+
+    #[test]
+    fn set_options_in_config_file() {
+        let mut config = VnStatConfigFile::parse(CONTENT);
+        assert_eq!(config.set("UseUTC", "1"), Some("0".to_owned()));
+        assert_eq!(config.set("ListDays", "7"), None);
+        config.set("MonthRotate", "15");
+        // Values with slashes and spaces are written as they are
+        config.set("DatabaseDir", "\"/tmp/vn stat\"");
+        assert_eq!(config.remove("Interface"), Some("\"\"".to_owned()));
+
+        assert_eq!(
+            config.to_string(),
+            "# vnStat configuration file\n\
+            \n\
+            DatabaseDir \"/tmp/vn stat\"\n\
+            \n\
+            # how many days should be shown in list\n\
+            ;ListDays 30\n\
+            ListDays 7\n\
+            List5Mins     24   # five minutes\n\
+            UseUTC 1\n\
+            MonthRotate 15\n"
+        );
+    }
+
+    #[test]
+    fn write_config_file_with_backup() {
+        let path = temp_dir().join("vnstat-config-file-test.conf");
+        let path = path.to_str().unwrap();
+        fs::write(path, CONTENT).unwrap();
+
+        let mut config = VnStatConfigFile::read(path).unwrap();
+        config.set("UseUTC", "1");
+        config.write(path).unwrap();
+
+        assert_eq!(
+            VnStatConfigFile::read(path).unwrap().get("UseUTC"),
+            Some("1")
+        );
+        assert_eq!(fs::read_to_string(format!("{path}.bak")).unwrap(), CONTENT);
+        assert!(!Path::new(&format!("{path}.tmp")).exists());
+
+        fs::remove_file(path).unwrap();
+        fs::remove_file(format!("{path}.bak")).unwrap();
+    }
+}
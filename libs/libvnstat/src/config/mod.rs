@@ -0,0 +1,189 @@
+mod file;
+pub mod options;
+
+pub use file::VnStatConfigFile;
+pub use options::ConfigValue;
+
+use app::Configs;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind::InvalidInput},
+};
+
+#[derive(Debug, Clone)]
+pub struct VnStatConfig {
+    path: String,
+}
+
+impl VnStatConfig {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_owned(),
+        }
+    }
+
+    /// Options set in the file with values typed by their kind
+    pub fn get_props(&self) -> Result<HashMap<String, ConfigValue>> {
+        Ok(VnStatConfigFile::read(&self.path)?
+            .props()
+            .into_iter()
+            .map(|(key, value)| {
+                let value = ConfigValue::parse(&key, &value);
+                (key, value)
+            })
+            .collect())
+    }
+
+    pub fn set_prop(&self, key: &str, value: &str) -> Result<Vec<ConfigChange>> {
+        self.set_props(&[(key, value)])
+    }
+
+    /// Validate every option before writing them at once,
+    /// the file is left untouched when one of them is invalid.
+    pub fn set_props(&self, props: &[(&str, &str)]) -> Result<Vec<ConfigChange>> {
+        let props = props
+            .iter()
+            .map(|(key, value)| (*key, Some(*value)))
+            .collect::<Vec<_>>();
+        self.edit(&props)?.save()
+    }
+
+    /// Validate and apply `props` in memory, a `None` value removes the option
+    /// so vnStat falls back to its default. Nothing is written until [`ConfigEdit::save`].
+    pub fn edit(&self, props: &[(&str, Option<&str>)]) -> Result<ConfigEdit> {
+        let mut file = VnStatConfigFile::read(&self.path)?;
+        let mut changes: Vec<ConfigChange> = vec![];
+        for (key, value) in props {
+            let (key, new_value) = match value {
+                Some(value) => {
+                    let (key, value) = options::format(key, value)?;
+                    (key, Some(value))
+                }
+                None => match options::find(key) {
+                    Some((key, _)) => (key, None),
+                    None => {
+                        return Err(anyhow!(Error::new(
+                            InvalidInput,
+                            format!("unknown option '{key}'")
+                        )))
+                    }
+                },
+            };
+            let old_value = match &new_value {
+                Some(value) => file.set(&key, value),
+                None => file.remove(&key),
+            };
+            // Keep the value the option had before the batch when it's edited twice
+            match changes.iter_mut().find(|c| c.prop == key) {
+                Some(change) => change.new_value = new_value,
+                None => changes.push(ConfigChange {
+                    prop: key,
+                    old_value,
+                    new_value,
+                }),
+            }
+        }
+        changes.retain(|c| c.old_value != c.new_value);
+        Ok(ConfigEdit {
+            path: self.path.clone(),
+            file,
+            changes,
+        })
+    }
+}
+
+/// Change of an option, values are formatted as they're written in the file
+/// and `None` means the option isn't set.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigChange {
+    pub prop: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+/// Validated edits of the configuration file
+#[derive(Debug, Clone)]
+pub struct ConfigEdit {
+    path: String,
+    file: VnStatConfigFile,
+    changes: Vec<ConfigChange>,
+}
+
+impl ConfigEdit {
+    pub fn changes(&self) -> &[ConfigChange] {
+        &self.changes
+    }
+
+    /// Write the file atomically, it's left untouched when nothing has changed
+    pub fn save(self) -> Result<Vec<ConfigChange>> {
+        if !self.changes.is_empty() {
+            self.file.write(&self.path)?;
+        }
+        Ok(self.changes)
+    }
+}
+
+impl Default for VnStatConfig {
+    fn default() -> Self {
+        Self {
+            path: Configs::init().unwrap().vnstat().config_file(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env::temp_dir, fs};
+
+    #[test]
+    fn read_vnstat_config_file() {
+        // use serde_json::{json, to_string_pretty};
+        let props = VnStatConfig::default().get_props().unwrap();
+        println!("{:#?}", props);
+        assert!(true)
+    }
+
+    #[test]
+    fn edit_prop_in_vnstat_config_file() {
+        let test_config_file = temp_dir().join("vnstat.test.conf");
+        let test_config_file = test_config_file.to_str().unwrap();
+        fs::write(test_config_file, "List5Mins 24\nUseUTC 0\n").unwrap();
+        let config = VnStatConfig::new(test_config_file);
+
+        config.set_prop("list5mins", "99").unwrap();
+        let changes = config
+            .set_props(&[("UseUTC", "1"), ("Interface", "eth0"), ("UseUTC", "0")])
+            .unwrap();
+        // `UseUTC` is back to its previous value
+        assert_eq!(
+            changes,
+            vec![ConfigChange {
+                prop: "Interface".to_owned(),
+                old_value: None,
+                new_value: Some("\"eth0\"".to_owned()),
+            }]
+        );
+        let props = config.get_props().unwrap();
+        assert_eq!(props.get("List5Mins"), Some(&ConfigValue::Integer(99)));
+        assert_eq!(
+            props.get("Interface"),
+            Some(&ConfigValue::Text("eth0".to_owned()))
+        );
+
+        config.edit(&[("interface", None)]).unwrap().save().unwrap();
+        assert!(!config.get_props().unwrap().contains_key("Interface"));
+
+        // Nothing is written when one of the options is invalid
+        assert!(config
+            .set_props(&[("ListDays", "7"), ("MonthRotate", "99")])
+            .is_err());
+        assert!(!config.get_props().unwrap().contains_key("ListDays"));
+
+        fs::remove_file(test_config_file).unwrap();
+        fs::remove_file(format!("{test_config_file}.bak")).unwrap();
+    }
+}
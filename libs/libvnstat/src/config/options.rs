@@ -0,0 +1,296 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    io::{Error, ErrorKind::InvalidInput},
+};
+
+/// Type of values accepted by a vnStat option
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptionKind {
+    /// Quoted string, e.g. `Interface "eth0"`
+    Text,
+    /// `0` or `1`
+    Boolean,
+    Integer {
+        min: i64,
+        max: i64,
+    },
+}
+
+use OptionKind::*;
+
+const fn range(min: i64, max: i64) -> OptionKind {
+    Integer { min, max }
+}
+
+/// Options of vnStat 2.x configuration file, see `man vnstat.conf`
+pub const OPTIONS: &[(&str, OptionKind)] = &[
+    // vnstat
+    ("Interface", Text),
+    ("DatabaseDir", Text),
+    ("Locale", Text),
+    ("MonthRotate", range(1, 28)),
+    ("MonthRotateAffectsYears", Boolean),
+    ("DayFormat", Text),
+    ("MonthFormat", Text),
+    ("TopFormat", Text),
+    ("RXCharacter", Text),
+    ("TXCharacter", Text),
+    ("RXHourCharacter", Text),
+    ("TXHourCharacter", Text),
+    ("UnitMode", range(0, 2)),
+    ("RateUnit", Boolean),
+    ("RateUnitMode", Boolean),
+    ("OutputStyle", range(0, 4)),
+    ("EstimateBarVisible", Boolean),
+    ("DefaultDecimals", range(0, 2)),
+    ("HourlyDecimals", range(0, 2)),
+    ("HourlySectionStyle", range(0, 3)),
+    ("Sampletime", range(0, i64::MAX)),
+    ("List5Mins", range(-1, i64::MAX)),
+    ("ListHours", range(-1, i64::MAX)),
+    ("ListDays", range(-1, i64::MAX)),
+    ("ListMonths", range(-1, i64::MAX)),
+    ("ListYears", range(-1, i64::MAX)),
+    ("ListTop", range(-1, i64::MAX)),
+    ("ListJsonXml", range(-1, i64::MAX)),
+    ("QueryMode", range(0, 1)),
+    // vnstatd
+    ("DaemonUser", Text),
+    ("DaemonGroup", Text),
+    ("BandwidthDetection", Boolean),
+    ("MaxBandwidth", range(0, i64::MAX)),
+    ("5MinuteHours", range(-1, i64::MAX)),
+    ("HourlyDays", range(-1, i64::MAX)),
+    ("DailyDays", range(-1, i64::MAX)),
+    ("MonthlyMonths", range(-1, i64::MAX)),
+    ("YearlyYears", range(-1, i64::MAX)),
+    ("TopDayEntries", range(-1, i64::MAX)),
+    ("UpdateInterval", range(1, i64::MAX)),
+    ("PollInterval", range(2, 60)),
+    ("SaveInterval", range(1, 60)),
+    ("OfflineSaveInterval", range(1, 60)),
+    ("RescanDatabaseOnSave", Boolean),
+    ("AlwaysAddNewInterfaces", Boolean),
+    ("InterfaceMatchMethod", range(0, 3)),
+    ("SaveOnStatusChange", Boolean),
+    ("UseLogging", range(0, 2)),
+    ("CreateDirs", range(0, 2)),
+    ("UpdateFileOwner", Boolean),
+    ("LogFile", Text),
+    ("PidFile", Text),
+    ("64bitInterfaceCounters", range(-2, 1)),
+    ("DatabaseWriteAheadLogging", Boolean),
+    ("DatabaseSynchronous", range(-1, 3)),
+    ("UseUTC", Boolean),
+    ("TrafficlessEntries", Boolean),
+    ("TimeSyncWait", range(0, 60)),
+    ("BandwidthDetectionInterval", range(0, 30)),
+    ("CheckDiskSpace", Boolean),
+    ("BootVariation", range(0, 300)),
+    // vnstati
+    ("HeaderFormat", Text),
+    ("HourlyRate", Boolean),
+    ("SummaryRate", Boolean),
+    ("TransparentBg", Boolean),
+    ("CornerRadius", range(0, 20)),
+    ("LargeFonts", Boolean),
+    ("LineSpacingAdjustment", range(-5, 10)),
+    ("ImageScale", range(50, 500)),
+    ("5MinuteGraphResultCount", range(0, i64::MAX)),
+    ("5MinuteGraphHeight", range(0, i64::MAX)),
+    ("HourlyGraphMode", Boolean),
+    ("SummaryGraph", Boolean),
+    ("SummaryLayout", range(0, 1)),
+    ("EstimateStyle", range(0, 2)),
+    ("BarColumnShowsRate", Boolean),
+    ("CBackground", Text),
+    ("CEdge", Text),
+    ("CHeader", Text),
+    ("CHeaderTitle", Text),
+    ("CHeaderDate", Text),
+    ("CText", Text),
+    ("CLine", Text),
+    ("CLineL", Text),
+    ("CRx", Text),
+    ("CTx", Text),
+    ("CRxD", Text),
+    ("CTxD", Text),
+];
+
+/// Prefix of per interface `MaxBW<interface>` options
+const MAX_BANDWIDTH_PREFIX: &str = "MaxBW";
+
+/// Name (as spelled by vnStat) and kind of option `key`, keys are case insensitive
+pub fn find(key: &str) -> Option<(String, OptionKind)> {
+    if let Some((name, kind)) = OPTIONS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+    {
+        return Some((name.to_string(), *kind));
+    }
+    match strip_prefix_ignore_case(key, MAX_BANDWIDTH_PREFIX) {
+        Some(interface) if !interface.is_empty() && !interface.contains(char::is_whitespace) => {
+            Some((
+                format!("{MAX_BANDWIDTH_PREFIX}{interface}"),
+                range(0, i64::MAX),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Validate `value` of option `key` and format it as it's written in the configuration file
+pub fn format(key: &str, value: &str) -> Result<(String, String)> {
+    let invalid = |details: String| anyhow!(Error::new(InvalidInput, details));
+
+    let (name, kind) = find(key).ok_or_else(|| invalid(format!("unknown option '{key}'")))?;
+    let value = value.trim();
+    let value = match kind {
+        Text => {
+            let text = unquote(value).unwrap_or(value);
+            if text.contains(['"', '\n', '\r']) {
+                return Err(invalid(format!(
+                    "'{name}' can't contain quotes or line breaks"
+                )));
+            }
+            format!("\"{text}\"")
+        }
+        Boolean => match value.to_lowercase().as_str() {
+            "1" | "true" | "yes" => "1".to_owned(),
+            "0" | "false" | "no" => "0".to_owned(),
+            _ => return Err(invalid(format!("'{name}' must be either 0 or 1"))),
+        },
+        Integer { min, max } => match value.parse::<i64>() {
+            Ok(number) if (min..=max).contains(&number) => number.to_string(),
+            _ if max == i64::MAX => {
+                return Err(invalid(format!("'{name}' must be an integer >= {min}")))
+            }
+            _ => {
+                return Err(invalid(format!(
+                    "'{name}' must be an integer between {min} and {max}"
+                )))
+            }
+        },
+    };
+    Ok((name, value))
+}
+
+/// Value of an option typed by its kind, text values are unquoted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ConfigValue {
+    Boolean(bool),
+    Integer(i64),
+    Text(String),
+}
+
+impl ConfigValue {
+    /// Type `raw` value of option `key` as it's written in the file,
+    /// values that don't match the kind of their option are kept as text.
+    pub fn parse(key: &str, raw: &str) -> Self {
+        let raw = raw.trim();
+        let unquoted = unquote(raw);
+        let text = unquoted.unwrap_or(raw);
+        match find(key).map(|(_, kind)| kind) {
+            Some(Boolean) => match text {
+                "0" => Self::Boolean(false),
+                "1" => Self::Boolean(true),
+                _ => Self::Text(text.to_owned()),
+            },
+            Some(Integer { .. }) => text
+                .parse()
+                .map(Self::Integer)
+                .unwrap_or_else(|_| Self::Text(text.to_owned())),
+            // Options unknown to this version are typed by their looks
+            None if unquoted.is_none() => text
+                .parse()
+                .map(Self::Integer)
+                .unwrap_or_else(|_| Self::Text(text.to_owned())),
+            Some(Text) | None => Self::Text(text.to_owned()),
+        }
+    }
+}
+
+/// Formatted as it's accepted by [`format`]
+impl fmt::Display for ConfigValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Boolean(value) => write!(f, "{}", *value as u8),
+            Self::Integer(value) => write!(f, "{value}"),
+            Self::Text(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// `s` without `prefix`, which is matched case insensitively
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let (head, tail) = s.split_at_checked(prefix.len())?;
+    head.eq_ignore_ascii_case(prefix).then_some(tail)
+}
+
+/// `value` without its surrounding quotes, an unterminated quote is dropped too.
+/// `None` when it isn't quoted.
+fn unquote(value: &str) -> Option<&str> {
+    let value = value.strip_prefix('"')?;
+    Some(value.strip_suffix('"').unwrap_or(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_option_values() {
+        assert_eq!(
+            format("interface", "eth0").unwrap(),
+            ("Interface".to_owned(), "\"eth0\"".to_owned())
+        );
+        assert_eq!(
+            format("DatabaseDir", "\"/var/lib/vnstat dir\"").unwrap().1,
+            "\"/var/lib/vnstat dir\""
+        );
+        assert_eq!(format("UseUTC", "true").unwrap().1, "1");
+        assert_eq!(format("MonthRotate", " 15 ").unwrap().1, "15");
+        assert_eq!(format("maxbweth0", "1000").unwrap().0, "MaxBWeth0");
+
+        assert!(format("NotAnOption", "1").is_err());
+        assert!(format("MonthRotate", "29").is_err());
+        assert!(format("List5Mins", "many").is_err());
+        assert!(format("UseUTC", "2").is_err());
+        assert!(format("Interface", "eth0\" \nDatabaseDir \"/tmp").is_err());
+        assert!(format("MaxBW", "1").is_err());
+    }
+
+    #[test]
+    fn type_option_values() {
+        use ConfigValue::*;
+
+        assert_eq!(
+            ConfigValue::parse("Interface", "\"eth0\""),
+            Text("eth0".to_owned())
+        );
+        assert_eq!(
+            ConfigValue::parse("DayFormat", "\"%Y-%m-%d\""),
+            Text("%Y-%m-%d".to_owned())
+        );
+        assert_eq!(ConfigValue::parse("UseUTC", "1"), Boolean(true));
+        assert_eq!(ConfigValue::parse("HourlyDays", "-1"), Integer(-1));
+        assert_eq!(ConfigValue::parse("MaxBWeth0", "1000"), Integer(1000));
+        // Values that don't match their kind and unknown options
+        assert_eq!(ConfigValue::parse("UseUTC", "yes"), Text("yes".to_owned()));
+        assert_eq!(
+            ConfigValue::parse("Interface", "\"eth0"),
+            Text("eth0".to_owned())
+        );
+        assert_eq!(ConfigValue::parse("NewOption", "15"), Integer(15));
+        assert_eq!(
+            ConfigValue::parse("NewOption", "\"15\""),
+            Text("15".to_owned())
+        );
+        assert_eq!(ConfigValue::parse("Locale", ""), Text(String::new()));
+
+        assert_eq!(Boolean(true).to_string(), "1");
+    }
+}
@@ -1,6 +1,13 @@
 pub mod models;
+pub mod pool;
+pub mod schema;
 
+pub use pool::{ConnectionOptions, VnStatPool};
+pub use schema::{VnStatSchema, SUPPORTED_DATABASE_VERSION};
+
+use super::VnStatConfig;
 use anyhow::{anyhow, Result};
+use app::Configs;
 use core::fmt;
 use std::io::{
     Error,
@@ -14,9 +21,12 @@ use diesel::{
     RunQueryDsl,
 };
 const DEFAULT_VN_STAT_DATABASE_PATH: &str = "/var/lib/vnstat/vnstat.db";
+/// File name of the database inside vnStat `DatabaseDir`
+const VN_STAT_DATABASE_FILE: &str = "vnstat.db";
 pub struct VnStatDatabase {
     pub path: String,
     pub conn: Option<SqliteConnection>,
+    pub schema: Option<VnStatSchema>,
 }
 
 impl VnStatDatabase {
@@ -30,40 +40,71 @@ impl VnStatDatabase {
             true => Ok(Self {
                 path: path.to_owned(),
                 conn: None,
+                schema: None,
             }),
         }
     }
+    /// Database at the configured path, see `VnStatDatabase::path()`
     pub fn default() -> Result<Self> {
-        if !Path::new(DEFAULT_VN_STAT_DATABASE_PATH).exists() {
-            return Err(anyhow!(Error::new(NotFound,format!("Default VnStatDatabase file ({}) is not found, You should create sqlite db file in default vnStat VnStatDatabase path.", DEFAULT_VN_STAT_DATABASE_PATH))));
+        let path = Self::path();
+        if !Path::new(&path).exists() {
+            return Err(anyhow!(Error::new(NotFound,format!("VnStatDatabase file ({}) is not found, set 'vnstat.database_path' in configs or 'DatabaseDir' in vnStat config file.", path))));
         }
-        match Path::new(DEFAULT_VN_STAT_DATABASE_PATH).exists() {
-            false => Err(anyhow!(Error::new(
-                NotFound,
-                format!(
-                    "VnStatDatabase file [{}] is not found",
-                    DEFAULT_VN_STAT_DATABASE_PATH
-                ),
-            ))),
-            true => Ok(Self {
-                path: DEFAULT_VN_STAT_DATABASE_PATH.to_owned(),
-                conn: None,
-            }),
+        Ok(Self {
+            path,
+            conn: None,
+            schema: None,
+        })
+    }
+
+    /// Path of vnStat database, taken from the first available of:
+    /// `vnstat.database_path` in configs, `DatabaseDir` in vnStat config file,
+    /// then the default path of vnStat.
+    pub fn path() -> String {
+        let configs = match Configs::init() {
+            Ok(configs) => configs.vnstat(),
+            Err(_) => return DEFAULT_VN_STAT_DATABASE_PATH.to_owned(),
+        };
+        if let Some(path) = configs.database_path() {
+            return path;
         }
+        VnStatConfig::new(&configs.config_file())
+            .get_props()
+            .ok()
+            .and_then(|props| props.get("DatabaseDir").map(|dir| dir.to_string()))
+            .map(|dir| dir.trim_end_matches('/').to_owned())
+            .filter(|dir| !dir.is_empty())
+            .map(|dir| format!("{dir}/{VN_STAT_DATABASE_FILE}"))
+            .unwrap_or(DEFAULT_VN_STAT_DATABASE_PATH.to_owned())
     }
 
+    /// Connect to the database after probing its schema
     pub fn connect(&mut self) -> Result<&mut Self> {
-        self.conn = Some(SqliteConnection::establish(self.path.as_str())?);
+        let conn = SqliteConnection::establish(self.path.as_str())?;
+        ConnectionOptions {
+            read_only: true,
+            busy_timeout: pool::DEFAULT_BUSY_TIMEOUT,
+        }
+        .apply(&conn)?;
+        self.schema = Some(VnStatSchema::probe(&conn)?);
+        self.conn = Some(conn);
         Ok(self)
     }
 
     pub fn select_table<T>(&mut self, table: &str) -> Result<Vec<T>>
+    where
+        T: diesel::deserialize::QueryableByName<diesel::sqlite::Sqlite>,
+    {
+        self.query::<T>(&format!("SELECT * from {}", table))
+    }
+
+    /// Run a raw `SELECT` statement and load its rows
+    pub fn query<T>(&mut self, query: &str) -> Result<Vec<T>>
     where
         T: diesel::deserialize::QueryableByName<diesel::sqlite::Sqlite>,
     {
         match self.conn.is_some() {
-            true => Ok(sql_query(format!("SELECT * from {}", table))
-                .load(&*self.conn.as_ref().unwrap())?),
+            true => Ok(sql_query(query).load(&*self.conn.as_ref().unwrap())?),
             false => Err(anyhow!(Error::new(
                 Interrupted,
                 "vnStat Database wasn't connected",
@@ -91,10 +132,7 @@ mod tests {
     use std::fs;
     #[test]
     fn new_database_with_default_path() -> Result<()> {
-        assert_eq!(
-            VnStatDatabase::default()?.path,
-            DEFAULT_VN_STAT_DATABASE_PATH.to_owned()
-        );
+        assert_eq!(VnStatDatabase::default()?.path, VnStatDatabase::path());
         Ok(())
     }
 
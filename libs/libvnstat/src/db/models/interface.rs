@@ -1,24 +1,28 @@
-use diesel::sql_types::{Date, Integer, Text};
+use chrono::NaiveDateTime;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text, Timestamp};
 use serde::Serialize;
 
+/// Row of vnStat 2.x `interface` table
 #[derive(Debug, QueryableByName, Serialize, Clone)]
 pub struct Interface {
     #[sql_type = "Integer"]
     pub id: i32,
     #[sql_type = "Text"]
     pub name: String,
+    #[sql_type = "Nullable<Text>"]
+    pub alias: Option<String>,
     #[sql_type = "Integer"]
     pub active: i32,
-    #[sql_type = "Date"]
-    pub created: String,
-    #[sql_type = "Date"]
-    pub updated: String,
-    #[sql_type = "Integer"]
-    pub rxcounter: i32,
-    #[sql_type = "Integer"]
-    pub txcounter: i32,
-    #[sql_type = "Integer"]
-    pub rxtotal: i32,
-    #[sql_type = "Integer"]
-    pub txtotal: i32,
+    #[sql_type = "Timestamp"]
+    pub created: NaiveDateTime,
+    #[sql_type = "Timestamp"]
+    pub updated: NaiveDateTime,
+    #[sql_type = "BigInt"]
+    pub rxcounter: i64,
+    #[sql_type = "BigInt"]
+    pub txcounter: i64,
+    #[sql_type = "BigInt"]
+    pub rxtotal: i64,
+    #[sql_type = "BigInt"]
+    pub txtotal: i64,
 }
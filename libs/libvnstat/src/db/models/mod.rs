@@ -5,4 +5,4 @@ pub mod traffic;
 
 pub use info::Info;
 pub use interface::Interface;
-pub use traffic::Traffic;
+pub use traffic::{InterfaceTraffic, Traffic};
@@ -14,3 +14,13 @@ pub struct Traffic {
     #[sql_type = "BigInt"]
     pub tx: i64,
 }
+
+/// Traffic row annotated with the name of its interface
+#[derive(Debug, QueryableByName, Serialize, Clone)]
+pub struct InterfaceTraffic {
+    #[sql_type = "Text"]
+    pub interface_name: String,
+    #[diesel(embed)]
+    #[serde(flatten)]
+    pub traffic: Traffic,
+}
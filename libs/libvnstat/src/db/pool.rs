@@ -0,0 +1,130 @@
+use super::VnStatSchema;
+use anyhow::{anyhow, Result};
+use diesel::{
+    connection::SimpleConnection,
+    dsl::sql_query,
+    r2d2::{self, ConnectionManager, CustomizeConnection, Pool, PooledConnection},
+    RunQueryDsl, SqliteConnection,
+};
+use once_cell::sync::OnceCell;
+use std::{
+    io::{Error, ErrorKind::NotFound},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+pub const DEFAULT_POOL_SIZE: u32 = 4;
+pub const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Options applied on every connection taken from a pool
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionOptions {
+    /// Reject every statement that changes the database (`PRAGMA query_only`)
+    pub read_only: bool,
+    /// How long to wait for a lock held by another connection (e.g. vnstatd writer)
+    pub busy_timeout: Duration,
+}
+
+impl ConnectionOptions {
+    pub fn apply(&self, conn: &SqliteConnection) -> diesel::QueryResult<()> {
+        conn.batch_execute(&format!(
+            "PRAGMA query_only = {}; PRAGMA busy_timeout = {};",
+            self.read_only as u8,
+            self.busy_timeout.as_millis()
+        ))
+    }
+}
+
+impl CustomizeConnection<SqliteConnection, r2d2::Error> for ConnectionOptions {
+    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), r2d2::Error> {
+        self.apply(conn).map_err(r2d2::Error::QueryError)
+    }
+}
+
+/// Shared pool of read-only connections to the vnStat database
+#[derive(Clone)]
+pub struct VnStatPool {
+    path: String,
+    pool: Pool<ConnectionManager<SqliteConnection>>,
+    schema: Arc<OnceCell<VnStatSchema>>,
+}
+
+impl VnStatPool {
+    /// Connections are opened on demand, so the pool can be created before vnstatd creates its database.
+    pub fn new(path: &str, size: u32, busy_timeout: Duration) -> Self {
+        Self {
+            path: path.to_owned(),
+            pool: Pool::builder()
+                .max_size(size.max(1))
+                .min_idle(Some(0))
+                .connection_customizer(Box::new(ConnectionOptions {
+                    read_only: true,
+                    busy_timeout,
+                }))
+                .build_unchecked(ConnectionManager::new(path)),
+            schema: Arc::new(OnceCell::new()),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get(&self) -> Result<PooledConnection<ConnectionManager<SqliteConnection>>> {
+        // SQLite would create an empty database instead of failing
+        if !Path::new(&self.path).exists() {
+            return Err(anyhow!(Error::new(
+                NotFound,
+                format!("VnStatDatabase file [{}] is not found", self.path),
+            )));
+        }
+        Ok(self.pool.get()?)
+    }
+
+    /// Schema of the database, probed once by the first successful call
+    pub fn schema(&self) -> Result<VnStatSchema> {
+        Ok(*self
+            .schema
+            .get_or_try_init(|| VnStatSchema::probe(&*self.get()?))?)
+    }
+
+    /// Run a raw `SELECT` statement and load its rows
+    pub fn query<T>(&self, query: &str) -> Result<Vec<T>>
+    where
+        T: diesel::deserialize::QueryableByName<diesel::sqlite::Sqlite>,
+    {
+        self.schema()?;
+        Ok(sql_query(query).load(&*self.get()?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::{Connection, RunQueryDsl};
+    use std::{env::temp_dir, fs};
+
+    #[test]
+    fn pooled_connections_are_read_only() {
+        let path = temp_dir().join("vnstat-pool-test.db");
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+        let conn = SqliteConnection::establish(path).unwrap();
+        conn.batch_execute(
+            "CREATE TABLE info(id INTEGER PRIMARY KEY, name TEXT, value TEXT);
+            INSERT INTO info(name, value) VALUES ('dbversion', '1');
+            CREATE TABLE interface(id INTEGER PRIMARY KEY, name TEXT);",
+        )
+        .unwrap();
+
+        let pool = VnStatPool::new(path, 2, DEFAULT_BUSY_TIMEOUT);
+        assert_eq!(pool.schema().unwrap().version, 1);
+        assert!(sql_query("INSERT INTO interface(name) VALUES ('eth0')")
+            .execute(&*pool.get().unwrap())
+            .is_err());
+
+        fs::remove_file(path).unwrap();
+        assert!(pool.get().is_err());
+    }
+}
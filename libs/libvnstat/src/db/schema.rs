@@ -0,0 +1,138 @@
+use anyhow::{anyhow, Result};
+use diesel::{
+    dsl::sql_query,
+    sql_types::{BigInt, Text},
+    RunQueryDsl, SqliteConnection,
+};
+use std::io::{Error, ErrorKind::InvalidData};
+
+/// Latest `dbversion` of vnStat databases that can be read
+pub const SUPPORTED_DATABASE_VERSION: i32 = 1;
+
+/// Layout of a vnStat database, probed before querying it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VnStatSchema {
+    pub version: i32,
+    /// `interface.alias` column is missing in databases created by early 2.x releases
+    pub has_alias: bool,
+}
+
+#[derive(QueryableByName)]
+struct Value {
+    #[sql_type = "Text"]
+    value: String,
+}
+
+#[derive(QueryableByName)]
+struct Count {
+    #[sql_type = "BigInt"]
+    count: i64,
+}
+
+impl VnStatSchema {
+    /// Refuse databases that weren't created by vnStat 2.x or were created by
+    /// newer releases with an unknown layout.
+    pub fn probe(conn: &SqliteConnection) -> Result<Self> {
+        let version = sql_query("SELECT value FROM info WHERE name = 'dbversion'")
+            .load::<Value>(conn)
+            .ok()
+            .and_then(|v| v.into_iter().next())
+            .ok_or_else(|| {
+                anyhow!(Error::new(
+                    InvalidData,
+                    "vnStat database has no 'dbversion', only databases of vnStat 2.x are supported",
+                ))
+            })?
+            .value;
+        let version = version.trim().parse::<i32>().map_err(|_| {
+            anyhow!(Error::new(
+                InvalidData,
+                format!("invalid vnStat database version '{version}'"),
+            ))
+        })?;
+
+        if version > SUPPORTED_DATABASE_VERSION {
+            return Err(anyhow!(Error::new(
+                InvalidData,
+                format!(
+                    "vnStat database version {version} isn't supported, latest supported version is {SUPPORTED_DATABASE_VERSION}"
+                ),
+            )));
+        }
+
+        let has_alias = sql_query(
+            "SELECT COUNT(*) AS count FROM pragma_table_info('interface') WHERE name = 'alias'",
+        )
+        .load::<Count>(conn)?
+        .first()
+        .map(|c| c.count > 0)
+        .unwrap_or(false);
+
+        Ok(Self { version, has_alias })
+    }
+
+    /// Columns of `interface` table in the order of `models::Interface`
+    pub fn interface_columns(&self) -> String {
+        format!(
+            "id, name, {} AS alias, active, created, updated, rxcounter, txcounter, rxtotal, txtotal",
+            match self.has_alias {
+                true => "alias",
+                false => "NULL",
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::Connection;
+
+    fn database(queries: &[&str]) -> SqliteConnection {
+        let conn = SqliteConnection::establish(":memory:").unwrap();
+        for q in queries {
+            sql_query(*q).execute(&conn).unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn probe_vnstat_2_database() {
+        let conn = database(&[
+            "CREATE TABLE info(id INTEGER PRIMARY KEY, name TEXT, value TEXT)",
+            "INSERT INTO info(name, value) VALUES ('dbversion', '1')",
+            "CREATE TABLE interface(id INTEGER PRIMARY KEY, name TEXT, alias TEXT)",
+        ]);
+        assert_eq!(
+            VnStatSchema::probe(&conn).unwrap(),
+            VnStatSchema {
+                version: 1,
+                has_alias: true
+            }
+        );
+    }
+
+    #[test]
+    fn adapt_to_database_without_alias() {
+        let conn = database(&[
+            "CREATE TABLE info(id INTEGER PRIMARY KEY, name TEXT, value TEXT)",
+            "INSERT INTO info(name, value) VALUES ('dbversion', '1')",
+            "CREATE TABLE interface(id INTEGER PRIMARY KEY, name TEXT)",
+        ]);
+        let schema = VnStatSchema::probe(&conn).unwrap();
+        assert!(!schema.has_alias);
+        assert!(schema.interface_columns().contains("NULL AS alias"));
+    }
+
+    #[test]
+    fn refuse_unsupported_databases() {
+        let newer = database(&[
+            "CREATE TABLE info(id INTEGER PRIMARY KEY, name TEXT, value TEXT)",
+            "INSERT INTO info(name, value) VALUES ('dbversion', '99')",
+        ]);
+        assert!(VnStatSchema::probe(&newer).is_err());
+
+        let unknown = database(&["CREATE TABLE foo(id INTEGER PRIMARY KEY)"]);
+        assert!(VnStatSchema::probe(&unknown).is_err());
+    }
+}
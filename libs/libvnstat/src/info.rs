@@ -1,17 +1,22 @@
-use super::db::{models::Info, VnStatDatabase};
+use super::db::{models::Info, VnStatPool};
 use anyhow::Result;
-pub struct VnStatInfo;
+pub struct VnStatInfo {
+    database: VnStatPool,
+}
 
 impl VnStatInfo {
+    pub fn new(database: VnStatPool) -> Self {
+        Self { database }
+    }
+
     pub fn get(&self) -> Result<Vec<Info>> {
-        Ok(VnStatDatabase::default()?
-            .connect()?
-            .select_table::<Info>("info")?)
+        self.database.query::<Info>("SELECT * FROM info")
     }
 }
 
 #[test]
 fn get_vnstat_info() {
-    println!("{:#?}", VnStatInfo.get());
-    assert!(VnStatInfo.get().is_ok())
+    let info = super::VnStat::default().info();
+    println!("{:#?}", info.get());
+    assert!(info.get().is_ok())
 }
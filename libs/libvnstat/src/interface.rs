@@ -1,17 +1,29 @@
-use super::db::{models::Interface, VnStatDatabase};
+use super::db::{models::Interface, VnStatPool};
 use anyhow::Result;
-pub struct VnStatInterface;
+pub struct VnStatInterface {
+    database: VnStatPool,
+}
 
 impl VnStatInterface {
+    pub fn new(database: VnStatPool) -> Self {
+        Self { database }
+    }
+
     pub fn get(&self) -> Result<Vec<Interface>> {
-        Ok(VnStatDatabase::default()?
-            .connect()?
-            .select_table::<Interface>("interface")?)
+        let columns = self.database.schema()?.interface_columns();
+        self.database
+            .query::<Interface>(&format!("SELECT {columns} FROM interface"))
+    }
+
+    /// Find an interface by its name (e.g. `eth0`)
+    pub fn find(&self, name: &str) -> Result<Option<Interface>> {
+        Ok(self.get()?.into_iter().find(|i| i.name == name))
     }
 }
 
 #[test]
 fn get_vnstat_interface_list() {
-    println!("{:#?}", VnStatInterface.get());
-    assert!(VnStatInterface.get().is_ok())
+    let interface = super::VnStat::default().interface();
+    println!("{:#?}", interface.get());
+    assert!(interface.get().is_ok())
 }
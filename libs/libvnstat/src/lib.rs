@@ -5,36 +5,63 @@ pub mod daemon;
 pub mod db;
 pub mod info;
 pub mod interface;
+pub mod live;
+pub mod summary;
 pub mod traffic;
 
-use anyhow::Result;
+use app::Configs;
+use std::time::Duration;
 
 pub use config::*;
 pub use daemon::*;
 pub use db::*;
 pub use info::*;
 pub use interface::*;
+pub use live::*;
+pub use summary::*;
 pub use traffic::*;
 
-pub struct VnStat;
+/// Entry point of vnStat queries, every query borrows a connection of the shared `database` pool
+#[derive(Clone)]
+pub struct VnStat {
+    database: VnStatPool,
+}
 
 impl VnStat {
-    pub fn database(&self) -> Result<VnStatDatabase> {
-        VnStatDatabase::default()
+    pub fn new(database: VnStatPool) -> Self {
+        Self { database }
+    }
+    pub fn database(&self) -> &VnStatPool {
+        &self.database
     }
     pub fn config(&self) -> VnStatConfig {
         VnStatConfig::default()
     }
     pub fn info(&self) -> VnStatInfo {
-        VnStatInfo
+        VnStatInfo::new(self.database.clone())
     }
     pub fn interface(&self) -> VnStatInterface {
-        VnStatInterface
+        VnStatInterface::new(self.database.clone())
     }
     pub fn traffic(&self, interval: &str) -> VnStatTraffic {
-        VnStatTraffic::new(interval)
+        VnStatTraffic::new(self.database.clone(), interval)
+    }
+    pub fn live(&self, interfaces: Vec<String>) -> VnStatLive {
+        VnStatLive::new(interfaces)
     }
     pub fn daemon(&self) -> VnStatDaemon {
         VnStatDaemon
     }
 }
+
+impl Default for VnStat {
+    /// Pool of the database path resolved from configs, see `VnStatDatabase::path()`
+    fn default() -> Self {
+        let configs = Configs::init().map(|c| c.vnstat()).unwrap_or_default();
+        Self::new(VnStatPool::new(
+            &VnStatDatabase::path(),
+            configs.pool_size(),
+            Duration::from_millis(configs.busy_timeout()),
+        ))
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Error, ErrorKind::InvalidData},
+    time::Instant,
+};
+
+const PROC_NET_DEV: &str = "/proc/net/dev";
+
+/// Kernel counters of an interface
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct InterfaceCounters {
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+}
+
+/// Rates of an interface between two samples, in bytes and packets per second
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct LiveRate {
+    pub name: String,
+    pub rx: f64,
+    pub tx: f64,
+    pub rx_packets: f64,
+    pub tx_packets: f64,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct LiveSample {
+    pub timestamp: String,
+    pub interfaces: Vec<LiveRate>,
+}
+
+/// Samples interface counters like `vnstat --live`
+pub struct VnStatLive {
+    interfaces: Vec<String>,
+    previous: Option<(Instant, HashMap<String, InterfaceCounters>)>,
+}
+
+impl VnStatLive {
+    /// Sample `interfaces`, or every interface except loopback if it's empty
+    pub fn new(interfaces: Vec<String>) -> Self {
+        Self {
+            interfaces,
+            previous: None,
+        }
+    }
+
+    /// Read counters and compute rates since the previous call,
+    /// the first call only takes the initial counters and returns no rates.
+    pub fn sample(&mut self) -> Result<LiveSample> {
+        let now = Instant::now();
+        let counters = self.read_counters()?;
+
+        let mut rates = vec![];
+        if let Some((then, previous)) = &self.previous {
+            let seconds = now.duration_since(*then).as_secs_f64().max(f64::EPSILON);
+            let rate = |current: u64, previous: u64| {
+                // Counter was reset (e.g. interface re-created) or wrapped
+                current.saturating_sub(previous) as f64 / seconds
+            };
+
+            let mut names = counters.keys().collect::<Vec<&String>>();
+            names.sort();
+            for name in names {
+                if let Some(prev) = previous.get(name) {
+                    let curr = counters[name];
+                    rates.push(LiveRate {
+                        name: name.clone(),
+                        rx: rate(curr.rx_bytes, prev.rx_bytes),
+                        tx: rate(curr.tx_bytes, prev.tx_bytes),
+                        rx_packets: rate(curr.rx_packets, prev.rx_packets),
+                        tx_packets: rate(curr.tx_packets, prev.tx_packets),
+                    });
+                }
+            }
+        }
+        self.previous = Some((now, counters));
+
+        Ok(LiveSample {
+            timestamp: Local::now().to_rfc3339(),
+            interfaces: rates,
+        })
+    }
+
+    fn read_counters(&self) -> Result<HashMap<String, InterfaceCounters>> {
+        let counters = parse_net_dev(&fs::read_to_string(PROC_NET_DEV)?)?;
+        Ok(counters
+            .into_iter()
+            .filter(|(name, _)| match self.interfaces.is_empty() {
+                true => name != "lo",
+                false => self.interfaces.contains(name),
+            })
+            .collect())
+    }
+}
+
+/// Parse content of `/proc/net/dev`
+fn parse_net_dev(content: &str) -> Result<HashMap<String, InterfaceCounters>> {
+    let mut counters = HashMap::new();
+    // First two lines are headers
+    for line in content.lines().skip(2) {
+        let (name, fields) = match line.split_once(':') {
+            Some(line) => line,
+            None => continue,
+        };
+        let fields = fields
+            .split_whitespace()
+            .map(|f| f.parse::<u64>())
+            .collect::<Result<Vec<u64>, _>>()
+            .map_err(|e| anyhow!(Error::new(InvalidData, format!("{PROC_NET_DEV}: {e}"))))?;
+        if fields.len() < 10 {
+            return Err(anyhow!(Error::new(
+                InvalidData,
+                format!("{PROC_NET_DEV}: unexpected number of fields"),
+            )));
+        }
+        counters.insert(
+            name.trim().to_owned(),
+            InterfaceCounters {
+                rx_bytes: fields[0],
+                rx_packets: fields[1],
+                tx_bytes: fields[8],
+                tx_packets: fields[9],
+            },
+        );
+    }
+    Ok(counters)
+}
+
+#[test]
+fn parse_proc_net_dev() {
+    let content = "Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo: 19362382    2279    0    0    0     0          0         0 19362382    2279    0    0    0     0       0          0
+  eth0: 1000 10 0 0 0 0 0 0 2000 20 0 0 0 0 0 0
+";
+    let counters = parse_net_dev(content).unwrap();
+    assert_eq!(counters.len(), 2);
+    assert_eq!(
+        counters["eth0"],
+        InterfaceCounters {
+            rx_bytes: 1000,
+            rx_packets: 10,
+            tx_bytes: 2000,
+            tx_packets: 20,
+        }
+    );
+    assert!(parse_net_dev("header\nheader\n eth0: 1 2 3\n").is_err());
+}
+
+#[test]
+fn first_sample_has_no_rates() {
+    let mut live = VnStatLive::new(vec![]);
+    assert!(live.sample().unwrap().interfaces.is_empty());
+    assert!(live
+        .sample()
+        .unwrap()
+        .interfaces
+        .iter()
+        .all(|i| i.name != "lo"));
+}
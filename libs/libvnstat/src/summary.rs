@@ -0,0 +1,207 @@
+use super::{db::models::InterfaceTraffic, TrafficInterval};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Aggregation of traffic rows of one interface over a range of periods
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct TrafficSummary {
+    pub interface: i32,
+    pub interface_name: String,
+    /// Date of the first and the last summarized periods
+    pub from: String,
+    pub to: String,
+    pub periods: usize,
+    pub rx: i64,
+    pub tx: i64,
+    pub total: i64,
+    /// Average rates in bytes per second
+    pub average_rate: TrafficRate,
+    pub peak: PeakPeriod,
+    /// Percentiles of traffic (rx + tx) per period in bytes
+    pub percentiles: Percentiles,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct TrafficRate {
+    pub rx: f64,
+    pub tx: f64,
+    pub total: f64,
+}
+
+impl TrafficRate {
+    fn new(rx: i64, tx: i64, seconds: i64) -> Self {
+        let seconds = seconds.max(1) as f64;
+        Self {
+            rx: rx as f64 / seconds,
+            tx: tx as f64 / seconds,
+            total: (rx + tx) as f64 / seconds,
+        }
+    }
+}
+
+/// The period with the highest traffic (rx + tx)
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PeakPeriod {
+    pub date: String,
+    pub rx: i64,
+    pub tx: i64,
+    pub total: i64,
+    pub rate: TrafficRate,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Percentiles {
+    pub p50: i64,
+    pub p90: i64,
+    pub p95: i64,
+    pub p99: i64,
+}
+
+impl Percentiles {
+    /// Nearest-rank percentiles, `values` must be sorted
+    fn from_sorted(values: &[i64]) -> Self {
+        let rank = |p: f64| {
+            let index = ((p / 100.0) * values.len() as f64).ceil() as usize;
+            values[index.clamp(1, values.len()) - 1]
+        };
+        Self {
+            p50: rank(50.0),
+            p90: rank(90.0),
+            p95: rank(95.0),
+            p99: rank(99.0),
+        }
+    }
+}
+
+impl TrafficSummary {
+    /// Summarize `rows` per interface, interfaces without rows are omitted.
+    pub fn from_rows(interval: &TrafficInterval, rows: &[InterfaceTraffic]) -> Vec<Self> {
+        let mut interfaces: BTreeMap<i32, Vec<&InterfaceTraffic>> = BTreeMap::new();
+        for row in rows {
+            interfaces
+                .entry(row.traffic.interface)
+                .or_default()
+                .push(row);
+        }
+
+        interfaces
+            .into_values()
+            .map(|mut rows| {
+                rows.sort_by_key(|r| parse_date(&r.traffic.date));
+
+                let seconds_of = |r: &InterfaceTraffic| match parse_date(&r.traffic.date) {
+                    Some(date) => interval.period_seconds(date.date()),
+                    None => interval.period_seconds(NaiveDate::MIN),
+                };
+                let (rx, tx) = rows
+                    .iter()
+                    .fold((0, 0), |(rx, tx), r| (rx + r.traffic.rx, tx + r.traffic.tx));
+                let seconds = rows.iter().map(|r| seconds_of(r)).sum();
+
+                let peak = rows
+                    .iter()
+                    .max_by_key(|r| r.traffic.rx + r.traffic.tx)
+                    .unwrap();
+
+                let mut totals = rows
+                    .iter()
+                    .map(|r| r.traffic.rx + r.traffic.tx)
+                    .collect::<Vec<i64>>();
+                totals.sort_unstable();
+
+                Self {
+                    interface: peak.traffic.interface,
+                    interface_name: peak.interface_name.clone(),
+                    from: rows.first().unwrap().traffic.date.clone(),
+                    to: rows.last().unwrap().traffic.date.clone(),
+                    periods: rows.len(),
+                    rx,
+                    tx,
+                    total: rx + tx,
+                    average_rate: TrafficRate::new(rx, tx, seconds),
+                    peak: PeakPeriod {
+                        date: peak.traffic.date.clone(),
+                        rx: peak.traffic.rx,
+                        tx: peak.traffic.tx,
+                        total: peak.traffic.rx + peak.traffic.tx,
+                        rate: TrafficRate::new(peak.traffic.rx, peak.traffic.tx, seconds_of(peak)),
+                    },
+                    percentiles: Percentiles::from_sorted(&totals),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Parse dates stored by vnStat, i.e. `YYYY-MM-DD` or `YYYY-MM-DD hh:mm[:ss]`
+fn parse_date(date: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M"))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::Traffic;
+
+    fn row(interface: i32, name: &str, date: &str, rx: i64, tx: i64) -> InterfaceTraffic {
+        InterfaceTraffic {
+            interface_name: name.to_owned(),
+            traffic: Traffic {
+                id: 0,
+                interface,
+                date: date.to_owned(),
+                rx,
+                tx,
+            },
+        }
+    }
+
+    #[test]
+    fn summarize_rows_per_interface() {
+        let rows = vec![
+            row(1, "eth0", "2022-07-01 01:00:00", 3600, 0),
+            row(2, "wlan0", "2022-07-01 00:00:00", 10, 10),
+            row(1, "eth0", "2022-07-01 00:00:00", 7200, 3600),
+            row(1, "eth0", "2022-07-01 02:00:00", 0, 0),
+        ];
+        let summary = TrafficSummary::from_rows(&TrafficInterval::new("hour"), &rows);
+
+        assert_eq!(summary.len(), 2);
+        let eth0 = &summary[0];
+        assert_eq!(eth0.interface_name, "eth0");
+        assert_eq!(eth0.periods, 3);
+        assert_eq!((eth0.rx, eth0.tx, eth0.total), (10800, 3600, 14400));
+        assert_eq!(eth0.from, "2022-07-01 00:00:00");
+        assert_eq!(eth0.to, "2022-07-01 02:00:00");
+        assert_eq!(eth0.average_rate.rx, 1.0);
+        assert_eq!(eth0.peak.date, "2022-07-01 00:00:00");
+        assert_eq!(eth0.peak.rate.total, 3.0);
+        assert_eq!(eth0.percentiles.p50, 3600);
+        assert_eq!(eth0.percentiles.p99, 10800);
+        assert_eq!(summary[1].interface_name, "wlan0");
+    }
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let values = (1..=100).collect::<Vec<i64>>();
+        let percentiles = Percentiles::from_sorted(&values);
+        assert_eq!(
+            (
+                percentiles.p50,
+                percentiles.p90,
+                percentiles.p95,
+                percentiles.p99
+            ),
+            (50, 90, 95, 99)
+        );
+        assert_eq!(Percentiles::from_sorted(&[7]).p50, 7);
+    }
+}
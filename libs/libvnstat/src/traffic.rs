@@ -1,27 +1,329 @@
-use super::db::{models::Traffic as TrafficModel, VnStatDatabase};
+use super::{
+    db::{
+        models::{InterfaceTraffic, Traffic as TrafficModel},
+        VnStatPool,
+    },
+    TrafficSummary, VnStatInterface,
+};
 use anyhow::{anyhow, Result};
-use std::io::{Error, ErrorKind::InvalidInput};
+use chrono::{Months, NaiveDate, NaiveDateTime};
+use diesel::{deserialize::QueryableByName, sqlite::Sqlite};
+use std::{
+    io::{Error, ErrorKind::InvalidInput},
+    marker::PhantomData,
+    str::FromStr,
+};
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 
 pub struct VnStatTraffic {
+    database: VnStatPool,
     interval: TrafficInterval,
+    interface: Option<String>,
+    query: TrafficQuery,
 }
 
 impl VnStatTraffic {
-    pub fn new(interval: &str) -> Self {
+    pub fn new(database: VnStatPool, interval: &str) -> Self {
         Self {
+            database,
             interval: TrafficInterval::new(interval),
+            interface: None,
+            query: TrafficQuery::default(),
         }
     }
+
+    /// Only rows of the interface named `name` (e.g. `eth0`)
+    pub fn interface(&mut self, name: &str) -> &mut Self {
+        self.interface = Some(name.to_owned());
+        self
+    }
+    /// Only rows dated at or after `date`
+    pub fn from(&mut self, date: NaiveDateTime) -> &mut Self {
+        self.query.from = Some(date);
+        self
+    }
+    /// Only rows dated at or before `date`
+    pub fn to(&mut self, date: NaiveDateTime) -> &mut Self {
+        self.query.to = Some(date);
+        self
+    }
+    pub fn order(&mut self, order: TrafficOrder) -> &mut Self {
+        self.query.order = order;
+        self
+    }
+    pub fn limit(&mut self, limit: u32) -> &mut Self {
+        self.query.limit = Some(limit);
+        self
+    }
+    pub fn offset(&mut self, offset: u32) -> &mut Self {
+        self.query.offset = Some(offset);
+        self
+    }
+
     pub fn get(&self) -> Result<Vec<TrafficModel>> {
+        let query = self.build_query()?;
+        self.database
+            .query::<TrafficModel>(&query.to_sql(self.interval.get()))
+    }
+
+    /// Same as `get` but every row is annotated with its interface name
+    pub fn get_with_interface_name(&self) -> Result<Vec<InterfaceTraffic>> {
+        let query = self.build_query()?;
+        self.database
+            .query::<InterfaceTraffic>(&query.to_sql_with_interface_name(self.interval.get()))
+    }
+
+    /// Same as `get` but rows are fetched `page_size` at a time while they're iterated
+    pub fn pages(&self, page_size: u32) -> Result<TrafficPages<TrafficModel>> {
+        Ok(TrafficPages::new(
+            self.database.clone(),
+            self.interval.get(),
+            self.build_query()?,
+            TrafficQuery::to_sql,
+            page_size,
+        ))
+    }
+
+    /// Same as `get_with_interface_name` but rows are fetched `page_size` at a time
+    /// while they're iterated
+    pub fn pages_with_interface_name(
+        &self,
+        page_size: u32,
+    ) -> Result<TrafficPages<InterfaceTraffic>> {
+        Ok(TrafficPages::new(
+            self.database.clone(),
+            self.interval.get(),
+            self.build_query()?,
+            TrafficQuery::to_sql_with_interface_name,
+            page_size,
+        ))
+    }
+
+    /// The latest row of every interface, by a single query
+    pub fn latest(&self) -> Result<Vec<InterfaceTraffic>> {
         if !self.interval.clone().is_validated() {
             return Err(anyhow!(Error::new(InvalidInput, "invalid interval")));
         }
-        Ok(VnStatDatabase::default()?
-            .connect()?
-            .select_table::<TrafficModel>(self.interval.get())?)
+        self.database
+            .query::<InterfaceTraffic>(&latest_sql(self.interval.get()))
+    }
+
+    /// Totals, average rates, peak period and percentiles of every interface
+    pub fn summary(&self) -> Result<Vec<TrafficSummary>> {
+        Ok(TrafficSummary::from_rows(
+            &self.interval,
+            &self.get_with_interface_name()?,
+        ))
+    }
+
+    fn build_query(&self) -> Result<TrafficQuery> {
+        if !self.interval.clone().is_validated() {
+            return Err(anyhow!(Error::new(InvalidInput, "invalid interval")));
+        }
+        self.query.validate()?;
+
+        let mut query = self.query.clone();
+        if let Some(name) = &self.interface {
+            query.interface = match VnStatInterface::new(self.database.clone()).find(name)? {
+                Some(interface) => Some(interface.id),
+                None => {
+                    return Err(anyhow!(Error::new(
+                        InvalidInput,
+                        format!("interface '{name}' isn't found"),
+                    )))
+                }
+            };
+        }
+        Ok(query)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TrafficOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl FromStr for TrafficOrder {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "asc" => Ok(Self::Asc),
+            "desc" => Ok(Self::Desc),
+            _ => Err(Error::new(
+                InvalidInput,
+                "order must be either 'asc' or 'desc'",
+            )),
+        }
     }
 }
 
+impl std::fmt::Display for TrafficOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Asc => write!(f, "ASC"),
+            Self::Desc => write!(f, "DESC"),
+        }
+    }
+}
+
+/// Bounds, ordering and pagination applied on a traffic table
+#[derive(Clone, Debug, Default)]
+pub struct TrafficQuery {
+    interface: Option<i32>,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+    order: TrafficOrder,
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+impl TrafficQuery {
+    pub fn validate(&self) -> Result<()> {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from > to {
+                return Err(anyhow!(Error::new(
+                    InvalidInput,
+                    "'from' date must be earlier than 'to' date",
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the `SELECT` statement of `table`.
+    ///
+    /// Values are never taken from raw user input: dates are re-formatted from
+    /// `NaiveDateTime`, interface and pagination values are integers.
+    pub fn to_sql(&self, table: &str) -> String {
+        self.build_sql(format!("SELECT * FROM {table} t"))
+    }
+
+    /// Same as `to_sql` but `table` is joined with the `interface` table to select the
+    /// name of the interface of every row
+    pub fn to_sql_with_interface_name(&self, table: &str) -> String {
+        self.build_sql(format!(
+            "SELECT t.id AS id, t.interface AS interface, i.name AS interface_name, t.date AS date, t.rx AS rx, t.tx AS tx \
+            FROM {table} t INNER JOIN interface i ON i.id = t.interface"
+        ))
+    }
+
+    fn build_sql(&self, mut sql: String) -> String {
+        let mut conditions = vec![];
+        if let Some(interface) = self.interface {
+            conditions.push(format!("t.interface = {interface}"));
+        }
+        if let Some(from) = self.from {
+            conditions.push(format!(
+                "datetime(t.date) >= datetime('{}')",
+                from.format(DATE_FORMAT)
+            ));
+        }
+        if let Some(to) = self.to {
+            conditions.push(format!(
+                "datetime(t.date) <= datetime('{}')",
+                to.format(DATE_FORMAT)
+            ));
+        }
+
+        if !conditions.is_empty() {
+            sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+        sql.push_str(&format!(
+            " ORDER BY datetime(t.date) {order}, t.id {order}",
+            order = self.order
+        ));
+
+        // SQLite doesn't accept OFFSET without LIMIT, -1 means no limit.
+        match (self.limit, self.offset) {
+            (Some(limit), Some(offset)) => sql.push_str(&format!(" LIMIT {limit} OFFSET {offset}")),
+            (Some(limit), None) => sql.push_str(&format!(" LIMIT {limit}")),
+            (None, Some(offset)) => sql.push_str(&format!(" LIMIT -1 OFFSET {offset}")),
+            (None, None) => (),
+        }
+        sql
+    }
+}
+
+/// Rows of a traffic query fetched a page at a time, so they aren't loaded at once.
+///
+/// Pages are taken by `LIMIT` and `OFFSET` within `limit` and `offset` of the query,
+/// iterating ends after a page that is shorter than the others or that failed.
+pub struct TrafficPages<T> {
+    database: VnStatPool,
+    table: String,
+    query: TrafficQuery,
+    sql: fn(&TrafficQuery, &str) -> String,
+    page_size: u32,
+    fetched: u32,
+    done: bool,
+    row: PhantomData<T>,
+}
+
+impl<T> TrafficPages<T> {
+    fn new(
+        database: VnStatPool,
+        table: &str,
+        query: TrafficQuery,
+        sql: fn(&TrafficQuery, &str) -> String,
+        page_size: u32,
+    ) -> Self {
+        Self {
+            database,
+            table: table.to_owned(),
+            query,
+            sql,
+            page_size: page_size.max(1),
+            fetched: 0,
+            done: false,
+            row: PhantomData,
+        }
+    }
+}
+
+impl<T> Iterator for TrafficPages<T>
+where
+    T: QueryableByName<Sqlite>,
+{
+    type Item = Result<Vec<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let size = match self.query.limit {
+            Some(limit) => limit.saturating_sub(self.fetched).min(self.page_size),
+            None => self.page_size,
+        };
+        if self.done || size == 0 {
+            return None;
+        }
+        let mut page = self.query.clone();
+        page.limit = Some(size);
+        page.offset = Some(self.query.offset.unwrap_or(0) + self.fetched);
+
+        let rows = self.database.query::<T>(&(self.sql)(&page, &self.table));
+        match &rows {
+            Ok(rows) => {
+                self.fetched += rows.len() as u32;
+                self.done = rows.len() < size as usize;
+            }
+            Err(_) => self.done = true,
+        }
+        Some(rows)
+    }
+}
+
+/// `SELECT` statement of the latest row of every interface in `table`
+fn latest_sql(table: &str) -> String {
+    format!(
+        "SELECT t.id AS id, t.interface AS interface, i.name AS interface_name, t.date AS date, t.rx AS rx, t.tx AS tx \
+        FROM {table} t INNER JOIN interface i ON i.id = t.interface \
+        INNER JOIN (SELECT interface, MAX(datetime(date)) AS date FROM {table} GROUP BY interface) l \
+        ON l.interface = t.interface AND l.date = datetime(t.date) \
+        ORDER BY i.name"
+    )
+}
+
 #[derive(Clone)]
 pub struct TrafficInterval {
     interval: String,
@@ -39,6 +341,22 @@ impl TrafficInterval {
     fn get(&self) -> &str {
         self.interval.as_str()
     }
+
+    /// Length in seconds of the period that starts at `date`
+    pub fn period_seconds(&self, date: NaiveDate) -> i64 {
+        match self.get() {
+            "fiveminute" => 5 * 60,
+            "hour" => 60 * 60,
+            "month" => days_until(date, date.checked_add_months(Months::new(1))) * 86400,
+            "year" => days_until(date, date.checked_add_months(Months::new(12))) * 86400,
+            // "day" and "top" (top days)
+            _ => 86400,
+        }
+    }
+}
+
+fn days_until(date: NaiveDate, next: Option<NaiveDate>) -> i64 {
+    next.map(|next| (next - date).num_days()).unwrap_or(30)
 }
 
 #[test]
@@ -46,3 +364,165 @@ impl TrafficInterval {
 pub fn interval_validation() {
     assert_eq!(TrafficInterval::new("top").is_validated(), true)
 }
+
+#[test]
+fn build_traffic_query() {
+    let date = |s| NaiveDateTime::parse_from_str(s, DATE_FORMAT).unwrap();
+    let select = "SELECT t.id AS id, t.interface AS interface, i.name AS interface_name, t.date AS date, t.rx AS rx, t.tx AS tx";
+
+    assert_eq!(
+        TrafficQuery::default().to_sql("day"),
+        "SELECT * FROM day t ORDER BY datetime(t.date) ASC, t.id ASC"
+    );
+    let query = TrafficQuery {
+        interface: Some(2),
+        from: Some(date("2022-07-01 00:00:00")),
+        to: Some(date("2022-07-03 12:30:00")),
+        order: TrafficOrder::Desc,
+        limit: Some(48),
+        offset: None,
+    };
+    let conditions = "WHERE t.interface = 2 AND datetime(t.date) >= datetime('2022-07-01 00:00:00') AND datetime(t.date) <= datetime('2022-07-03 12:30:00') ORDER BY datetime(t.date) DESC, t.id DESC LIMIT 48";
+    assert_eq!(
+        query.to_sql("hour"),
+        format!("SELECT * FROM hour t {conditions}")
+    );
+    // The `interface` table is only joined when names of interfaces are selected
+    assert_eq!(
+        query.to_sql_with_interface_name("hour"),
+        format!("{select} FROM hour t INNER JOIN interface i ON i.id = t.interface {conditions}")
+    );
+    assert_eq!(
+        TrafficQuery {
+            offset: Some(10),
+            ..Default::default()
+        }
+        .to_sql("month"),
+        "SELECT * FROM month t ORDER BY datetime(t.date) ASC, t.id ASC LIMIT -1 OFFSET 10"
+    );
+}
+
+#[test]
+fn latest_row_of_every_interface() {
+    use crate::db::pool::DEFAULT_BUSY_TIMEOUT;
+    use diesel::{connection::SimpleConnection, Connection, SqliteConnection};
+    use std::{env::temp_dir, fs};
+
+    let path = temp_dir().join("vnstat-latest-traffic-test.db");
+    let path = path.to_str().unwrap();
+    let _ = fs::remove_file(path);
+    SqliteConnection::establish(path)
+        .unwrap()
+        .batch_execute(
+            "CREATE TABLE info(id INTEGER PRIMARY KEY, name TEXT, value TEXT);
+            INSERT INTO info(name, value) VALUES ('dbversion', '1');
+            CREATE TABLE interface(id INTEGER PRIMARY KEY, name TEXT);
+            INSERT INTO interface(id, name) VALUES (1, 'eth0'), (2, 'wlan0'), (3, 'lo');
+            CREATE TABLE fiveminute(id INTEGER PRIMARY KEY, interface INTEGER, date DATE, rx INTEGER, tx INTEGER);
+            INSERT INTO fiveminute(id, interface, date, rx, tx) VALUES
+                (1, 1, '2022-07-01 10:05:00', 10, 1),
+                (2, 2, '2022-07-01 10:05:00', 20, 2),
+                (3, 1, '2022-07-01 10:00:00', 30, 3),
+                (4, 2, '2022-07-01 10:10:00', 40, 4);",
+        )
+        .unwrap();
+
+    let traffic = VnStatTraffic::new(VnStatPool::new(path, 1, DEFAULT_BUSY_TIMEOUT), "fiveminute");
+    let latest = traffic.latest().unwrap();
+    assert_eq!(
+        latest
+            .iter()
+            .map(|t| (t.interface_name.as_str(), t.traffic.rx))
+            .collect::<Vec<_>>(),
+        vec![("eth0", 10), ("wlan0", 40)]
+    );
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn fetch_traffic_a_page_at_a_time() {
+    use crate::db::pool::DEFAULT_BUSY_TIMEOUT;
+    use diesel::{connection::SimpleConnection, Connection, SqliteConnection};
+    use std::{env::temp_dir, fs};
+
+    let path = temp_dir().join("vnstat-traffic-pages-test.db");
+    let path = path.to_str().unwrap();
+    let _ = fs::remove_file(path);
+    SqliteConnection::establish(path)
+        .unwrap()
+        .batch_execute(
+            "CREATE TABLE info(id INTEGER PRIMARY KEY, name TEXT, value TEXT);
+            INSERT INTO info(name, value) VALUES ('dbversion', '1');
+            CREATE TABLE interface(id INTEGER PRIMARY KEY, name TEXT);
+            INSERT INTO interface(id, name) VALUES (1, 'eth0');
+            CREATE TABLE day(id INTEGER PRIMARY KEY, interface INTEGER, date DATE, rx INTEGER, tx INTEGER);
+            INSERT INTO day(id, interface, date, rx, tx) VALUES
+                (1, 1, '2022-07-01', 10, 1),
+                (2, 1, '2022-07-02', 20, 2),
+                (3, 1, '2022-07-03', 30, 3),
+                (4, 1, '2022-07-04', 40, 4),
+                (5, 1, '2022-07-05', 50, 5);",
+        )
+        .unwrap();
+
+    let mut traffic = VnStatTraffic::new(VnStatPool::new(path, 1, DEFAULT_BUSY_TIMEOUT), "day");
+    let pages = |traffic: &VnStatTraffic| {
+        traffic
+            .pages(2)
+            .unwrap()
+            .map(|page| page.unwrap().iter().map(|t| t.rx).collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(pages(&traffic), vec![vec![10, 20], vec![30, 40], vec![50]]);
+
+    // Pages stay within the limit and the offset of the query
+    traffic.limit(3).offset(1);
+    assert_eq!(pages(&traffic), vec![vec![20, 30], vec![40]]);
+    traffic.limit(4).offset(1);
+    assert_eq!(pages(&traffic), vec![vec![20, 30], vec![40, 50]]);
+    traffic.limit(0);
+    assert!(pages(&traffic).is_empty());
+
+    let names = VnStatTraffic::new(VnStatPool::new(path, 1, DEFAULT_BUSY_TIMEOUT), "day")
+        .pages_with_interface_name(4)
+        .unwrap()
+        .map(|page| page.unwrap().len())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec![4, 1]);
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn reject_reversed_date_bounds() {
+    let date = |s| NaiveDateTime::parse_from_str(s, DATE_FORMAT).unwrap();
+    let query = TrafficQuery {
+        from: Some(date("2022-07-03 00:00:00")),
+        to: Some(date("2022-07-01 00:00:00")),
+        ..Default::default()
+    };
+    assert!(query.validate().is_err())
+}
+
+#[test]
+fn period_length_of_intervals() {
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+    assert_eq!(
+        TrafficInterval::new("hour").period_seconds(date(2022, 7, 1)),
+        3600
+    );
+    assert_eq!(
+        TrafficInterval::new("day").period_seconds(date(2022, 7, 1)),
+        86400
+    );
+    assert_eq!(
+        TrafficInterval::new("month").period_seconds(date(2022, 2, 1)),
+        28 * 86400
+    );
+    assert_eq!(
+        TrafficInterval::new("year").period_seconds(date(2020, 1, 1)),
+        366 * 86400
+    );
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+/// Parse a duration like `90`, `30m`, `2h`, `1h30m` or `7d`, a number without unit is seconds
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err("Duration can't be empty".to_owned());
+    }
+    if let Ok(secs) = s.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut secs: u64 = 0;
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(format!("Invalid duration: '{s}', unknown unit '{c}'")),
+        };
+        let value = number
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid duration: '{s}', a number is expected before '{c}'"))?;
+        secs = value
+            .checked_mul(unit)
+            .and_then(|v| secs.checked_add(v))
+            .ok_or_else(|| format!("Invalid duration: '{s}', it's too long"))?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(format!(
+            "Invalid duration: '{s}', a unit is expected after '{number}'"
+        ));
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+#[test]
+fn parse_durations() {
+    assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+    assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+    assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(2 * 60 * 60)));
+    assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(90 * 60)));
+    assert_eq!(parse_duration("1D"), Ok(Duration::from_secs(24 * 60 * 60)));
+
+    for invalid in ["", "h", "2x", "1h30", "-1h", "99999999999999999999w"] {
+        assert!(parse_duration(invalid).is_err(), "{invalid}");
+    }
+}
@@ -1,3 +1,4 @@
+pub mod duration;
 pub mod file;
 pub mod process;
 pub mod unix_socket;
@@ -45,6 +45,16 @@ pub enum Commands {
     UnBlockIPs,
     BlockList,
     ConnectionsList,
+    ConfigHistory,
+    ConfigRollback,
+    UserAdd,
+    UserRemove,
+    UserList,
+    AuthSetPassword,
+    DbStatus,
+    KeyList,
+    KeyRevoke,
+    KeyCreate,
 }
 
 impl FromStr for Commands {
@@ -61,6 +71,16 @@ impl FromStr for Commands {
             "unblock" | "server-unblock" => Ok(Self::UnBlockIPs),
             "block-list" | "server-block-list" => Ok(Self::BlockList),
             "connections-list" | "server-connections-list" => Ok(Self::ConnectionsList),
+            "history" | "config-history" => Ok(Self::ConfigHistory),
+            "rollback" | "config-rollback" => Ok(Self::ConfigRollback),
+            "user-add" => Ok(Self::UserAdd),
+            "user-remove" => Ok(Self::UserRemove),
+            "user-list" => Ok(Self::UserList),
+            "auth-set-password" => Ok(Self::AuthSetPassword),
+            "db-status" => Ok(Self::DbStatus),
+            "key-list" | "server-key-list" => Ok(Self::KeyList),
+            "key-revoke" | "server-key-revoke" => Ok(Self::KeyRevoke),
+            "key-create" | "server-key-create" => Ok(Self::KeyCreate),
             _ => Err("invalid message"),
         }
     }
@@ -79,6 +99,16 @@ impl ToString for Commands {
             Self::UnBlockIPs => "server-unblock",
             Self::ConnectionsList => "server-connections-list",
             Self::BlockList => "server-block-list",
+            Self::ConfigHistory => "config-history",
+            Self::ConfigRollback => "config-rollback",
+            Self::UserAdd => "user-add",
+            Self::UserRemove => "user-remove",
+            Self::UserList => "user-list",
+            Self::AuthSetPassword => "auth-set-password",
+            Self::DbStatus => "db-status",
+            Self::KeyList => "server-key-list",
+            Self::KeyRevoke => "server-key-revoke",
+            Self::KeyCreate => "server-key-create",
         }
         .to_owned()
     }
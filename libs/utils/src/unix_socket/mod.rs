@@ -45,6 +45,18 @@ impl UnixSocket {
         Ok(sock)
     }
 
+    /// Close the socket, the socket file of a bound one is removed
+    pub fn close(self) -> Result<()> {
+        if let Some(listener) = self.listener {
+            let path = listener.local_addr()?.as_pathname().map(Path::to_path_buf);
+            drop(listener);
+            if let Some(path) = path {
+                remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Connect to unix socket
     pub async fn connect(path: &str) -> Result<Self> {
         Ok(Self {
@@ -65,19 +77,29 @@ impl UnixSocket {
         }
     }
 
-    /// Receive messages from stream
+    /// User id of the process on the other side of the stream, `None` when it's unknown
+    pub fn peer_uid(&self) -> Option<u32> {
+        let cred = self.stream.as_ref()?.peer_cred().ok()?;
+        Some(cred.uid())
+    }
+
+    /// Receive messages from stream, a client reads until the server closes the stream
     pub async fn receive(&mut self) -> Result<String> {
-        loop {
-            if self.side.eq(&UnixSocketSide::Server) {
-                self.streaming().await?;
-            }
+        if self.side.eq(&UnixSocketSide::Server) {
+            self.streaming().await?;
+        }
 
+        let mut message = vec![];
+        loop {
             self.stream.as_ref().unwrap().readable().await?;
             let mut buf = vec![0; 1024];
             match self.stream.as_ref().unwrap().try_read(&mut buf) {
+                Ok(0) => break,
                 Ok(n) => {
-                    buf.truncate(n);
-                    return Ok(from_utf8(&buf)?.to_owned());
+                    message.extend_from_slice(&buf[..n]);
+                    if self.side.eq(&UnixSocketSide::Server) {
+                        break;
+                    }
                 }
                 Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
                     continue;
@@ -87,25 +109,28 @@ impl UnixSocket {
                 }
             }
         }
+        Ok(from_utf8(&message)?.to_owned())
     }
 
-    /// Send messages to stream
-    pub async fn send(&self, message: &str) -> Result<()> {
-        loop {
+    /// Send messages to stream, the server closes the stream after its response is sent
+    pub async fn send(&mut self, message: &str) -> Result<()> {
+        let message = message.as_bytes();
+        let mut written = 0;
+        while written < message.len() {
             self.stream.as_ref().unwrap().writable().await?;
-            if let Err(err) = self.stream.as_ref().unwrap().try_write(message.as_bytes()) {
-                match err {
-                    ref e if e.kind() == std::io::ErrorKind::WouldBlock => {
-                        continue;
-                    }
-                    e => {
-                        return Err(anyhow!(e));
-                    }
+            match self.stream.as_ref().unwrap().try_write(&message[written..]) {
+                Ok(n) => written += n,
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    continue;
+                }
+                Err(e) => {
+                    return Err(anyhow!(e));
                 }
-            } else {
-                break;
             }
         }
+        if self.side.eq(&UnixSocketSide::Server) {
+            self.stream = None;
+        }
         Ok(())
     }
 }
@@ -1,5 +1,6 @@
 use clap::{clap_derive::ArgEnum, Parser, Subcommand};
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
+use utils::duration::parse_duration;
 #[derive(Parser, Debug)]
 #[clap(
     author,
@@ -54,6 +55,26 @@ pub enum Commands {
         #[clap(subcommand)]
         command: ServerCommands,
     },
+    /// To review and roll back changes of vnStat configuration.
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommands,
+    },
+    /// To manage the password of your vns HTTP server.
+    Auth {
+        #[clap(subcommand)]
+        command: AuthCommands,
+    },
+    /// To manage users who can log in to your vns HTTP server.
+    User {
+        #[clap(subcommand)]
+        command: UserCommands,
+    },
+    /// To review the authentication database of your vns HTTP server.
+    Db {
+        #[clap(subcommand)]
+        command: DbCommands,
+    },
 }
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Subcommand)]
@@ -62,7 +83,11 @@ pub enum ServerCommands {
     /// You will need to restart vns daemon to running the server again.
 
     #[clap(value_parser)]
-    Shutdown,
+    Shutdown {
+        /// Shutdown the admin server instead
+        #[clap(long, value_parser)]
+        admin: bool,
+    },
     /// Get server status
 
     #[clap(value_parser)]
@@ -71,16 +96,30 @@ pub enum ServerCommands {
     ///May drop socket pending connection. All open connections remain active.
 
     #[clap(value_parser)]
-    Pause,
+    Pause {
+        /// Pause the admin server instead
+        #[clap(long, value_parser)]
+        admin: bool,
+    },
     /// Resume accepting incoming connections.
 
     #[clap(value_parser)]
-    Resume,
-    ///  Block specific ip address to disallow using HTTP server
+    Resume {
+        /// Resume the admin server instead
+        #[clap(long, value_parser)]
+        admin: bool,
+    },
+    ///  Block specific ip addresses or ranges to disallow using HTTP server
     #[clap(value_parser)]
     Block {
         #[clap(required = true, value_parser)]
         addresses: Vec<String>,
+        /// Block temporarily, e.g. 30m, 2h or 7d
+        #[clap(long = "for", value_parser = parse_duration)]
+        duration: Option<Duration>,
+        /// Why it's blocked
+        #[clap(long, value_parser)]
+        reason: Option<String>,
     },
 
     ///  un-Block specific ip address that was blocked and allow using HTTP server again
@@ -100,6 +139,84 @@ pub enum ServerCommands {
         #[clap(arg_enum)]
         list: List,
     },
+
+    /// To list, revoke and create keys of your vns HTTP server
+    Keys {
+        #[clap(subcommand)]
+        command: KeyCommands,
+    },
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Subcommand)]
+pub enum KeyCommands {
+    /// Get list of keys that aren't expired, their values are masked
+    #[clap(value_parser)]
+    List {
+        /// Only keys of a connection
+        #[clap(long, value_parser)]
+        conn: Option<String>,
+    },
+
+    /// Revoke keys by their ids, or every key of a connection
+    #[clap(value_parser)]
+    Revoke {
+        #[clap(
+            required_unless_present = "conn",
+            conflicts_with = "conn",
+            value_parser
+        )]
+        ids: Vec<i32>,
+        /// UUID of the connection
+        #[clap(long, value_parser)]
+        conn: Option<String>,
+    },
+
+    /// Create a long-lived service key, e.g. for a dashboard
+    #[clap(value_parser)]
+    Create {
+        #[clap(required = true, value_parser)]
+        label: String,
+        /// Comma separated scopes: read, config, daemon or admin
+        #[clap(long, value_parser, default_value = "read")]
+        scopes: String,
+        /// Expiry of the key, e.g. 90d. A year by default
+        #[clap(long = "expires-in", value_parser = parse_duration)]
+        expires_in: Option<Duration>,
+    },
+}
+
+impl KeyCommands {
+    pub fn args(&self) -> Vec<String> {
+        match self.clone() {
+            KeyCommands::List { conn } => conn.into_iter().collect(),
+            KeyCommands::Revoke { ids, conn } => match conn {
+                Some(conn) => vec!["--conn".to_owned(), conn],
+                None => ids.iter().map(|id| id.to_string()).collect(),
+            },
+            KeyCommands::Create {
+                label,
+                scopes,
+                expires_in,
+            } => {
+                let mut args = vec!["--scopes".to_owned(), scopes];
+                if let Some(expires_in) = expires_in {
+                    args.extend(["--for".to_owned(), expires_in.as_secs().to_string()]);
+                }
+                args.push(label);
+                args
+            }
+        }
+    }
+}
+
+impl Display for KeyCommands {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyCommands::List { .. } => write!(f, "key-list"),
+            KeyCommands::Revoke { .. } => write!(f, "key-revoke"),
+            KeyCommands::Create { .. } => write!(f, "key-create"),
+        }
+    }
 }
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, ArgEnum)]
 pub enum List {
@@ -107,19 +224,156 @@ pub enum List {
     Block,
 }
 
+impl ServerCommands {
+    pub fn args(&self) -> Vec<String> {
+        match self.clone() {
+            ServerCommands::Block {
+                addresses,
+                duration,
+                reason,
+            } => {
+                let mut args = vec![];
+                if let Some(duration) = duration {
+                    args.extend(["--for".to_owned(), duration.as_secs().to_string()]);
+                }
+                if let Some(reason) = reason {
+                    args.extend(["--reason".to_owned(), reason]);
+                }
+                args.extend(addresses);
+                args
+            }
+            ServerCommands::UnBlock { addresses } => addresses,
+            ServerCommands::Keys { command } => command.args(),
+            ServerCommands::Shutdown { admin: true }
+            | ServerCommands::Pause { admin: true }
+            | ServerCommands::Resume { admin: true } => vec!["--admin".to_owned()],
+            _ => vec![],
+        }
+    }
+}
+
 impl Display for ServerCommands {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ServerCommands::Shutdown => write!(f, "shutdown"),
+            ServerCommands::Shutdown { .. } => write!(f, "shutdown"),
             ServerCommands::Status => write!(f, "status"),
-            ServerCommands::Pause => write!(f, "pause"),
-            ServerCommands::Resume => write!(f, "resume"),
+            ServerCommands::Pause { .. } => write!(f, "pause"),
+            ServerCommands::Resume { .. } => write!(f, "resume"),
             ServerCommands::Block { .. } => write!(f, "block"),
             ServerCommands::UnBlock { .. } => write!(f, "unblock"),
             ServerCommands::List { list } => match list {
                 List::Block => write!(f, "block-list"),
                 List::Connections => write!(f, "connections-list"),
             },
+            ServerCommands::Keys { command } => command.fmt(f),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Subcommand)]
+pub enum ConfigCommands {
+    /// Get changes of vnStat configuration, the latest first
+    #[clap(value_parser)]
+    History {
+        /// Number of revisions to show
+        #[clap(long, value_parser)]
+        limit: Option<usize>,
+    },
+
+    /// Restore vnStat configuration as it was at a revision.
+    /// Revision 0 is the configuration before the first recorded change.
+    #[clap(value_parser)]
+    Rollback {
+        #[clap(required = true, value_parser)]
+        revision: u32,
+    },
+}
+
+impl ConfigCommands {
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            ConfigCommands::History { limit } => limit.iter().map(|l| l.to_string()).collect(),
+            ConfigCommands::Rollback { revision } => vec![revision.to_string()],
+        }
+    }
+}
+
+impl Display for ConfigCommands {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigCommands::History { .. } => write!(f, "config-history"),
+            ConfigCommands::Rollback { .. } => write!(f, "config-rollback"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Subcommand)]
+pub enum UserCommands {
+    /// Add a user, its password is read from stdin when --password isn't given
+    #[clap(value_parser)]
+    Add {
+        #[clap(required = true, value_parser)]
+        username: String,
+        /// viewer (read only), operator (read, config and daemon) or admin (everything)
+        #[clap(long, value_parser, default_value = "viewer")]
+        role: String,
+        #[clap(long, value_parser)]
+        password: Option<String>,
+    },
+
+    /// Remove users and revoke their keys
+    #[clap(value_parser)]
+    Remove {
+        #[clap(required = true, value_parser)]
+        usernames: Vec<String>,
+    },
+
+    /// Get list of users
+    #[clap(value_parser)]
+    List,
+}
+
+impl Display for UserCommands {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UserCommands::Add { .. } => write!(f, "user-add"),
+            UserCommands::Remove { .. } => write!(f, "user-remove"),
+            UserCommands::List => write!(f, "user-list"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Subcommand)]
+pub enum AuthCommands {
+    /// Change the password of `auth` configs, it's stored hashed and keys issued by the old
+    /// password are revoked. The password is read from stdin when --password isn't given
+    #[clap(value_parser)]
+    SetPassword {
+        #[clap(long, value_parser)]
+        password: Option<String>,
+    },
+}
+
+impl Display for AuthCommands {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthCommands::SetPassword { .. } => write!(f, "auth-set-password"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Subcommand)]
+pub enum DbCommands {
+    /// Get migrations of the authentication database and whether they're applied.
+    /// Pending migrations are applied by restarting vnsd or by `vnsd --migrate-only`
+    #[clap(value_parser)]
+    Status,
+}
+
+impl Display for DbCommands {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DbCommands::Status => write!(f, "db-status"),
         }
     }
 }
@@ -1,4 +1,4 @@
-use std::{str::FromStr, time::Duration};
+use std::{io::stdin, str::FromStr, time::Duration};
 
 use app::{log::Logger, UDS_ADDRESS};
 use clap::Parser;
@@ -12,8 +12,9 @@ use utils::unix_socket::{
     Commands as UnixSocketCommands, Request, Response, ServerResponseStatus, UnixSocket,
 };
 use vns::cli::{
-    Args, Commands, List as ListType,
+    Args, AuthCommands, Commands, ConfigCommands, DbCommands, KeyCommands, List as ListType,
     ServerCommands::{self, *},
+    UserCommands,
 };
 
 const TIME_OF_WAITING_RESPONSE_FROM_UNIX_SERVER: u64 = 6000; // By Milliseconds
@@ -25,42 +26,72 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     match args.commands {
         Some(Commands::Server { command }) => {
-            let mut socket = UnixSocket::connect(UDS_ADDRESS)
-                .await
-                .map_err(|e| error!("{e}"))
-                .unwrap();
-            let message = Request::new(
+            let request = Request::new(
                 UnixSocketCommands::from_str(&command.to_string()).unwrap(),
-                match command.clone() {
-                    Block { addresses } | UnBlock { addresses } => addresses,
-                    _ => vec![],
-                },
+                command.args(),
             );
-
-            match socket.send(&format!("{}", json!(message))).await {
-                Err(e) => error!("Couldn't send to unix stream: {e}"),
-                Ok(_) => {
-                    if command == Shutdown {
-                        warn!("Shutdown server gracefully, you will need to restart vns daemon to re-running http server");
-                    }
-                    select!(
-                        _ = async {
-                            match socket.receive().await {
-                                Err(e) => error!("Cannot receive response from unix server: {e}"),
-                                Ok(res) => {
-                                    let res = serde_json::from_str::<Response>(&res).unwrap();
-                                    handle_response(command, res);
-                                }
-                            }
-                        } => {}
-                        _ = async {
-                            time::sleep(Duration::from_millis(TIME_OF_WAITING_RESPONSE_FROM_UNIX_SERVER)).await;
-                        } => {
-                            error!("No response from unix server: connection timeout.")
-                        }
-                    );
+            if matches!(command, Shutdown { .. }) {
+                warn!("Shutdown server gracefully, you will need to restart vns daemon to re-running http server");
+            }
+            if let Some(res) = send_request(request).await {
+                handle_response(command, res);
+            }
+        }
+        Some(Commands::Config { command }) => {
+            let request = Request::new(
+                UnixSocketCommands::from_str(&command.to_string()).unwrap(),
+                command.args(),
+            );
+            if let Some(res) = send_request(request).await {
+                handle_config_response(command, res);
+            }
+        }
+        Some(Commands::Auth { command }) => {
+            let AuthCommands::SetPassword { password } = command.clone();
+            let password = match password.or_else(read_password) {
+                Some(password) => password,
+                None => return Ok(()),
+            };
+            let request = Request::new(
+                UnixSocketCommands::from_str(&command.to_string()).unwrap(),
+                vec![password],
+            );
+            if let Some(res) = send_request(request).await {
+                print_messages(&res);
+            }
+        }
+        Some(Commands::User { command }) => {
+            let args = match command.clone() {
+                UserCommands::Add {
+                    username,
+                    role,
+                    password,
+                } => {
+                    let password = match password.or_else(read_password) {
+                        Some(password) => password,
+                        None => return Ok(()),
+                    };
+                    vec![username, role, password]
                 }
+                UserCommands::Remove { usernames } => usernames,
+                UserCommands::List => vec![],
             };
+            let request = Request::new(
+                UnixSocketCommands::from_str(&command.to_string()).unwrap(),
+                args,
+            );
+            if let Some(res) = send_request(request).await {
+                handle_user_response(command, res);
+            }
+        }
+        Some(Commands::Db { command }) => {
+            let request = Request::new(
+                UnixSocketCommands::from_str(&command.to_string()).unwrap(),
+                vec![],
+            );
+            if let Some(res) = send_request(request).await {
+                handle_db_response(command, res);
+            }
         }
         None => {
             println!(
@@ -73,29 +104,95 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Read a password from the first line of stdin
+fn read_password() -> Option<String> {
+    println!("Password:");
+    let mut password = String::new();
+    if let Err(e) = stdin().read_line(&mut password) {
+        error!("Cannot read password: {e}");
+        return None;
+    }
+    Some(password.trim_end_matches(&['\r', '\n'][..]).to_owned())
+}
+
+/// Send `request` to vnsd and wait for its response
+async fn send_request(request: Request) -> Option<Response> {
+    let mut socket = UnixSocket::connect(UDS_ADDRESS)
+        .await
+        .map_err(|e| error!("{e}"))
+        .unwrap();
+
+    if let Err(e) = socket.send(&format!("{}", json!(request))).await {
+        error!("Couldn't send to unix stream: {e}");
+        return None;
+    }
+    select!(
+        res = socket.receive() => match res {
+            Err(e) => {
+                error!("Cannot receive response from unix server: {e}");
+                None
+            }
+            Ok(res) => Some(serde_json::from_str::<Response>(&res).unwrap()),
+        },
+        _ = time::sleep(Duration::from_millis(TIME_OF_WAITING_RESPONSE_FROM_UNIX_SERVER)) => {
+            error!("No response from unix server: connection timeout.");
+            None
+        }
+    )
+}
+
 fn handle_response(command: ServerCommands, res: Response) {
     match command {
         Status => {
             #[derive(Deserialize)]
-            struct Status {
+            struct Listener {
+                address: String,
+                mutating: bool,
+            }
+            #[derive(Deserialize)]
+            struct ServerStatus {
                 status: String,
-                ip: String,
-                port: String,
+                listeners: Vec<Listener>,
             }
+            #[derive(Deserialize)]
+            struct Status {
+                #[serde(flatten)]
+                public: ServerStatus,
+                admin: Option<ServerStatus>,
+            }
+            let print_status = |res: &ServerStatus| {
+                println!(
+                    "{:<7} {}",
+                    "Status".white(),
+                    match res.status.to_lowercase().as_str() {
+                        "active" => format!("{} ({})", "Active", "Running".green()),
+                        "inactive" => format!("{} ({})", "InActive", "Stopped".red()),
+                        "idle" => format!("{} ({})", "Idle", "Paused".blue()),
+                        _ => "".to_owned(),
+                    }
+                );
+                for (i, listener) in res.listeners.iter().enumerate() {
+                    println!(
+                        "{} {} ({})",
+                        match i {
+                            0 => "Listen".white().to_string(),
+                            _ => " ".repeat("Listen".len()),
+                        },
+                        listener.address,
+                        match listener.mutating {
+                            true => "mutating",
+                            false => "read-only",
+                        }
+                    );
+                }
+            };
             let res: Status = serde_json::from_str(&res.messages[0].body).unwrap();
 
-            println!(
-                "{:<7} {}",
-                "Status".white(),
-                match res.status.to_lowercase().as_str() {
-                    "active" => format!("{} ({})", "Active", "Running".green()),
-                    "inactive" => format!("{} ({})", "InActive", "Stopped".red()),
-                    "idle" => format!("{} ({})", "Idle", "Paused".blue()),
-                    _ => "".to_owned(),
-                }
-            );
-            println!("{:<7} {}", "IP".white(), res.ip);
-            println!("{:<7} {}", "PORT".white(), res.port);
+            print_status(&res.public);
+            if let Some(admin) = &res.admin {
+                println!("\n{}", "Admin server".white());
+                print_status(admin);
+            }
         }
         List { list } => {
             let mut table = Table::new();
@@ -105,14 +202,24 @@ fn handle_response(command: ServerCommands, res: Response) {
                     struct Row {
                         pub ip_addr: String,
                         pub blocked_at: String,
+                        pub expires_at: Option<String>,
+                        pub reason: Option<String>,
                     }
 
                     let data: Vec<Row> = serde_json::from_str(&res.messages[0].body).unwrap();
-                    table
-                        .load_preset(UTF8_FULL)
-                        .set_header(["IP address", "Blocked at"]);
+                    table.load_preset(UTF8_FULL).set_header([
+                        "IP address",
+                        "Blocked at",
+                        "Expires at",
+                        "Reason",
+                    ]);
                     for row in data {
-                        table.add_row([row.ip_addr, row.blocked_at]);
+                        table.add_row([
+                            row.ip_addr,
+                            row.blocked_at,
+                            row.expires_at.unwrap_or_else(|| "Never".to_owned()),
+                            row.reason.unwrap_or_default(),
+                        ]);
                     }
                 }
                 ListType::Connections => {
@@ -139,18 +246,173 @@ fn handle_response(command: ServerCommands, res: Response) {
             }
             println!("{table}");
         }
+        Keys {
+            command: KeyCommands::List { .. },
+        } if matches!(
+            res.messages.first().map(|m| &m.status),
+            Some(ServerResponseStatus::Success)
+        ) =>
+        {
+            #[derive(Deserialize)]
+            struct Row {
+                pub id: i32,
+                pub value: String,
+                pub label: Option<String>,
+                pub username: Option<String>,
+                pub scopes: String,
+                pub conn_uuid: String,
+                pub expires_at: String,
+            }
 
-        _ => {
-            for message in res.messages.iter() {
-                println!(
-                    "[{}] {}",
-                    match message.status {
-                        ServerResponseStatus::Failed => "Failed".red(),
-                        ServerResponseStatus::Success => "Success".green(),
-                    },
-                    message.body
-                );
+            let data: Vec<Row> = serde_json::from_str(&res.messages[0].body).unwrap();
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL).set_header([
+                "ID",
+                "Key",
+                "Label",
+                "Owner",
+                "Scopes",
+                "Connection UUID",
+                "Expires at",
+            ]);
+            for row in data {
+                table.add_row([
+                    row.id.to_string(),
+                    row.value,
+                    row.label.unwrap_or_default(),
+                    row.username.unwrap_or_else(|| "(auth password)".to_owned()),
+                    row.scopes,
+                    row.conn_uuid,
+                    row.expires_at,
+                ]);
             }
+            println!("{table}");
         }
+
+        _ => print_messages(&res),
     };
 }
+
+fn print_messages(res: &Response) {
+    for message in res.messages.iter() {
+        println!(
+            "[{}] {}",
+            match message.status {
+                ServerResponseStatus::Failed => "Failed".red(),
+                ServerResponseStatus::Success => "Success".green(),
+            },
+            message.body
+        );
+    }
+}
+
+fn handle_config_response(command: ConfigCommands, res: Response) {
+    match (command, res.messages.first()) {
+        (ConfigCommands::History { .. }, Some(message))
+            if matches!(message.status, ServerResponseStatus::Success) =>
+        {
+            #[derive(Deserialize)]
+            struct Change {
+                pub prop: String,
+                pub old_value: Option<String>,
+                pub new_value: Option<String>,
+            }
+            #[derive(Deserialize)]
+            struct Row {
+                pub revision: i32,
+                pub conn_uuid: String,
+                pub changed_at: String,
+                pub changes: Vec<Change>,
+            }
+
+            let data: Vec<Row> = serde_json::from_str(&message.body).unwrap();
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL).set_header([
+                "Revision",
+                "Changes",
+                "Connection UUID",
+                "Changed at",
+            ]);
+            for row in data {
+                let unset = || "(unset)".to_owned();
+                let changes = row
+                    .changes
+                    .into_iter()
+                    .map(|c| {
+                        format!(
+                            "{}: {} -> {}",
+                            c.prop,
+                            c.old_value.unwrap_or_else(unset),
+                            c.new_value.unwrap_or_else(unset)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                table.add_row([
+                    row.revision.to_string(),
+                    changes,
+                    row.conn_uuid,
+                    row.changed_at,
+                ]);
+            }
+            println!("{table}");
+        }
+        _ => print_messages(&res),
+    }
+}
+
+fn handle_db_response(command: DbCommands, res: Response) {
+    match (command, res.messages.first()) {
+        (DbCommands::Status, Some(message))
+            if matches!(message.status, ServerResponseStatus::Success) =>
+        {
+            #[derive(Deserialize)]
+            struct Row {
+                pub version: i32,
+                pub name: String,
+                pub applied_at: Option<String>,
+            }
+
+            let data: Vec<Row> = serde_json::from_str(&message.body).unwrap();
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .set_header(["Version", "Name", "Applied at"]);
+            for row in data {
+                table.add_row([
+                    row.version.to_string(),
+                    row.name,
+                    row.applied_at.unwrap_or_else(|| "Pending".to_owned()),
+                ]);
+            }
+            println!("{table}");
+        }
+        _ => print_messages(&res),
+    }
+}
+
+fn handle_user_response(command: UserCommands, res: Response) {
+    match (command, res.messages.first()) {
+        (UserCommands::List, Some(message))
+            if matches!(message.status, ServerResponseStatus::Success) =>
+        {
+            #[derive(Deserialize)]
+            struct Row {
+                pub username: String,
+                pub role: String,
+                pub created_at: String,
+            }
+
+            let data: Vec<Row> = serde_json::from_str(&message.body).unwrap();
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .set_header(["Username", "Role", "Created at"]);
+            for row in data {
+                table.add_row([row.username, row.role, row.created_at]);
+            }
+            println!("{table}");
+        }
+        _ => print_messages(&res),
+    }
+}
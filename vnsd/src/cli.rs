@@ -2,10 +2,16 @@ use clap::Parser;
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
 pub struct Args {
-    /// select pid file
+    /// listen on this IP address only, instead of listeners of the configs
     #[clap(long)]
     pub ip: Option<String>,
-    /// set daemon process user
+    /// listen on this port only, instead of listeners of the configs
     #[clap(long)]
     pub port: Option<u16>,
+    /// apply pending migrations of the authentication database and exit
+    #[clap(long)]
+    pub migrate_only: bool,
+    /// migrate the authentication database up or down to a version and exit
+    #[clap(long, value_name = "VERSION")]
+    pub migrate_to: Option<i32>,
 }
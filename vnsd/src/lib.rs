@@ -5,6 +5,7 @@ extern crate diesel;
 
 // modules
 pub mod cli;
+pub mod reload;
 pub mod server;
 pub mod uds_request_handler;
 pub mod utils;
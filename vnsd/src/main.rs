@@ -2,15 +2,24 @@ use app::{Configs, Logger, UDS_ADDRESS};
 use clap::Parser;
 use log::{error, info, warn};
 use serde_json;
-use tokio::{self, spawn};
+use std::time::Duration;
+use tokio::{self, spawn, time};
 use utils::unix_socket::{Request, Response, UnixSocket};
 use vnsd::{
+    api::auth::database::{
+        migrations::{self, DATABASE_VERSION},
+        BlockList, InitDatabase,
+    },
     cli::Args,
-    server::{Server, ServerAddr},
+    reload::RuntimeConfigs,
+    server::{Routes, ServerData, Servers},
     uds_request_handler::RequestHandler,
-    utils::terminate_process,
+    utils::{reload_signal, terminate_signal},
 };
 
+/// Seconds between two purges of expired blocks
+const BLOCK_LIST_PURGE_INTERVAL: u64 = 600;
+
 #[tokio::main]
 async fn main() -> std::process::ExitCode {
     Logger::init();
@@ -22,7 +31,45 @@ async fn main() -> std::process::ExitCode {
             return std::process::ExitCode::FAILURE;
         }
     };
-    let configs = Configs::init().unwrap();
+    if args.migrate_only || args.migrate_to.is_some() {
+        return migrate(args.migrate_to.unwrap_or(DATABASE_VERSION));
+    }
+    let runtime = match RuntimeConfigs::load(&args) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            error!("Invalid configs: {e}");
+            return std::process::ExitCode::FAILURE;
+        }
+    };
+    runtime.init();
+    let mut reload_signal = match reload_signal() {
+        Ok(signal) => signal,
+        Err(e) => {
+            error!("Cannot listen to SIGHUP: {e}");
+            return std::process::ExitCode::FAILURE;
+        }
+    };
+    let data = match ServerData::init() {
+        Ok(data) => data,
+        Err(e) => {
+            error!("Cannot initialize the server: {e:#}");
+            return std::process::ExitCode::FAILURE;
+        }
+    };
+
+    let auth_db = data.auth_db.clone();
+    spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(BLOCK_LIST_PURGE_INTERVAL));
+        loop {
+            interval.tick().await;
+            match auth_db.get().map(|db| BlockList::purge_expired(&db)) {
+                Ok(Ok(0)) => (),
+                Ok(Ok(purged)) => info!("{purged} expired block(s) purged"),
+                Ok(Err(e)) => error!("Cannot purge expired blocks: {e}"),
+                Err(e) => error!("Cannot connect to authentication database: {e}"),
+            }
+        }
+    });
 
     let mut listener = match UnixSocket::bind(UDS_ADDRESS) {
         Err(e) => {
@@ -34,55 +81,125 @@ async fn main() -> std::process::ExitCode {
             lis
         }
     };
-    let server = Server::new(ServerAddr::new(
-        &args.ip.unwrap_or(configs.server().ip()),
-        args.port.unwrap_or(configs.server().port()),
-    ))
-    .map_err(|e| error!("Cannot bind http server: {e}"))
-    .unwrap();
-
-    spawn(async move {
-        terminate_process()
-            .await
-            .map_err(|e| error!("{e}"))
-            .unwrap();
-    });
-    let _: (_, Result<(), anyhow::Error>) = tokio::join!(
-        // Running HTTP server
-        async {
-            let (ip, port) = server.address();
+    let servers = match Servers::new(runtime.public, runtime.admin, data) {
+        Ok(servers) => servers,
+        Err(e) => {
+            error!("Cannot bind http server: {e}");
+            let _ = listener.close();
+            return std::process::ExitCode::FAILURE;
+        }
+    };
+    if Configs::current().unwrap().auth().uses_default_password() {
+        for server in servers.iter() {
+            for listener in server.listeners().iter().filter(|l| !l.addr.is_local()) {
+                warn!(
+                    "Server is reachable on '{}' by the default password, change it by 'vns auth set-password'",
+                    listener.addr
+                );
+            }
+        }
+    }
+    if let Some(tls) = servers.public.data().tls.clone() {
+        tls.watch();
+    }
 
-            info!("Server running on http://{ip}:{port}");
-            server
-                .run()
-                .await
-                .map_err(|e| error!("Cannot run the server: {e}"))
-                .is_err()
-                .then(|| warn!("Server has been disconnected"));
-        },
-        // Listening to UNIX socket commands
-        async {
-            loop {
-                match listener.receive().await {
-                    Ok(req) => {
-                        if let Ok(req) = serde_json::from_str::<Request>(&req) {
-                            let mut res = Response::new();
+    let shutdown = async {
+        match terminate_signal().await {
+            Ok(signal) => info!("{signal} received, the server is shutting down"),
+            Err(e) => {
+                error!("Cannot listen to termination signals: {e}");
+                return std::future::pending().await;
+            }
+        }
+        servers.stop().await;
+    };
+    let running = async {
+        let _: (_, Result<(), anyhow::Error>, _) = tokio::join!(
+            // Running HTTP server
+            async {
+                for server in servers.iter() {
+                    let name = match server.routes() {
+                        Routes::All if servers.admin.is_some() => "Admin server",
+                        _ => "Server",
+                    };
+                    for listener in server.listeners() {
+                        let url = listener.url(server.data().tls.is_some());
+                        info!("{name} running on {url}");
+                    }
+                }
+                servers
+                    .run()
+                    .await
+                    .map_err(|e| error!("Cannot run the server: {e}"))
+                    .is_err()
+                    .then(|| warn!("Server has been disconnected"));
+            },
+            // Listening to UNIX socket commands
+            async {
+                loop {
+                    match listener.receive().await {
+                        Ok(req) => {
+                            if let Ok(req) = serde_json::from_str::<Request>(&req) {
+                                let mut res = Response::new();
+                                let peer_uid = listener.peer_uid();
 
-                            RequestHandler::new(&server, req, &mut res).handle().await;
+                                RequestHandler::new(&servers, req, peer_uid, &mut res)
+                                    .handle()
+                                    .await;
 
-                            if let Err(e) =
-                                listener.send(&format!("{}", serde_json::json!(res))).await
-                            {
-                                error!("Could send to unix stream: {e}");
+                                if let Err(e) =
+                                    listener.send(&format!("{}", serde_json::json!(res))).await
+                                {
+                                    error!("Could send to unix stream: {e}");
+                                }
                             }
                         }
+                        Err(e) => {
+                            error!("{e}");
+                        }
+                    };
+                }
+            },
+            // Reloading configs on SIGHUP
+            async {
+                while reload_signal.recv().await.is_some() {
+                    info!("SIGHUP received, reloading configs");
+                    match RuntimeConfigs::load(&args) {
+                        Ok(configs) => configs.apply(&servers).await,
+                        Err(e) => error!("Configs are rejected, the current ones are kept: {e}"),
                     }
-                    Err(e) => {
-                        error!("{e}");
-                    }
-                };
+                }
             }
-        }
-    );
+        );
+    };
+    // Other tasks are dropped once the servers are stopped by a signal
+    tokio::select! {
+        _ = running => (),
+        _ = shutdown => (),
+    }
+
+    if let Err(e) = listener.close() {
+        error!("Cannot remove unix socket '{UDS_ADDRESS}': {e}");
+    }
+    info!("vnsd has been shut down");
+    Logger::flush();
     std::process::ExitCode::SUCCESS
 }
+
+/// Migrate the authentication database to `version` without running the server
+fn migrate(version: i32) -> std::process::ExitCode {
+    match InitDatabase::connect().and_then(|db| migrations::migrate_to(db.conn(), version)) {
+        Ok(changed) if changed.is_empty() => {
+            info!("Authentication database is already at version {version}");
+            std::process::ExitCode::SUCCESS
+        }
+        Ok(changed) => {
+            info!("Authentication database migrated to version {version}, migrations {changed:?} were applied or reverted");
+            std::process::ExitCode::SUCCESS
+        }
+        Err(e) => {
+            error!("Cannot migrate authentication database: {e}");
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
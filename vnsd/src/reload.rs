@@ -0,0 +1,96 @@
+use crate::{
+    api::auth::ip::AllowList,
+    cli::Args,
+    server::{Server, ServerAddr, Servers},
+};
+use app::{Configs, Logger};
+use log::{error, info, warn, LevelFilter};
+
+/// Configs of the configuration file that are checked before any of them is put in force
+pub struct RuntimeConfigs {
+    configs: Configs,
+    log_level: LevelFilter,
+    allow_list: AllowList,
+    /// Listeners of the public server
+    pub public: ServerAddr,
+    /// Listeners of the admin server, `None` when it's disabled
+    pub admin: Option<ServerAddr>,
+}
+
+impl RuntimeConfigs {
+    /// Read the configuration file, `--ip` and `--port` replace listeners of the public server
+    pub fn load(args: &Args) -> Result<Self, String> {
+        let configs =
+            Configs::init().map_err(|e| format!("Cannot read configuration file: {e}"))?;
+        let server = configs.server();
+        let public = match (&args.ip, args.port) {
+            (None, None) => ServerAddr::from_configs(&server.listeners())
+                .map_err(|e| format!("Invalid listeners: {e}"))?,
+            (ip, port) => ServerAddr::new(
+                &ip.clone().unwrap_or(server.ip()),
+                port.unwrap_or(server.port()),
+            ),
+        };
+        let admin = match server.admin() {
+            admin if admin.enabled() => Some(
+                ServerAddr::from_configs(&admin.listeners())
+                    .map_err(|e| format!("Invalid listeners of the admin server: {e}"))?,
+            ),
+            _ => None,
+        };
+        Ok(Self {
+            log_level: configs.log().level_filter()?,
+            allow_list: AllowList::new(&configs.security().allow_list())?,
+            configs,
+            public,
+            admin,
+        })
+    }
+
+    /// Put the configs in force before the servers are initialized
+    pub fn init(&self) {
+        Logger::set_level(self.log_level);
+        Configs::set_current(self.configs.clone());
+    }
+
+    /// Put the configs in force while the servers are running: the log level, `auth` and
+    /// `security` configs, and listeners, a server is rebound when its listeners are changed.
+    /// Enabling or disabling the admin server and `tls` configs need a restart.
+    pub async fn apply(self, servers: &Servers) {
+        let current = Configs::current().ok();
+        if current.is_some_and(|c| c.server().tls() != self.configs.server().tls()) {
+            warn!("Changes of 'tls' configs are applied after a restart");
+        }
+        if self.admin.is_some() != servers.admin.is_some() {
+            warn!("Enabling or disabling the admin server is applied after a restart");
+        }
+        self.init();
+        let data = servers.public.data();
+        data.rate_limiter.set_limits(&self.configs.security());
+        *data.allow_list.write().unwrap() = self.allow_list;
+
+        rebind(&servers.public, self.public).await;
+        if let (Some(server), Some(addr)) = (&servers.admin, self.admin) {
+            rebind(server, addr).await;
+        }
+        info!("Configs have been reloaded");
+    }
+}
+
+async fn rebind(server: &Server, addr: ServerAddr) {
+    if server.addr() == addr || server.status().is_inactive() {
+        return;
+    }
+    let tls = server.data().tls.is_some();
+    match server.rebind(addr).await {
+        Ok(()) => {
+            for listener in server.listeners() {
+                info!("Server has been rebound to {}", listener.url(tls));
+            }
+        }
+        Err(e) if server.status().is_inactive() => {
+            error!("Cannot rebind the server, it has been stopped: {e}")
+        }
+        Err(e) => error!("Cannot rebind the server, it listens on the previous addresses: {e}"),
+    }
+}
@@ -0,0 +1,178 @@
+use super::{query::*, schema::schema_migrations, Info, Statements};
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use diesel::{
+    connection::SimpleConnection,
+    dsl::sql_query,
+    insert_into,
+    prelude::{Connection, SqliteConnection},
+    sql_types::{BigInt, Text},
+    ExpressionMethods, QueryDsl, RunQueryDsl,
+};
+use serde_derive::Serialize;
+use std::io::{self, ErrorKind};
+
+/// Version of the schema used by this build, i.e. version of the latest migration
+pub const DATABASE_VERSION: i32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// A change of the schema, `down` reverts what `up` does
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+#[derive(Queryable, Insertable, Clone, Debug)]
+#[table_name = "schema_migrations"]
+struct AppliedMigration {
+    version: i32,
+    name: String,
+    applied_at: String,
+}
+
+impl AppliedMigration {
+    fn new(migration: &Migration) -> Self {
+        Self {
+            version: migration.version,
+            name: migration.name.to_owned(),
+            applied_at: Local::now().to_rfc2822(),
+        }
+    }
+}
+
+/// A migration and when it was applied, `applied_at` is `None` while it's pending
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MigrationStatus {
+    pub version: i32,
+    pub name: String,
+    pub applied_at: Option<String>,
+}
+
+/// Apply pending migrations, returns versions that were applied
+pub fn run(conn: &SqliteConnection) -> Result<Vec<i32>> {
+    migrate_to(conn, DATABASE_VERSION)
+}
+
+/// Apply or revert migrations until the schema is at `version`, every migration in its own
+/// transaction. Returns versions that were applied or reverted, in order.
+pub fn migrate_to(conn: &SqliteConnection, version: i32) -> Result<Vec<i32>> {
+    if !(0..=DATABASE_VERSION).contains(&version) {
+        return Err(anyhow!(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("Unknown database version {version}, the latest is {DATABASE_VERSION}"),
+        )));
+    }
+    let applied = applied(conn)?
+        .into_iter()
+        .map(|m| m.version)
+        .collect::<Vec<_>>();
+    let mut changed = vec![];
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|m| m.version <= version && !applied.contains(&m.version))
+    {
+        conn.transaction::<_, anyhow::Error, _>(|| {
+            conn.batch_execute(migration.up)?;
+            insert_into(schema_migrations::table)
+                .values(AppliedMigration::new(migration))
+                .execute(conn)?;
+            Ok(())
+        })
+        .map_err(|e| {
+            anyhow!(
+                "Cannot apply migration {} '{}': {e}",
+                migration.version,
+                migration.name
+            )
+        })?;
+        changed.push(migration.version);
+    }
+    for migration in MIGRATIONS
+        .iter()
+        .rev()
+        .filter(|m| m.version > version && applied.contains(&m.version))
+    {
+        conn.transaction::<_, anyhow::Error, _>(|| {
+            conn.batch_execute(migration.down)?;
+            diesel::delete(
+                schema_migrations::table.filter(schema_migrations::version.eq(migration.version)),
+            )
+            .execute(conn)?;
+            Ok(())
+        })
+        .map_err(|e| {
+            anyhow!(
+                "Cannot revert migration {} '{}': {e}",
+                migration.version,
+                migration.name
+            )
+        })?;
+        changed.push(migration.version);
+    }
+
+    // Older builds read the version from `info`
+    sql_query(CREATE_INFO_QUERY).execute(conn)?;
+    Info::set_version(conn, version)?;
+    Ok(changed)
+}
+
+/// Every migration of this build and whether it's applied
+pub fn status(conn: &SqliteConnection) -> Result<Vec<MigrationStatus>> {
+    let applied = applied(conn)?;
+    Ok(MIGRATIONS
+        .iter()
+        .map(|migration| MigrationStatus {
+            version: migration.version,
+            name: migration.name.to_owned(),
+            applied_at: applied
+                .iter()
+                .find(|m| m.version == migration.version)
+                .map(|m| m.applied_at.clone()),
+        })
+        .collect())
+}
+
+/// Migrations recorded in `schema_migrations`. Databases made before migrations were
+/// recorded have their version in `info`, migrations up to it are recorded as applied.
+fn applied(conn: &SqliteConnection) -> Result<Vec<AppliedMigration>> {
+    if !table_exists(conn, "schema_migrations")? {
+        let legacy_version = match table_exists(conn, "info")? {
+            true => {
+                Info::find(conn, |i| i.key() == "db_version").and_then(|i| i.value().parse().ok())
+            }
+            false => None,
+        };
+        conn.transaction::<_, anyhow::Error, _>(|| {
+            sql_query(CREATE_SCHEMA_MIGRATIONS_QUERY).execute(conn)?;
+            if let Some(legacy_version) = legacy_version {
+                let baseline = MIGRATIONS
+                    .iter()
+                    .filter(|m| m.version <= legacy_version)
+                    .map(AppliedMigration::new)
+                    .collect::<Vec<_>>();
+                insert_into(schema_migrations::table)
+                    .values(&baseline)
+                    .execute(conn)?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(schema_migrations::table
+        .order(schema_migrations::version)
+        .load::<AppliedMigration>(conn)?)
+}
+
+fn table_exists(conn: &SqliteConnection, name: &str) -> Result<bool> {
+    #[derive(QueryableByName)]
+    struct Count {
+        #[sql_type = "BigInt"]
+        count: i64,
+    }
+    let tables =
+        sql_query("SELECT COUNT(*) AS count FROM sqlite_master WHERE type = 'table' AND name = ?")
+            .bind::<Text, _>(name)
+            .get_result::<Count>(conn)?;
+    Ok(tables.count > 0)
+}
@@ -1,24 +1,30 @@
 use app::MainDirectory;
 
+pub mod migrations;
 mod model;
 mod query;
 pub mod schema;
 mod tests;
 
 pub use model::*;
-use query::*;
 
 use anyhow::{anyhow, Result};
 use diesel::{
-    dsl::sql_query,
     prelude::{Connection, SqliteConnection},
-    RunQueryDsl,
+    r2d2::{ConnectionManager, Pool},
 };
+use libvnstat::{db::pool::DEFAULT_BUSY_TIMEOUT, ConnectionOptions};
+use log::info;
 use std::{
     fs::{create_dir_all, File},
     path::Path,
 };
 
+const DATABASE_POOL_SIZE: u32 = 4;
+
+/// Shared connections to the authentication database
+pub type DatabasePool = Pool<ConnectionManager<SqliteConnection>>;
+
 pub struct InitDatabase {
     pub conn: SqliteConnection,
 }
@@ -26,46 +32,43 @@ pub struct InitDatabase {
 /// Initialization of the authentication database, i.e. its creation and creation of its tables
 impl InitDatabase {
     pub fn connect() -> Result<Self> {
-        let path = match DatabaseFile::new()?.create_if_not_exists() {
-            Err(e) => {
-                return Err(anyhow!(std::io::Error::new(
-                    e.downcast_ref::<std::io::Error>().unwrap().kind(),
-                    format!("Cannot create database file: {e}")
-                )))
-            }
-            Ok(f) => f.path(),
-        };
         Ok(Self {
-            conn: SqliteConnection::establish(&path)?,
+            conn: SqliteConnection::establish(&Self::file_path()?)?,
         })
     }
 
-    pub fn init(&self) -> Result<()> {
-        sql_query(CREATE_INFO_QUERY).execute(&self.conn)?;
-
-        Info::setup(&self.conn);
-        let db_version = Info::find(&self.conn, |i| i.key() == "db_version")
-            .unwrap()
-            .value();
-
-        if DATABASE_VERSION > db_version.parse()? {
-            let tables = vec!["connections", "keys", "info"];
+    /// Pool of connections to the database, it is migrated once here
+    /// instead of every time a connection is taken.
+    pub fn pool() -> Result<DatabasePool> {
+        let pool = Pool::builder()
+            .max_size(DATABASE_POOL_SIZE)
+            .connection_customizer(Box::new(ConnectionOptions {
+                read_only: false,
+                busy_timeout: DEFAULT_BUSY_TIMEOUT,
+            }))
+            .build(ConnectionManager::new(Self::file_path()?))?;
+        Self::migrate(&*pool.get()?)?;
+        Ok(pool)
+    }
 
-            for t in tables.iter() {
-                sql_query(&format!("DROP TABLE IF EXISTS {t}")).execute(&self.conn)?;
-            }
-            sql_query(CREATE_INFO_QUERY).execute(&self.conn)?;
-            Info::setup(&self.conn);
+    fn file_path() -> Result<String> {
+        match DatabaseFile::new()?.create_if_not_exists() {
+            Err(e) => Err(anyhow!(std::io::Error::new(
+                e.downcast_ref::<std::io::Error>().unwrap().kind(),
+                format!("Cannot create database file: {e}")
+            ))),
+            Ok(f) => Ok(f.path()),
         }
+    }
+
+    pub fn init(&self) -> Result<()> {
+        Self::migrate(&self.conn)
+    }
 
-        for q in [
-            CREATE_KEYS_QUERY,
-            CREATE_CONNECTIONS_QUERY,
-            CREATE_BLOCK_LIST_QUERY,
-        ]
-        .iter()
-        {
-            sql_query(*q).execute(&self.conn)?;
+    /// Apply pending migrations, data of older versions is kept
+    fn migrate(conn: &SqliteConnection) -> Result<()> {
+        for version in migrations::run(conn)? {
+            info!("Authentication database migrated to version {version}");
         }
         Ok(())
     }
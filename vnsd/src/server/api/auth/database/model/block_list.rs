@@ -1,11 +1,15 @@
-use super::traits::*;
-use crate::server::api::auth::database::{schema::block_list, Statements};
+use crate::server::api::auth::{
+    database::{schema::block_list, Statements},
+    ip::{parse_client, parse_range},
+};
 use anyhow::Result;
-use chrono::Local;
-use diesel::{insert_into, EqAll, QueryDsl, RunQueryDsl, SqliteConnection};
-use regex::Regex;
+use chrono::{DateTime, Duration, Local};
+use diesel::{
+    insert_into, Connection, EqAll, ExpressionMethods, QueryDsl, RunQueryDsl, SqliteConnection,
+};
 use serde_derive::Serialize;
 
+#[derive(Debug)]
 pub struct BlockError {
     pub kind: BlockErrorKinds,
     pub details: String,
@@ -20,6 +24,12 @@ impl BlockError {
     }
 }
 
+impl From<diesel::result::Error> for BlockError {
+    fn from(err: diesel::result::Error) -> Self {
+        Self::new(BlockErrorKinds::Database, &err.to_string())
+    }
+}
+
 impl std::fmt::Display for BlockError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.details)
@@ -30,60 +40,62 @@ impl std::fmt::Display for BlockError {
 pub enum BlockErrorKinds {
     AlreadyBlocked,
     AlreadyUnBlocked,
-    InvliadIPv4Pattern,
+    InvalidAddress,
+    Database,
 }
 
 #[derive(Queryable, Insertable, Clone, Debug, PartialEq, Serialize)]
 #[table_name = "block_list"]
 pub struct BlockList {
     pub id: i32,
+    /// An IPv4/IPv6 address, or a CIDR range like `203.0.113.0/24`
     pub ip_addr: String,
     pub blocked_at: String,
+    /// The block is permanent when it's `None`
+    pub expires_at: Option<String>,
+    pub reason: Option<String>,
 }
 
 impl BlockList {
-    pub fn new(conn: &SqliteConnection, addr: &str) -> Result<Self, BlockError> {
-        let pattern = Regex::new(r"^((25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.){3}(25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)$").unwrap();
-        if !pattern.is_match(addr) {
-            return Err(BlockError::new(
-                BlockErrorKinds::InvliadIPv4Pattern,
-                "Invalid IPv4 address pattern",
-            ));
-        }
-        let last_id = match block_list::table.load::<Self>(conn) {
-            Err(_) => 0,
-            Ok(keys) => match keys.last() {
-                Some(key) => key.id,
-                None => 0,
-            },
-        };
-        Ok(Self {
-            id: last_id + 1,
-            ip_addr: addr.to_owned(),
-            blocked_at: Local::now().to_rfc2822(),
-        })
+    pub fn block(conn: &SqliteConnection, addr: &str) -> Result<(), BlockError> {
+        Self::block_for(conn, addr, None, None)
     }
 
-    pub fn block(conn: &SqliteConnection, addr: &str) -> Result<(), BlockError> {
-        if Self::find(conn, |l| l.ip_addr == addr).is_some() {
+    /// Block `addr` for `duration`, or permanently when it's `None`
+    pub fn block_for(
+        conn: &SqliteConnection,
+        addr: &str,
+        duration: Option<Duration>,
+        reason: Option<&str>,
+    ) -> Result<(), BlockError> {
+        let addr = &Self::normalize(addr)?;
+        if Self::find(conn, |l| l.ip_addr == *addr && !l.is_expired()).is_some() {
             return Err(BlockError::new(
                 BlockErrorKinds::AlreadyBlocked,
                 &format!("IP address already blocked"),
             ));
         }
-        Self::new(conn, addr)?.create(conn).unwrap();
+        let expires_at = duration.map(|d| (Local::now() + d).to_rfc2822());
+
+        // Expired blocks of `addr` are replaced, ids are assigned by SQLite
+        // so concurrent blocks of different addresses don't conflict
+        conn.transaction(|| {
+            diesel::delete(block_list::table.filter(block_list::ip_addr.eq_all(addr)))
+                .execute(conn)?;
+            insert_into(block_list::table)
+                .values((
+                    block_list::ip_addr.eq(addr),
+                    block_list::blocked_at.eq(Local::now().to_rfc2822()),
+                    block_list::expires_at.eq(expires_at),
+                    block_list::reason.eq(reason),
+                ))
+                .execute(conn)
+        })?;
         Ok(())
     }
     pub fn unblock(conn: &SqliteConnection, addr: &str) -> Result<(), BlockError> {
-        let pattern = Regex::new(r"^((25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.){3}(25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)$").unwrap();
-        if !pattern.is_match(addr) {
-            return Err(BlockError::new(
-                BlockErrorKinds::InvliadIPv4Pattern,
-                "Invalid IPv4 address pattern",
-            ));
-        }
-
-        if Self::find(conn, |l| l.ip_addr == addr).is_none() {
+        let addr = &Self::normalize(addr)?;
+        if Self::find(conn, |l| l.ip_addr == *addr && !l.is_expired()).is_none() {
             return Err(BlockError::new(
                 BlockErrorKinds::AlreadyUnBlocked,
                 &format!("IP address already un-blocked"),
@@ -91,24 +103,56 @@ impl BlockList {
         }
 
         use crate::server::api::auth::database::schema::block_list::dsl::*;
-        diesel::delete(block_list.filter(ip_addr.eq_all(addr)))
-            .execute(conn)
-            .unwrap();
+        diesel::delete(block_list.filter(ip_addr.eq_all(addr))).execute(conn)?;
         Ok(())
     }
+    /// Whether `ip_addr` is blocked by itself or by a range that contains it
     pub fn is_blocked(conn: &SqliteConnection, ip_addr: &str) -> bool {
-        Self::find(conn, |item| item.ip_addr == ip_addr).is_some()
+        let ip = parse_client(ip_addr);
+        Self::find(conn, |item| {
+            let matches = match (ip, parse_range(&item.ip_addr)) {
+                (Some(ip), Some(range)) => range.contains(&ip),
+                _ => item.ip_addr == ip_addr,
+            };
+            matches && !item.is_expired()
+        })
+        .is_some()
     }
-}
 
-impl Create for BlockList {
-    type Output = Self;
+    /// Address or range as it's stored, e.g. `203.0.113.7/24` is `203.0.113.0/24`
+    /// and `2001:DB8::1` is `2001:db8::1`
+    fn normalize(addr: &str) -> Result<String, BlockError> {
+        match parse_range(addr) {
+            Some(range) if range.prefix_len() == range.max_prefix_len() => {
+                Ok(range.addr().to_string())
+            }
+            Some(range) => Ok(range.to_string()),
+            None => Err(BlockError::new(
+                BlockErrorKinds::InvalidAddress,
+                "Invalid IP address or CIDR range",
+            )),
+        }
+    }
 
-    fn create(&self, conn: &SqliteConnection) -> Result<Self::Output> {
-        use crate::server::api::auth::database::schema::block_list::dsl::*;
-        insert_into(block_list).values(self).execute(conn)?;
+    /// Remove expired blocks, returns how many were removed
+    pub fn purge_expired(conn: &SqliteConnection) -> Result<usize> {
+        let expired = Self::select(conn, |item| item.is_expired())
+            .into_iter()
+            .map(|item| item.id)
+            .collect::<Vec<_>>();
+        if expired.is_empty() {
+            return Ok(0);
+        }
+        let purged = diesel::delete(block_list::table.filter(block_list::id.eq_any(expired)))
+            .execute(conn)?;
+        Ok(purged)
+    }
 
-        Ok(self.clone())
+    pub fn is_expired(&self) -> bool {
+        match self.expires_at.as_deref().map(DateTime::parse_from_rfc2822) {
+            Some(Ok(expires_at)) => expires_at <= Local::now(),
+            _ => false,
+        }
     }
 }
 
@@ -144,7 +188,7 @@ mod tests {
     #[allow(unused_imports)]
     use super::*;
     #[allow(unused_imports)]
-    use crate::api::auth::*;
+    use crate::api::auth::{database::InitDatabase, *};
     #[test]
     async fn block_nvalid_ip_address() {
         let db = InitDatabase::connect().unwrap();
@@ -165,7 +209,7 @@ mod tests {
         ];
         for addr in invalid_addresses {
             assert_eq!(
-                BlockErrorKinds::InvliadIPv4Pattern,
+                BlockErrorKinds::InvalidAddress,
                 BlockList::block(db.conn(), addr).unwrap_err().kind
             );
         }
@@ -189,7 +233,7 @@ mod tests {
         ];
         for addr in invalid_addresses {
             assert_eq!(
-                BlockErrorKinds::InvliadIPv4Pattern,
+                BlockErrorKinds::InvalidAddress,
                 BlockList::block(db.conn(), addr).unwrap_err().kind
             );
         }
@@ -0,0 +1,150 @@
+use crate::server::api::auth::database::schema::config_history;
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use diesel::{
+    expression::functions::aggregate_ordering::max, insert_into, Connection, ExpressionMethods,
+    QueryDsl, Queryable, RunQueryDsl, SqliteConnection,
+};
+use libvnstat::{ConfigChange, VnStatConfig};
+use serde_derive::Serialize;
+use std::io::{Error, ErrorKind::NotFound};
+
+/// Connection uuid recorded for changes made by `vns` through the unix socket
+pub const LOCAL_CONN_UUID: &str = "local";
+
+/// A changed option of vnStat configuration, changes of the same edit share a revision
+#[derive(Queryable, Clone, Debug, PartialEq, Serialize)]
+pub struct ConfigHistory {
+    pub id: i32,
+    pub revision: i32,
+    pub prop: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub conn_uuid: String,
+    pub changed_at: String,
+}
+
+/// Every change of a single edit of vnStat configuration
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ConfigRevision {
+    pub revision: i32,
+    pub conn_uuid: String,
+    pub changed_at: String,
+    pub changes: Vec<ConfigChange>,
+}
+
+impl ConfigHistory {
+    /// Apply `props` to vnStat configuration all at once and record them as a new revision.
+    ///
+    /// The file isn't written when recording fails, and the record is rolled back when writing fails.
+    /// `None` is returned when `props` don't change anything.
+    pub fn apply(
+        db: &SqliteConnection,
+        config: &VnStatConfig,
+        conn_uuid: &str,
+        props: &[(&str, Option<&str>)],
+    ) -> Result<Option<ConfigRevision>> {
+        let edit = config.edit(props)?;
+        if edit.changes().is_empty() {
+            return Ok(None);
+        }
+
+        db.transaction::<_, anyhow::Error, _>(|| {
+            use config_history::dsl;
+
+            let revision = Self::latest_revision(db)? + 1;
+            let changed_at = Local::now().to_rfc2822();
+            for change in edit.changes() {
+                insert_into(dsl::config_history)
+                    .values((
+                        dsl::revision.eq(revision),
+                        dsl::prop.eq(&change.prop),
+                        dsl::old_value.eq(&change.old_value),
+                        dsl::new_value.eq(&change.new_value),
+                        dsl::conn_uuid.eq(conn_uuid),
+                        dsl::changed_at.eq(&changed_at),
+                    ))
+                    .execute(db)?;
+            }
+            Ok(Some(ConfigRevision {
+                revision,
+                conn_uuid: conn_uuid.to_owned(),
+                changed_at,
+                changes: edit.save()?,
+            }))
+        })
+    }
+
+    /// Restore options changed after `revision` to the values they had at that revision,
+    /// the rollback itself is recorded as a new revision. Revision `0` is the configuration
+    /// before the first recorded change.
+    pub fn rollback(
+        db: &SqliteConnection,
+        config: &VnStatConfig,
+        conn_uuid: &str,
+        revision: i32,
+    ) -> Result<Option<ConfigRevision>> {
+        if revision < 0 || revision > Self::latest_revision(db)? {
+            return Err(anyhow!(Error::new(
+                NotFound,
+                format!("Revision {revision} is not found")
+            )));
+        }
+
+        // The oldest change of each option after `revision` holds its value at `revision`
+        let mut props: Vec<(String, Option<String>)> = vec![];
+        for change in config_history::table
+            .filter(config_history::revision.gt(revision))
+            .order(config_history::id.asc())
+            .load::<Self>(db)?
+        {
+            if !props.iter().any(|(prop, _)| *prop == change.prop) {
+                props.push((change.prop, change.old_value));
+            }
+        }
+        let props = props
+            .iter()
+            .map(|(prop, value)| (prop.as_str(), value.as_deref()))
+            .collect::<Vec<_>>();
+        Self::apply(db, config, conn_uuid, &props)
+    }
+
+    /// Recorded revisions, the latest first
+    pub fn revisions(db: &SqliteConnection, limit: Option<usize>) -> Result<Vec<ConfigRevision>> {
+        let mut revisions: Vec<ConfigRevision> = vec![];
+        for change in config_history::table
+            .order((config_history::revision.desc(), config_history::id.asc()))
+            .load::<Self>(db)?
+        {
+            let change_of_revision = ConfigChange {
+                prop: change.prop,
+                old_value: change.old_value,
+                new_value: change.new_value,
+            };
+            match revisions.last_mut() {
+                Some(last) if last.revision == change.revision => {
+                    last.changes.push(change_of_revision)
+                }
+                _ => {
+                    if limit.map(|l| revisions.len() >= l).unwrap_or(false) {
+                        break;
+                    }
+                    revisions.push(ConfigRevision {
+                        revision: change.revision,
+                        conn_uuid: change.conn_uuid,
+                        changed_at: change.changed_at,
+                        changes: vec![change_of_revision],
+                    })
+                }
+            }
+        }
+        Ok(revisions)
+    }
+
+    pub fn latest_revision(db: &SqliteConnection) -> Result<i32> {
+        Ok(config_history::table
+            .select(max(config_history::revision))
+            .first::<Option<i32>>(db)?
+            .unwrap_or(0))
+    }
+}
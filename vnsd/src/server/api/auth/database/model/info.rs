@@ -1,12 +1,12 @@
 use crate::server::api::auth::database::{schema::info, Create, Statements};
 use anyhow::Result;
 
-use diesel::{insert_into, Insertable, Queryable, RunQueryDsl, SqliteConnection};
+use diesel::{
+    insert_into, update, ExpressionMethods, Insertable, QueryDsl, Queryable, RunQueryDsl,
+    SqliteConnection,
+};
 use serde_derive::Serialize;
 
-// Database Info
-pub const DATABASE_VERSION: i32 = 1;
-
 #[derive(Queryable, Insertable, Clone, Debug, PartialEq, Serialize)]
 #[table_name = "info"]
 pub struct Info {
@@ -36,15 +36,16 @@ impl Info {
     pub fn value(&self) -> String {
         self.value.clone()
     }
-    pub fn setup(conn: &SqliteConnection) {
-        let expected_info = [("db_version", DATABASE_VERSION.to_string())];
-
-        let exist_info = info::table.load::<Info>(conn).unwrap();
-        for i in expected_info.iter() {
-            if exist_info.iter().find(|e| e.key().eq(i.0)).is_none() {
-                Info::new(i.0, &i.1, conn).create(conn).unwrap();
-            }
+    /// Record `version` as the version of the database
+    pub fn set_version(conn: &SqliteConnection, version: i32) -> Result<()> {
+        if Self::find(conn, |i| i.key() == "db_version").is_none() {
+            Self::new("db_version", &version.to_string(), conn).create(conn)?;
+            return Ok(());
         }
+        update(info::table.filter(info::key.eq("db_version")))
+            .set(info::value.eq(version.to_string()))
+            .execute(conn)?;
+        Ok(())
     }
 }
 
@@ -1,12 +1,22 @@
-use super::{traits::*, Connections};
-use crate::server::api::auth::database::schema::{connections, keys};
-use anyhow::Result;
+use super::{traits::*, Connections, Users};
+use crate::server::api::auth::{
+    database::schema::{connections, keys},
+    scope::Scope,
+};
+use anyhow::{anyhow, Result};
 use app::Configs;
 use chrono::{DateTime, Duration, FixedOffset, Local};
 
-use diesel::{insert_into, Insertable, Queryable, RunQueryDsl, SqliteConnection};
+use diesel::{
+    delete, dsl::sql, insert_into, select, sql_types::Integer, Connection, ExpressionMethods,
+    Insertable, OptionalExtension, QueryDsl, Queryable, RunQueryDsl, SqliteConnection,
+};
 use rand::{distributions::Alphanumeric, Rng};
 use serde_derive::Serialize;
+use std::io::{self, ErrorKind};
+
+/// Days a service key is valid for when its expiry isn't given
+pub const SERVICE_KEY_EXPIRE_DAYS: i64 = 365;
 
 #[derive(Queryable, Insertable, Clone, Debug, PartialEq, Serialize)]
 #[table_name = "keys"]
@@ -16,17 +26,17 @@ pub struct Keys {
     pub created_at: String,
     pub expires_at: String,
     pub conn_uuid: String,
+    /// Owner of the key, `None` for keys issued by the password of `auth` configs
+    pub username: Option<String>,
+    /// Comma separated scopes, see [`Scope`]
+    pub scopes: String,
+    /// Name of a service key, `None` for keys issued by logging in
+    pub label: Option<String>,
 }
 
 impl Keys {
+    /// A new key of the connection of `conn_uuid`, its id is assigned when it's created
     pub fn generate_new_key(conn: &SqliteConnection, conn_uuid: &str) -> Self {
-        let last_id = match keys::table.load::<Self>(conn) {
-            Err(_) => 0,
-            Ok(keys) => match keys.last() {
-                Some(key) => key.id,
-                None => 0,
-            },
-        };
         'outer: loop {
             let value = rand::thread_rng()
                 .sample_iter(&Alphanumeric)
@@ -39,32 +49,157 @@ impl Keys {
                 }
             }
             return Self {
-                id: last_id + 1,
+                id: 0,
                 value,
                 created_at: Local::now().to_rfc2822(),
                 expires_at: match Local::now().checked_add_signed(Duration::days(
-                    Configs::init().unwrap().auth().key_expire_duration(),
+                    Configs::current().unwrap().auth().key_expire_duration(),
                 )) {
                     Some(dt) => dt,
                     None => Local::now(),
                 }
                 .to_rfc2822(),
                 conn_uuid: conn_uuid.to_owned(),
+                username: None,
+                scopes: Scope::join(&Scope::ALL),
+                label: None,
             };
         }
     }
 
-    pub fn is_valid(conn: &SqliteConnection, key_value: &str) -> bool {
-        let keys = keys::table.load::<Self>(conn).unwrap();
-        for key in keys {
-            if key.value().eq(&key_value)
-                && key.conn(conn).is_some()
-                && Local::now() < key.expires_at()
-            {
-                return true;
+    /// Key issued for `username` that's limited to `scopes`
+    pub fn owned_by(mut self, username: Option<&str>, scopes: &[Scope]) -> Self {
+        self.username = username.map(str::to_owned);
+        self.scopes = Scope::join(scopes);
+        self
+    }
+
+    /// Issue a long-lived key named `label`, e.g. for a dashboard or a script.
+    /// It has a connection of its own, so revoking it doesn't affect other keys.
+    ///
+    /// It's owned by the user of `issuer`, and it cannot have scopes that `issuer` lacks.
+    /// Keys issued locally have no issuer.
+    pub fn issue(
+        conn: &SqliteConnection,
+        label: &str,
+        issuer: Option<&Self>,
+        scopes: &[Scope],
+        valid_for: Option<Duration>,
+        ip_addr: &str,
+    ) -> Result<Self> {
+        let invalid = |details: &str| anyhow!(io::Error::new(ErrorKind::InvalidInput, details));
+        let label = label.trim();
+        if label.is_empty() || label.chars().count() > 64 {
+            return Err(invalid("Label must have 1 to 64 characters"));
+        }
+        if scopes.is_empty() {
+            return Err(invalid("A key needs one scope at least"));
+        }
+        if let Some(issuer) = issuer {
+            let granted = issuer.granted_scopes(conn);
+            if let Some(scope) = scopes.iter().find(|s| !granted.contains(s)) {
+                return Err(anyhow!(io::Error::new(
+                    ErrorKind::PermissionDenied,
+                    format!("Scope '{scope}' isn't granted to the issuer")
+                )));
             }
         }
-        false
+        let valid_for = valid_for.unwrap_or_else(|| Duration::days(SERVICE_KEY_EXPIRE_DAYS));
+        let expires_at = match Local::now().checked_add_signed(valid_for) {
+            Some(expires_at) if valid_for > Duration::zero() => expires_at,
+            _ => return Err(invalid("Invalid expiry of the key")),
+        };
+
+        let connection =
+            Connections::new(ip_addr, &format!("service key '{label}'")).create(conn)?;
+        let username = issuer.and_then(|k| k.username.as_deref());
+        let mut key = Self::generate_new_key(conn, &connection.uuid()).owned_by(username, scopes);
+        key.label = Some(label.to_owned());
+        key.expires_at = expires_at.to_rfc2822();
+        key.create(conn)
+    }
+
+    /// Valid keys, only of the connection of `conn_uuid` when it's given
+    pub fn active(conn: &SqliteConnection, conn_uuid: Option<&str>) -> Vec<Self> {
+        Self::select(conn, |key| {
+            conn_uuid.is_none_or(|uuid| key.conn_uuid == uuid)
+                && Local::now() < key.expires_at()
+                && key.conn(conn).is_some()
+        })
+    }
+
+    /// Revoke the key of `id`, it's refused right away
+    pub fn revoke(conn: &SqliteConnection, id: i32) -> Result<()> {
+        match delete(keys::table.filter(keys::id.eq(id))).execute(conn)? {
+            0 => Err(anyhow!(io::Error::new(
+                ErrorKind::NotFound,
+                format!("Key {id} is not found")
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Revoke every key of the connection of `conn_uuid`, returns how many were revoked
+    pub fn revoke_connection(conn: &SqliteConnection, conn_uuid: &str) -> Result<usize> {
+        if Connections::find(conn, |c| c.uuid == conn_uuid).is_none() {
+            return Err(anyhow!(io::Error::new(
+                ErrorKind::NotFound,
+                format!("Connection '{conn_uuid}' is not found")
+            )));
+        }
+        Ok(delete(keys::table.filter(keys::conn_uuid.eq(conn_uuid))).execute(conn)?)
+    }
+
+    /// The key with its value hidden but its first characters, so it can be listed
+    pub fn masked(mut self) -> Self {
+        self.value = format!("{}...", self.value.chars().take(4).collect::<String>());
+        self
+    }
+
+    pub fn is_valid(conn: &SqliteConnection, key_value: &str) -> bool {
+        Self::valid(conn, key_value).is_some()
+    }
+
+    /// The key of `key_value` if it exists, whether it's valid or not
+    pub fn find_by_value(conn: &SqliteConnection, key_value: &str) -> Result<Option<Self>> {
+        Ok(keys::table
+            .filter(keys::value.eq(key_value))
+            .first::<Self>(conn)
+            .optional()?)
+    }
+
+    /// The key of `key_value` if it exists, its connection exists and it isn't expired
+    pub fn valid(conn: &SqliteConnection, key_value: &str) -> Option<Self> {
+        Self::find_by_value(conn, key_value)
+            .ok()
+            .flatten()
+            .filter(|key| key.conn(conn).is_some() && Local::now() < key.expires_at())
+    }
+
+    /// Revoke keys issued by the password of `auth` configs, e.g. when it's changed.
+    /// Service keys that aren't owned by a user are revoked with them.
+    pub fn revoke_password_keys(conn: &SqliteConnection) -> Result<usize> {
+        Ok(delete(keys::table.filter(keys::username.is_null())).execute(conn)?)
+    }
+
+    pub fn scopes(&self) -> Vec<Scope> {
+        Scope::parse_list(&self.scopes).unwrap_or_default()
+    }
+
+    /// Scopes of the key that are still granted by the role of its owner,
+    /// keys of removed users have none.
+    pub fn granted_scopes(&self, conn: &SqliteConnection) -> Vec<Scope> {
+        let granted = match &self.username {
+            None => &Scope::ALL[..],
+            Some(username) => match Users::find(conn, |u| &u.username == username) {
+                Some(user) => user.role().scopes(),
+                None => &[],
+            },
+        };
+        self.scopes()
+            .into_iter()
+            .filter(|s| granted.contains(s))
+            .collect()
     }
 
     pub fn value(&self) -> String {
@@ -78,12 +213,10 @@ impl Keys {
     }
 
     pub fn conn(&self, conn: &SqliteConnection) -> Option<Connections> {
-        for connection in connections::table.load::<Connections>(conn).unwrap() {
-            if connection.uuid().eq(&self.conn_uuid) {
-                return Some(connection);
-            }
-        }
-        None
+        connections::table
+            .filter(connections::uuid.eq(&self.conn_uuid))
+            .first::<Connections>(conn)
+            .ok()
     }
 }
 
@@ -95,15 +228,32 @@ impl std::default::Default for Keys {
             created_at: Local::now().to_rfc2822(),
             expires_at: Local::now().to_rfc2822(),
             conn_uuid: "UNKNOWN".to_owned(),
+            username: None,
+            scopes: Scope::join(&Scope::ALL),
+            label: None,
         }
     }
 }
 
 impl Create for Keys {
     type Output = Self;
+    /// Insert the key, its id is assigned by SQLite so concurrent keys don't conflict
     fn create(&self, conn: &SqliteConnection) -> Result<Self::Output> {
-        insert_into(keys::table).values(self).execute(conn)?;
-        Ok(self.clone())
+        conn.transaction(|| {
+            insert_into(keys::table)
+                .values((
+                    keys::value.eq(&self.value),
+                    keys::created_at.eq(&self.created_at),
+                    keys::expires_at.eq(&self.expires_at),
+                    keys::conn_uuid.eq(&self.conn_uuid),
+                    keys::username.eq(&self.username),
+                    keys::scopes.eq(&self.scopes),
+                    keys::label.eq(&self.label),
+                ))
+                .execute(conn)?;
+            let id = select(sql::<Integer>("last_insert_rowid()")).get_result(conn)?;
+            Ok(Self { id, ..self.clone() })
+        })
     }
 }
 
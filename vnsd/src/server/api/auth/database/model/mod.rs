@@ -1,11 +1,15 @@
 mod block_list;
+mod config_history;
 mod connections;
 mod info;
 mod keys;
 mod traits;
+mod users;
 
 pub use block_list::*;
+pub use config_history::*;
 pub use connections::*;
 pub use info::*;
 pub use keys::*;
 pub use traits::*;
+pub use users::*;
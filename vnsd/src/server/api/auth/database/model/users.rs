@@ -0,0 +1,116 @@
+use super::traits::*;
+use crate::server::api::auth::{
+    database::schema::{keys, users},
+    password,
+    scope::Role,
+};
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use diesel::{
+    delete, insert_into, ExpressionMethods, QueryDsl, Queryable, RunQueryDsl, SqliteConnection,
+};
+use regex::Regex;
+use serde_derive::Serialize;
+use std::io::{
+    Error,
+    ErrorKind::{AlreadyExists, InvalidInput, NotFound},
+};
+
+#[derive(Queryable, Clone, Debug, PartialEq, Serialize)]
+pub struct Users {
+    pub id: i32,
+    pub username: String,
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    pub role: String,
+    pub created_at: String,
+}
+
+impl Users {
+    /// Add a user, its password is stored hashed
+    pub fn add(
+        conn: &SqliteConnection,
+        username: &str,
+        password: &str,
+        role: Role,
+    ) -> Result<Self> {
+        let pattern = Regex::new(r"^[A-Za-z0-9_.-]{1,32}$").unwrap();
+        if !pattern.is_match(username) {
+            return Err(anyhow!(Error::new(
+                InvalidInput,
+                "Username must be 1 to 32 letters, digits, '_', '.' or '-'"
+            )));
+        }
+        if password.is_empty() {
+            return Err(anyhow!(Error::new(InvalidInput, "Password can't be empty")));
+        }
+        if Self::find(conn, |u| u.username == username).is_some() {
+            return Err(anyhow!(Error::new(
+                AlreadyExists,
+                format!("User '{username}' already exists")
+            )));
+        }
+
+        insert_into(users::table)
+            .values((
+                users::username.eq(username),
+                users::password_hash.eq(password::hash(password)),
+                users::role.eq(role.to_string()),
+                users::created_at.eq(Local::now().to_rfc2822()),
+            ))
+            .execute(conn)?;
+        Ok(Self::find(conn, |u| u.username == username).unwrap())
+    }
+
+    /// Remove a user with its keys
+    pub fn remove(conn: &SqliteConnection, username: &str) -> Result<()> {
+        if Self::find(conn, |u| u.username == username).is_none() {
+            return Err(anyhow!(Error::new(
+                NotFound,
+                format!("User '{username}' is not found")
+            )));
+        }
+        delete(keys::table.filter(keys::username.eq(username))).execute(conn)?;
+        delete(users::table.filter(users::username.eq(username))).execute(conn)?;
+        Ok(())
+    }
+
+    /// The user of `username` if `password` is its password
+    pub fn authenticate(conn: &SqliteConnection, username: &str, password: &str) -> Option<Self> {
+        Self::find(conn, |u| u.username == username)
+            .filter(|user| password::verify(password, &user.password_hash))
+    }
+
+    pub fn role(&self) -> Role {
+        // Roles are validated before they're stored
+        self.role.parse().unwrap_or(Role::Viewer)
+    }
+}
+
+impl Statements for Users {
+    type Args = Self;
+    type SelectOutput = Vec<Self>;
+    type FindOutput = Option<Self>;
+    fn select<F>(conn: &SqliteConnection, f: F) -> Self::SelectOutput
+    where
+        F: Fn(Self::Args) -> bool,
+    {
+        users::table
+            .load::<Self>(conn)
+            .unwrap()
+            .into_iter()
+            .filter(|e| f(e.clone()))
+            .collect::<Self::SelectOutput>()
+    }
+
+    fn find<F>(conn: &SqliteConnection, f: F) -> Self::FindOutput
+    where
+        F: Fn(Self::Args) -> bool,
+    {
+        users::table
+            .load::<Self>(conn)
+            .unwrap()
+            .into_iter()
+            .find(|e| f(e.clone()))
+    }
+}
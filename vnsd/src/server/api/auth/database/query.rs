@@ -1,26 +1,5 @@
-// ! Changeable
+use super::migrations::Migration;
 
-pub const CREATE_KEYS_QUERY: &str = r#"
-    CREATE TABLE IF NOT EXISTS keys (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        value TEXT,
-        created_at DATE,
-        expires_at DATE,
-        conn_uuid String NOT NULL,
-        CONSTRAINT fk_conn
-            FOREIGN KEY (conn_uuid)
-            REFERENCES connections(uuid)
-    );
-"#;
-
-pub const CREATE_CONNECTIONS_QUERY: &str = r#"
-    CREATE TABLE IF NOT EXISTS connections (
-        uuid TEXT PRIMARY KEY,
-        ip_addr TEXT,
-        user_agent TEXT,
-        connected_at DATE
-    );
-"#;
 pub const CREATE_INFO_QUERY: &str = r#"
     CREATE TABLE IF NOT EXISTS info (
         id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -28,10 +7,96 @@ pub const CREATE_INFO_QUERY: &str = r#"
         value TEXT
     );
 "#;
-pub const CREATE_BLOCK_LIST_QUERY: &str = r#"
-    CREATE TABLE IF NOT EXISTS block_list (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        ip_addr TEXT,
-        blocked_at DATE
+pub const CREATE_SCHEMA_MIGRATIONS_QUERY: &str = r#"
+    CREATE TABLE IF NOT EXISTS schema_migrations (
+        version INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        applied_at DATE NOT NULL
     );
 "#;
+
+/// Changes of the schema in order, a released migration must never be changed.
+/// Change the schema by a new migration instead, and update `schema.rs` with it.
+pub const MIGRATIONS: [Migration; 5] = [
+    Migration {
+        version: 1,
+        name: "create_tables",
+        up: r#"
+            CREATE TABLE IF NOT EXISTS connections (
+                uuid TEXT PRIMARY KEY,
+                ip_addr TEXT,
+                user_agent TEXT,
+                connected_at DATE
+            );
+            CREATE TABLE IF NOT EXISTS keys (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                value TEXT,
+                created_at DATE,
+                expires_at DATE,
+                conn_uuid String NOT NULL,
+                CONSTRAINT fk_conn
+                    FOREIGN KEY (conn_uuid)
+                    REFERENCES connections(uuid)
+            );
+            CREATE TABLE IF NOT EXISTS block_list (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                ip_addr TEXT,
+                blocked_at DATE
+            );
+        "#,
+        down: r#"
+            DROP TABLE block_list;
+            DROP TABLE keys;
+            DROP TABLE connections;
+        "#,
+    },
+    Migration {
+        version: 2,
+        name: "add_config_history_and_users",
+        // Config history may exist already, it was added without changing the version
+        up: r#"
+            CREATE TABLE IF NOT EXISTS config_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                revision INTEGER NOT NULL,
+                prop TEXT NOT NULL,
+                old_value TEXT,
+                new_value TEXT,
+                conn_uuid TEXT NOT NULL,
+                changed_at DATE
+            );
+            CREATE TABLE IF NOT EXISTS users (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                username TEXT NOT NULL UNIQUE,
+                password_hash TEXT NOT NULL,
+                role TEXT NOT NULL,
+                created_at DATE
+            );
+            ALTER TABLE keys ADD COLUMN username TEXT;
+            ALTER TABLE keys ADD COLUMN scopes TEXT NOT NULL DEFAULT 'read,config,daemon,admin';
+        "#,
+        down: r#"
+            ALTER TABLE keys DROP COLUMN scopes;
+            ALTER TABLE keys DROP COLUMN username;
+            DROP TABLE users;
+            DROP TABLE config_history;
+        "#,
+    },
+    Migration {
+        version: 3,
+        name: "add_block_list_expiry",
+        up: "ALTER TABLE block_list ADD COLUMN expires_at DATE;",
+        down: "ALTER TABLE block_list DROP COLUMN expires_at;",
+    },
+    Migration {
+        version: 4,
+        name: "add_block_list_reason",
+        up: "ALTER TABLE block_list ADD COLUMN reason TEXT;",
+        down: "ALTER TABLE block_list DROP COLUMN reason;",
+    },
+    Migration {
+        version: 5,
+        name: "add_key_labels",
+        up: "ALTER TABLE keys ADD COLUMN label TEXT;",
+        down: "ALTER TABLE keys DROP COLUMN label;",
+    },
+];
@@ -9,13 +9,16 @@ table! {
 }
 
 table! {
-    use diesel::sql_types::{Date,Text,Integer};
+    use diesel::sql_types::{Date,Text,Integer,Nullable};
     keys (id) {
         id -> Integer,
         value -> Text,
         created_at -> Date,
         expires_at -> Date,
         conn_uuid -> Text,
+        username -> Nullable<Text>,
+        scopes -> Text,
+        label -> Nullable<Text>,
     }
 }
 table! {
@@ -27,10 +30,42 @@ table! {
     }
 }
 table! {
-    use diesel::sql_types::{Text,Date,Integer};
+    use diesel::sql_types::{Text,Date,Integer,Nullable};
     block_list {
         id -> Integer,
         ip_addr -> Text,
         blocked_at -> Date,
+        expires_at -> Nullable<Date>,
+        reason -> Nullable<Text>,
+    }
+}
+table! {
+    use diesel::sql_types::{Date,Text,Integer,Nullable};
+    config_history (id) {
+        id -> Integer,
+        revision -> Integer,
+        prop -> Text,
+        old_value -> Nullable<Text>,
+        new_value -> Nullable<Text>,
+        conn_uuid -> Text,
+        changed_at -> Date,
+    }
+}
+table! {
+    use diesel::sql_types::{Date,Text,Integer};
+    users (id) {
+        id -> Integer,
+        username -> Text,
+        password_hash -> Text,
+        role -> Text,
+        created_at -> Date,
+    }
+}
+table! {
+    use diesel::sql_types::{Date,Text,Integer};
+    schema_migrations (version) {
+        version -> Integer,
+        name -> Text,
+        applied_at -> Date,
     }
 }
@@ -3,6 +3,8 @@ use super::{model::*, schema::*, *};
 #[allow(unused_imports)]
 use chrono::{prelude::*, *};
 #[allow(unused_imports)]
+use diesel::{dsl::sql_query, RunQueryDsl};
+#[allow(unused_imports)]
 use dirs::config_dir;
 #[allow(unused_imports)]
 use std::fs::remove_file;
@@ -44,7 +46,7 @@ async fn initlize_database() {
     let tables = sql_query("SELECT name FROM sqlite_master WHERE type='table'")
         .load::<Table>(&db.conn)
         .unwrap();
-    let excepted_tables = vec!["connections", "keys"];
+    let excepted_tables = vec!["connections", "keys", "config_history"];
 
     println!("{tables:#?}");
 
@@ -92,8 +94,9 @@ async fn create_new_key() {
     // let connection = Connections::new(db.conn(), ip_addr, user_agent);
     // key.create(db.conn()).unwrap();
 
-    let key = Keys::generate_new_key(db.conn(), "");
-    key.create(db.conn()).unwrap();
+    let key = Keys::generate_new_key(db.conn(), "")
+        .create(db.conn())
+        .unwrap();
 
     assert!(keys::table.load::<Keys>(db.conn()).unwrap().contains(&key))
 }
@@ -121,8 +124,9 @@ async fn create_new_connection_with_its_key() {
     let connection = Connections::new(ip_addr, user_agent);
     connection.create(db.conn()).unwrap();
 
-    let key = Keys::generate_new_key(db.conn(), &connection.uuid());
-    key.create(db.conn()).unwrap();
+    let key = Keys::generate_new_key(db.conn(), &connection.uuid())
+        .create(db.conn())
+        .unwrap();
     assert!(keys::table.load::<Keys>(db.conn()).unwrap().contains(&key));
     assert!(connections::table
         .load::<Connections>(db.conn())
@@ -168,6 +172,7 @@ async fn validate_key() {
             expires_at: Local::now().to_rfc2822(),
             created_at: Local::now().to_rfc2822(),
             conn_uuid: connection.uuid(),
+            ..Default::default()
         }
         .create(db.conn())
         .unwrap(),
@@ -281,7 +286,7 @@ async fn select_columns_from_keys_table() {
 async fn select_columns_from_info_table() {
     let db = InitDatabase::connect().unwrap();
     db.init().unwrap();
-    let expected = vec![format!("{}", DATABASE_VERSION)];
+    let expected = vec![format!("{}", migrations::DATABASE_VERSION)];
     for key_value in Info::select(db.conn(), |i| i.key() == "db_version")
         .iter()
         .map(|i| i.value())
@@ -322,3 +327,287 @@ async fn find_column_in_connections_table() {
         .uuid()
     );
 }
+
+#[test]
+async fn record_and_rollback_config_changes() {
+    use libvnstat::{ConfigValue, VnStatConfig};
+    use std::{env::temp_dir, fs};
+
+    let db_path = temp_dir().join("vnsd-config-history-test.db");
+    let db_path = db_path.to_str().unwrap();
+    let _ = remove_file(db_path);
+    let db = SqliteConnection::establish(db_path).unwrap();
+    migrations::run(&db).unwrap();
+
+    let config_path = temp_dir().join("vnsd-config-history-test.conf");
+    let config_path = config_path.to_str().unwrap();
+    fs::write(config_path, "UseUTC 0\n").unwrap();
+    let config = VnStatConfig::new(config_path);
+
+    let edit = |props: &[(&str, Option<&str>)]| {
+        ConfigHistory::apply(&db, &config, "UUID", props)
+            .unwrap()
+            .map(|r| r.revision)
+    };
+    assert_eq!(
+        edit(&[("UseUTC", Some("1")), ("ListDays", Some("7"))]),
+        Some(1)
+    );
+    assert_eq!(edit(&[("ListDays", Some("9"))]), Some(2));
+    // Nothing has changed, so nothing is recorded
+    assert_eq!(edit(&[("ListDays", Some("9"))]), None);
+
+    let rollback = ConfigHistory::rollback(&db, &config, LOCAL_CONN_UUID, 0)
+        .unwrap()
+        .unwrap();
+    assert_eq!(rollback.revision, 3);
+    let props = config.get_props().unwrap();
+    assert_eq!(props.get("UseUTC"), Some(&ConfigValue::Boolean(false)));
+    assert!(!props.contains_key("ListDays"));
+
+    let revisions = ConfigHistory::revisions(&db, Some(2)).unwrap();
+    assert_eq!(
+        revisions.iter().map(|r| r.revision).collect::<Vec<_>>(),
+        vec![3, 2]
+    );
+    assert_eq!(revisions[0].conn_uuid, LOCAL_CONN_UUID);
+    assert!(ConfigHistory::rollback(&db, &config, LOCAL_CONN_UUID, 4).is_err());
+
+    remove_file(db_path).unwrap();
+    remove_file(config_path).unwrap();
+    remove_file(format!("{config_path}.bak")).unwrap();
+}
+
+#[test]
+async fn scope_keys_of_users() {
+    use crate::server::api::auth::scope::{Role, Scope};
+    use std::env::temp_dir;
+
+    let db_path = temp_dir().join("vnsd-users-test.db");
+    let db_path = db_path.to_str().unwrap();
+    let _ = remove_file(db_path);
+    let db = SqliteConnection::establish(db_path).unwrap();
+    migrations::run(&db).unwrap();
+
+    let user = Users::add(&db, "grafana", "p@ssw0rd", Role::Operator).unwrap();
+    assert_eq!(user.role(), Role::Operator);
+    assert_ne!(user.password_hash, "p@ssw0rd");
+    assert!(Users::add(&db, "grafana", "password", Role::Viewer).is_err());
+    assert!(Users::add(&db, "bad name", "password", Role::Viewer).is_err());
+    assert!(Users::authenticate(&db, "grafana", "p@ssw0rd").is_some());
+    assert!(Users::authenticate(&db, "grafana", "password").is_none());
+
+    let conn = Connections::new("0.0.0.0", "user_agent")
+        .create(&db)
+        .unwrap();
+    let key = Keys::generate_new_key(&db, &conn.uuid())
+        .owned_by(Some("grafana"), &[Scope::Read, Scope::Admin])
+        .create(&db)
+        .unwrap();
+    // Scopes that aren't granted by the role are dropped
+    assert_eq!(key.granted_scopes(&db), vec![Scope::Read]);
+
+    Users::remove(&db, "grafana").unwrap();
+    assert!(Keys::valid(&db, &key.value()).is_none());
+    assert!(Users::remove(&db, "grafana").is_err());
+
+    remove_file(db_path).unwrap();
+}
+
+#[test]
+async fn expire_blocks_and_keep_them_on_upgrade() {
+    use std::env::temp_dir;
+
+    let db_path = temp_dir().join("vnsd-block-list-test.db");
+    let db_path = db_path.to_str().unwrap();
+    let _ = remove_file(db_path);
+    let db = SqliteConnection::establish(db_path).unwrap();
+    // Database of version 2, its block list has no expiry nor reason
+    migrations::migrate_to(&db, 2).unwrap();
+    sql_query("DROP TABLE schema_migrations").execute(&db).unwrap();
+    sql_query("INSERT INTO block_list (ip_addr, blocked_at) VALUES ('1.1.1.1', 'Sun, 18 Oct 2026 09:51:02 +0000');")
+        .execute(&db)
+        .unwrap();
+
+    migrations::run(&db).unwrap();
+    assert!(BlockList::is_blocked(&db, "1.1.1.1"));
+    assert_eq!(
+        Info::find(&db, |i| i.key() == "db_version")
+            .unwrap()
+            .value(),
+        migrations::DATABASE_VERSION.to_string()
+    );
+
+    BlockList::block_for(&db, "2.2.2.2", Some(Duration::hours(1)), Some("scanner")).unwrap();
+    assert!(BlockList::is_blocked(&db, "2.2.2.2"));
+    BlockList::block_for(&db, "3.3.3.3", Some(Duration::seconds(-1)), None).unwrap();
+    assert!(!BlockList::is_blocked(&db, "3.3.3.3"));
+    BlockList::block_for(&db, "4.4.4.4", Some(Duration::seconds(-1)), None).unwrap();
+    // An expired block is replaced
+    BlockList::block(&db, "3.3.3.3").unwrap();
+    assert!(BlockList::is_blocked(&db, "3.3.3.3"));
+
+    assert_eq!(BlockList::purge_expired(&db).unwrap(), 1);
+    let block_list = BlockList::select(&db, |_| true);
+    assert_eq!(
+        block_list
+            .iter()
+            .map(|b| b.ip_addr.as_str())
+            .collect::<Vec<_>>(),
+        vec!["1.1.1.1", "2.2.2.2", "3.3.3.3"]
+    );
+    assert_eq!(block_list[1].reason.as_deref(), Some("scanner"));
+
+    remove_file(db_path).unwrap();
+}
+
+#[test]
+async fn block_ipv6_addresses_and_ranges() {
+    use std::env::temp_dir;
+
+    let db_path = temp_dir().join("vnsd-block-ranges-test.db");
+    let db_path = db_path.to_str().unwrap();
+    let _ = remove_file(db_path);
+    let db = SqliteConnection::establish(db_path).unwrap();
+    migrations::run(&db).unwrap();
+
+    BlockList::block(&db, "203.0.113.7/24").unwrap();
+    BlockList::block(&db, "2001:DB8::1").unwrap();
+    assert_eq!(
+        BlockList::select(&db, |_| true)
+            .iter()
+            .map(|b| b.ip_addr.as_str())
+            .collect::<Vec<_>>(),
+        vec!["203.0.113.0/24", "2001:db8::1"]
+    );
+    assert!(BlockList::block(&db, "203.0.113.0/24").is_err());
+
+    assert!(BlockList::is_blocked(&db, "203.0.113.200"));
+    assert!(!BlockList::is_blocked(&db, "203.0.114.1"));
+    assert!(BlockList::is_blocked(&db, "2001:db8::1"));
+    assert!(BlockList::is_blocked(&db, "[2001:db8::1]:443"));
+    assert!(!BlockList::is_blocked(&db, "2001:db8::2"));
+    // IPv4 clients of a dual-stack listener have IPv4-mapped addresses
+    assert!(BlockList::is_blocked(&db, "::ffff:203.0.113.200"));
+    BlockList::block(&db, "::ffff:198.51.100.1").unwrap();
+    assert!(BlockList::is_blocked(&db, "198.51.100.1"));
+
+    BlockList::unblock(&db, "203.0.113.0/24").unwrap();
+    assert!(!BlockList::is_blocked(&db, "203.0.113.200"));
+
+    remove_file(db_path).unwrap();
+}
+
+#[test]
+async fn migrate_up_and_down_keeping_data() {
+    use crate::server::api::auth::scope::Scope;
+    use std::env::temp_dir;
+
+    let db_path = temp_dir().join("vnsd-migrations-test.db");
+    let db_path = db_path.to_str().unwrap();
+    let _ = remove_file(db_path);
+    let db = SqliteConnection::establish(db_path).unwrap();
+    // Database of version 1, made before migrations were recorded
+    migrations::migrate_to(&db, 1).unwrap();
+    sql_query("DROP TABLE schema_migrations").execute(&db).unwrap();
+    sql_query("INSERT INTO connections VALUES ('UUID', '1.1.1.1', 'curl', 'Sun, 18 Oct 2026 09:51:02 +0000');")
+        .execute(&db)
+        .unwrap();
+    let expires_at = (Local::now() + Duration::days(1)).to_rfc2822();
+    sql_query(format!("INSERT INTO keys (value, created_at, expires_at, conn_uuid) VALUES ('KEY', 'Sun, 18 Oct 2026 09:51:02 +0000', '{expires_at}', 'UUID');"))
+        .execute(&db)
+        .unwrap();
+
+    assert_eq!(
+        migrations::run(&db).unwrap(),
+        (2..=migrations::DATABASE_VERSION).collect::<Vec<_>>()
+    );
+    // Keys of older versions were issued by the password of `auth` configs
+    let key = Keys::valid(&db, "KEY").unwrap();
+    assert_eq!(key.scopes(), Scope::ALL.to_vec());
+    assert!(migrations::status(&db)
+        .unwrap()
+        .iter()
+        .all(|m| m.applied_at.is_some()));
+    assert!(migrations::run(&db).unwrap().is_empty());
+
+    BlockList::block_for(&db, "1.1.1.1", None, Some("scanner")).unwrap();
+    assert_eq!(migrations::migrate_to(&db, 2).unwrap(), vec![5, 4, 3]);
+    let status = migrations::status(&db).unwrap();
+    assert!(status[..2].iter().all(|m| m.applied_at.is_some()));
+    assert!(status[2..].iter().all(|m| m.applied_at.is_none()));
+    assert_eq!(
+        Info::find(&db, |i| i.key() == "db_version").unwrap().value(),
+        "2"
+    );
+
+    migrations::run(&db).unwrap();
+    let block_list = BlockList::select(&db, |_| true);
+    assert_eq!(block_list[0].ip_addr, "1.1.1.1");
+    assert_eq!(block_list[0].reason, None);
+    assert!(Keys::valid(&db, "KEY").is_some());
+    assert!(migrations::migrate_to(&db, migrations::DATABASE_VERSION + 1).is_err());
+
+    remove_file(db_path).unwrap();
+}
+
+#[test]
+async fn issue_list_and_revoke_keys() {
+    use crate::server::api::auth::scope::Scope;
+    use std::env::temp_dir;
+
+    let db_path = temp_dir().join("vnsd-keys-test.db");
+    let db_path = db_path.to_str().unwrap();
+    let _ = remove_file(db_path);
+    let db = SqliteConnection::establish(db_path).unwrap();
+    migrations::run(&db).unwrap();
+
+    let conn = Connections::new("1.1.1.1", "curl").create(&db).unwrap();
+    let login_keys = (0..2)
+        .map(|_| {
+            Keys::generate_new_key(&db, &conn.uuid())
+                .create(&db)
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    let service_key = Keys::issue(&db, "grafana", None, &[Scope::Read], None, "local").unwrap();
+    assert_eq!(service_key.label.as_deref(), Some("grafana"));
+    assert_eq!(service_key.scopes(), vec![Scope::Read]);
+    assert!(service_key.expires_at() > Local::now() + Duration::days(364));
+    assert!(Keys::is_valid(&db, &service_key.value()));
+    assert_eq!(
+        Keys::find_by_value(&db, &service_key.value()).unwrap(),
+        Some(service_key.clone())
+    );
+    assert_eq!(Keys::find_by_value(&db, "UNKNOWN").unwrap(), None);
+    assert!(Keys::issue(&db, " ", None, &[Scope::Read], None, "local").is_err());
+    assert!(Keys::issue(&db, "grafana", None, &[], None, "local").is_err());
+    assert!(Keys::issue(&db, "old", None, &[Scope::Read], Some(Duration::days(-1)), "local").is_err());
+    // Keys cannot be issued with scopes that their issuer lacks
+    let issuer = &login_keys[0].clone().owned_by(None, &[Scope::Read]);
+    assert!(Keys::issue(&db, "script", Some(issuer), &[Scope::Daemon], None, "local").is_err());
+    let script_key = Keys::issue(&db, "script", Some(issuer), &[Scope::Read], None, "local").unwrap();
+    Keys::revoke(&db, script_key.id).unwrap();
+
+    assert_eq!(Keys::active(&db, None).len(), 3);
+    assert_eq!(Keys::active(&db, Some(&conn.uuid())), login_keys);
+    assert_eq!(service_key.clone().masked().value, service_key.value[..4].to_owned() + "...");
+
+    Keys::revoke(&db, login_keys[0].id).unwrap();
+    // Ids are assigned by SQLite, ids of revoked keys aren't reused
+    let new_key = Keys::generate_new_key(&db, &conn.uuid())
+        .create(&db)
+        .unwrap();
+    assert_eq!(new_key.id, script_key.id + 1);
+    Keys::revoke(&db, new_key.id).unwrap();
+    assert!(!Keys::is_valid(&db, &login_keys[0].value()));
+    assert!(Keys::is_valid(&db, &login_keys[1].value()));
+    assert!(Keys::revoke(&db, login_keys[0].id).is_err());
+
+    assert_eq!(Keys::revoke_connection(&db, &conn.uuid()).unwrap(), 1);
+    assert!(!Keys::is_valid(&db, &login_keys[1].value()));
+    assert!(Keys::is_valid(&db, &service_key.value()));
+    assert!(Keys::revoke_connection(&db, "UNKNOWN").is_err());
+
+    remove_file(db_path).unwrap();
+}
@@ -0,0 +1,102 @@
+use app::Configs;
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+/// Parse an IPv4/IPv6 address or a CIDR range, e.g. `203.0.113.0/24` or `2001:db8::/32`.
+/// An address is a range of itself only, and host bits of a range are cleared.
+/// IPv4-mapped IPv6 addresses are IPv4 ones, as in [`parse_client`].
+pub fn parse_range(s: &str) -> Option<IpNet> {
+    let s = s.trim();
+    match s.parse::<IpNet>() {
+        Ok(net) => Some(net.trunc()),
+        Err(_) => s
+            .parse::<IpAddr>()
+            .ok()
+            .map(|ip| IpNet::from(ip.to_canonical())),
+    }
+}
+
+/// Parse the address of a client, it may have a port, e.g. `[2001:db8::1]:8080`.
+/// IPv4 clients of a dual-stack listener have IPv4-mapped addresses, e.g. `::ffff:203.0.113.7`,
+/// they're IPv4 ones so blocks and the allow list match them.
+pub fn parse_client(s: &str) -> Option<IpAddr> {
+    s.parse::<IpAddr>()
+        .or_else(|_| s.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .ok()
+        .map(|ip| ip.to_canonical())
+}
+
+/// Ranges that are only allowed to connect, everyone is allowed when it's empty
+#[derive(Debug, Clone, Default)]
+pub struct AllowList {
+    ranges: Vec<IpNet>,
+}
+
+impl AllowList {
+    pub fn new(ranges: &[String]) -> Result<Self, String> {
+        Ok(Self {
+            ranges: ranges
+                .iter()
+                .map(|r| parse_range(r).ok_or_else(|| format!("Invalid address or range: '{r}'")))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// `allow_list` of `security` configs
+    pub fn from_configs() -> Result<Self, String> {
+        Self::new(&Configs::current().unwrap().security().allow_list())
+    }
+
+    pub fn allows(&self, addr: &str) -> bool {
+        if self.ranges.is_empty() {
+            return true;
+        }
+        match parse_client(addr) {
+            Some(ip) => self.ranges.iter().any(|r| r.contains(&ip)),
+            None => false,
+        }
+    }
+}
+
+#[test]
+async fn parse_addresses_and_ranges() {
+    assert_eq!(parse_range("203.0.113.7/24"), "203.0.113.0/24".parse().ok());
+    assert_eq!(parse_range("1.1.1.1"), "1.1.1.1/32".parse().ok());
+    assert_eq!(parse_range("2001:db8::1"), "2001:db8::1/128".parse().ok());
+    assert_eq!(parse_range("1.1.1.1/33"), None);
+    assert_eq!(parse_range("1.1.1"), None);
+
+    assert_eq!(parse_client("::1"), "::1".parse().ok());
+    assert_eq!(parse_client("[::1]:8080"), "::1".parse().ok());
+    assert_eq!(parse_client("127.0.0.1:8080"), "127.0.0.1".parse().ok());
+    assert_eq!(parse_client("UNKNOWN"), None);
+
+    assert_eq!(
+        parse_client("::ffff:203.0.113.7"),
+        "203.0.113.7".parse().ok()
+    );
+    assert_eq!(
+        parse_client("[::ffff:203.0.113.7]:8080"),
+        "203.0.113.7".parse().ok()
+    );
+    assert_eq!(
+        parse_range("::ffff:203.0.113.7"),
+        "203.0.113.7/32".parse().ok()
+    );
+}
+
+#[test]
+async fn allow_listed_ranges_only() {
+    assert!(AllowList::new(&[]).unwrap().allows("203.0.113.7"));
+
+    let allow_list = AllowList::new(&["10.0.0.0/8".to_owned(), "::1".to_owned()]).unwrap();
+    assert!(allow_list.allows("10.1.2.3"));
+    assert!(allow_list.allows("::1"));
+    assert!(!allow_list.allows("11.0.0.1"));
+    assert!(!allow_list.allows("::2"));
+    assert!(!allow_list.allows("UNKNOWN"));
+    assert!(allow_list.allows("::ffff:10.1.2.3"));
+    assert!(!allow_list.allows("::ffff:11.0.0.1"));
+
+    assert!(AllowList::new(&["10.0.0.0/40".to_owned()]).is_err());
+}
@@ -1,18 +1,34 @@
 pub mod database;
+pub mod ip;
+pub mod password;
+pub mod rate_limit;
+pub mod scope;
 
 use actix_web::{
     dev::ServiceRequest,
-    http::header::{HeaderValue, USER_AGENT},
-    Error,
+    error::InternalError,
+    http::header::{HeaderValue, RETRY_AFTER, USER_AGENT},
+    web, Error, HttpMessage, HttpResponse,
 };
 use actix_web_httpauth::extractors::{
     bearer::{BearerAuth, Config},
     AuthenticationError,
 };
 use app::Configs;
-use database::{BlockList, Connections, Create, InitDatabase, Keys, Statements};
+use database::{BlockList, Connections, Create, DatabasePool, Keys, Statements, Users};
+use crate::{
+    http::{metrics::Metrics, response::ResponseError},
+    server::listener::Listener,
+};
+use diesel::SqliteConnection;
 use log::*;
+use rate_limit::RateLimiter;
+use scope::{Role, Scope};
 use serde_derive::Serialize;
+use std::time::Duration;
+
+/// Address of clients that have no IP address, i.e. clients of unix socket listeners
+pub const LOCAL_PEER_ADDR: &str = "local";
 
 #[derive(Serialize)]
 pub struct AuthResponse {
@@ -30,26 +46,34 @@ impl AuthResponse {
 pub struct Key {
     value: String,
     expires_at: String,
+    role: Role,
+    scopes: Vec<Scope>,
 }
 impl Key {
-    fn new(value: String, expires_at: String) -> Self {
-        Self { value, expires_at }
+    fn new(value: String, expires_at: String, role: Role, scopes: Vec<Scope>) -> Self {
+        Self {
+            value,
+            expires_at,
+            role,
+            scopes,
+        }
     }
 }
 
 pub enum AuthErrors {
     IncorrectPassword,
     IpAddressWasBlocked,
+    ScopeNotGranted(Scope),
 }
 
 impl AuthErrors {
     pub fn message(&self) -> String {
         use AuthErrors::*;
         match self {
-            IncorrectPassword => "Password is incorrect",
-            IpAddressWasBlocked => "IP address was bloced from system",
+            IncorrectPassword => "Password is incorrect".to_owned(),
+            IpAddressWasBlocked => "IP address was bloced from system".to_owned(),
+            ScopeNotGranted(scope) => format!("Scope '{scope}' isn't granted to the user"),
         }
-        .to_string()
     }
 }
 
@@ -60,24 +84,50 @@ impl Auth {
         req: ServiceRequest,
         credentials: BearerAuth,
     ) -> Result<ServiceRequest, Error> {
-        let db = InitDatabase::connect().unwrap();
-        db.init().unwrap();
+        let db = match req.app_data::<web::Data<DatabasePool>>().map(|pool| pool.get()) {
+            Some(Ok(db)) => db,
+            Some(Err(err)) => {
+                error!("Cannot connect to authentication database: {err}");
+                return Err(InternalError::from_response(
+                    err,
+                    HttpResponse::InternalServerError().json(ResponseError::new().build()),
+                )
+                .into());
+            }
+            None => unreachable!("authentication database pool isn't registered"),
+        };
 
         let config = req
             .app_data::<Config>()
             .map(|data| data.clone())
             .unwrap_or_else(Default::default);
 
-        if BlockList::is_blocked(
-            db.conn(),
-            req.connection_info().realip_remote_addr().unwrap(),
-        ) {
+        // Forwarded headers can be forged, so failures are counted by the peer address
+        let ip_addr = req
+            .connection_info()
+            .peer_addr()
+            .unwrap_or(LOCAL_PEER_ADDR)
+            .to_owned();
+        if BlockList::is_blocked(&db, &ip_addr) {
+            Metrics::auth_failure();
             return Err(AuthenticationError::from(config).into());
         }
+        let limiter = req
+            .app_data::<web::Data<RateLimiter>>()
+            .expect("rate limiter isn't registered");
+        if let Some(retry_after) = limiter.retry_after(&ip_addr) {
+            // Attempts while it's limited are failures, so keeping trying gets it blocked
+            Metrics::auth_failure();
+            Self::on_failure(&db, limiter, &ip_addr);
+            let res = Self::too_many_requests(retry_after);
+            return Err(InternalError::from_response("too many failures", res).into());
+        }
 
-        match Keys::is_valid(db.conn(), credentials.token()) {
-            true => Ok(req),
-            _ => {
+        let key = match Keys::valid(&db, credentials.token()) {
+            Some(key) => key,
+            None => {
+                Metrics::auth_failure();
+                Self::on_failure(&db, limiter, &ip_addr);
                 warn!(
                     "Auth validate failed \n\t IP address: {} \n\t Peer address: {} \n\t User Agent: {:?} \n\t Authorization Token: {}",
                     req.connection_info().realip_remote_addr().unwrap_or("UNKNOWN"),
@@ -88,50 +138,160 @@ impl Auth {
                         _ => credentials.token().to_owned()
                     }
                 );
-                Err(AuthenticationError::from(config).into())
+                return Err(AuthenticationError::from(config).into());
             }
+        };
+
+        // Routes are matched against the decoded path, e.g. `/api/%75sers` is `/api/users`
+        let path = req.match_info().as_str().to_owned();
+        let scope = Scope::required(req.method(), &path);
+        if !key.granted_scopes(&db).contains(&scope) {
+            warn!(
+                "Key of {} isn't allowed to {} {path}: '{scope}' scope is required",
+                key.username.as_deref().unwrap_or("auth password"),
+                req.method(),
+            );
+            return Err(Self::forbidden(&format!(
+                "Cannot do this operation: '{scope}' scope is required."
+            )));
+        }
+        // Read-only mode is about the local vnStat, proxied requests are up to the upstream instance
+        if scope.is_mutating()
+            && !path.starts_with("/api/federation/")
+            && Configs::current().unwrap().security().read_only()
+        {
+            return Err(Self::forbidden(
+                "Cannot do this operation: read-only mode was activated.",
+            ));
         }
+        // Unlike read-only mode, it's about who can reach the listener, so proxied requests too
+        if scope.is_mutating() && !req.conn_data::<Listener>().is_some_and(|l| l.mutating) {
+            return Err(Self::forbidden(
+                "Cannot do this operation: it isn't accepted on this address.",
+            ));
+        }
+        // Handlers may need the key, e.g. to know who made the request
+        req.extensions_mut().insert(key);
+        Ok(req)
+    }
+
+    fn forbidden(details: &str) -> Error {
+        InternalError::from_response(
+            details.to_owned(),
+            HttpResponse::Forbidden().json(ResponseError::new().code(403).details(details).build()),
+        )
+        .into()
     }
 
+    /// Count a failure of `ip_addr`, it's blocked when it has failed too many times
+    pub fn on_failure(db: &SqliteConnection, limiter: &RateLimiter, ip_addr: &str) {
+        if !limiter.fail(ip_addr) || BlockList::is_blocked(db, ip_addr) {
+            return;
+        }
+        // Clients without an IP address cannot be blocked, they're only throttled
+        if ip_addr == LOCAL_PEER_ADDR {
+            return;
+        }
+        let duration = Configs::current().unwrap().security().block_duration();
+        match BlockList::block_for(
+            db,
+            ip_addr,
+            Some(chrono::Duration::seconds(duration as i64)),
+            Some("Too many authentication failures"),
+        ) {
+            Ok(()) => warn!("{ip_addr} has been blocked for {duration}s after too many failures"),
+            Err(err) => error!("Cannot block {ip_addr} after too many failures: {err}"),
+        }
+    }
+
+    /// Response to an IP address that has failed too many times
+    pub fn too_many_requests(retry_after: Duration) -> HttpResponse {
+        // Round up, so it's never retried too early
+        let secs = retry_after.as_secs() + (retry_after.subsec_nanos() > 0) as u64;
+        HttpResponse::TooManyRequests()
+            .insert_header((RETRY_AFTER, secs.to_string()))
+            .json(
+                ResponseError::new()
+                    .code(429)
+                    .details(&format!("Too many failures, retry after {secs} seconds"))
+                    .build(),
+            )
+    }
+
+    /// Whether `password` is the password of `auth` configs, its hash is preferred when it's set
+    fn is_auth_password(password: &str) -> bool {
+        let auth = Configs::current().unwrap().auth();
+        match auth.password_hash() {
+            Some(hash) => password::verify(password, &hash),
+            None => password::matches_plain(password, &auth.password()),
+        }
+    }
+
+    /// Issue a key by the password of `auth` configs, or by the password of a user.
+    ///
+    /// The key is limited to `scopes` when they're given, otherwise it has every scope granted by the role.
     pub fn login(
+        db: &SqliteConnection,
+        username: Option<&str>,
         password: &str,
+        scopes: Option<&[Scope]>,
         ip_addr: &str,
         user_agent: &str,
     ) -> Result<AuthResponse, AuthErrors> {
-        if Configs::init().unwrap().auth().password().eq(password) {
-            let db = InitDatabase::connect().map_err(|e| error!("{e}")).unwrap();
-            db.init().map_err(|e| error!("{e}")).unwrap();
-
-            if BlockList::is_blocked(db.conn(), ip_addr) {
-                return Err(AuthErrors::IpAddressWasBlocked);
+        // Checked first, so a blocked IP address cannot keep guessing passwords
+        if BlockList::is_blocked(db, ip_addr) {
+            return Err(AuthErrors::IpAddressWasBlocked);
+        }
+        let role = match username {
+            None if Self::is_auth_password(password) => Role::Admin,
+            Some(username) => match Users::authenticate(db, username, password) {
+                Some(user) => user.role(),
+                None => return Err(AuthErrors::IncorrectPassword),
+            },
+            None => return Err(AuthErrors::IncorrectPassword),
+        };
+        let scopes = match scopes {
+            Some(scopes) => {
+                if let Some(scope) = scopes.iter().find(|s| !role.scopes().contains(s)) {
+                    return Err(AuthErrors::ScopeNotGranted(*scope));
+                }
+                scopes.to_vec()
             }
+            None => role.scopes().to_vec(),
+        };
 
-            let conn = match Connections::find(db.conn(), |c| {
-                c.ip_addr() == ip_addr && c.user_agent() == user_agent
-            }) {
-                None => Connections::new(ip_addr, user_agent)
-                    .create(db.conn())
-                    .unwrap(),
-                Some(conn) => conn,
-            };
-
-            let key = match Keys::find(db.conn(), |k| {
-                if let Some(k_conn) = k.conn(db.conn()) {
-                    return k_conn == conn;
-                }
-                false
-            }) {
-                Some(k) if Keys::is_valid(db.conn(), &k.value()) => k,
-                _ => Keys::generate_new_key(db.conn(), &conn.uuid())
-                    .create(db.conn())
-                    .unwrap(),
-            };
-            Ok(AuthResponse::new(
-                conn.uuid(),
-                Key::new(key.value(), key.expires_at().to_rfc2822()),
-            ))
-        } else {
-            Err(AuthErrors::IncorrectPassword)
-        }
+        let conn = match Connections::find(db, |c| {
+            c.ip_addr() == ip_addr && c.user_agent() == user_agent
+        }) {
+            None => Connections::new(ip_addr, user_agent)
+                .create(db)
+                .unwrap(),
+            Some(conn) => conn,
+        };
+
+        let key = match Keys::find(db, |k| {
+            if let Some(k_conn) = k.conn(db) {
+                return k_conn == conn
+                    && k.label.is_none()
+                    && k.username.as_deref() == username
+                    && k.scopes() == scopes;
+            }
+            false
+        }) {
+            Some(k) if Keys::is_valid(db, &k.value()) => k,
+            _ => Keys::generate_new_key(db, &conn.uuid())
+                .owned_by(username, &scopes)
+                .create(db)
+                .unwrap(),
+        };
+        Ok(AuthResponse::new(
+            conn.uuid(),
+            Key::new(
+                key.value(),
+                key.expires_at().to_rfc2822(),
+                role,
+                key.scopes(),
+            ),
+        ))
     }
 }
@@ -0,0 +1,52 @@
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use rand::RngCore;
+use subtle::ConstantTimeEq;
+
+const SALT_LEN: usize = 16;
+
+/// Hash `password` by argon2id with a random salt, formatted as a PHC string,
+/// e.g. `$argon2id$v=19$m=19456,t=2,p=1$<salt>$<hash>`
+pub fn hash(password: &str) -> String {
+    let mut salt = [0u8; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let salt = SaltString::encode_b64(&salt).unwrap();
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        // Default parameters and a salt of 16 bytes are always valid
+        .unwrap()
+        .to_string()
+}
+
+/// Check `password` against a hash made by [`hash`] in constant time
+pub fn verify(password: &str, hashed: &str) -> bool {
+    match PasswordHash::new(hashed) {
+        Ok(hashed) => Argon2::default()
+            .verify_password(password.as_bytes(), &hashed)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Compare `password` with a plain one in constant time
+pub fn matches_plain(password: &str, plain: &str) -> bool {
+    password.as_bytes().ct_eq(plain.as_bytes()).into()
+}
+
+#[test]
+async fn hash_and_verify_password() {
+    let hashed = hash("p@ssw0rd");
+    assert!(hashed.starts_with("$argon2id$"));
+    // Salt is random
+    assert_ne!(hashed, hash("p@ssw0rd"));
+
+    assert!(verify("p@ssw0rd", &hashed));
+    assert!(!verify("password", &hashed));
+    assert!(!verify("p@ssw0rd", "p@ssw0rd"));
+    assert!(!verify("", "$argon2id$"));
+
+    assert!(matches_plain("password", "password"));
+    assert!(!matches_plain("password", "passwor"));
+}
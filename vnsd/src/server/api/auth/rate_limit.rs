@@ -0,0 +1,187 @@
+use app::{config::security::SecurityConfigs, Configs};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+/// Counter of failed logins and invalid keys of every IP address in a sliding window
+pub struct RateLimiter {
+    limits: RwLock<Limits>,
+    failures: Mutex<HashMap<String, Failures>>,
+}
+
+/// Failures of an IP address in the window, and when its block expires once it's blocked
+#[derive(Default)]
+struct Failures {
+    at: VecDeque<Instant>,
+    blocked_until: Option<Instant>,
+}
+
+impl Failures {
+    fn is_blocked(&self, now: Instant) -> bool {
+        self.blocked_until.is_some_and(|until| until > now)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Limits {
+    max_failures: u32,
+    block_after_failures: u32,
+    window: Duration,
+    block_duration: Duration,
+}
+
+impl Limits {
+    fn from_configs(security: &SecurityConfigs) -> Self {
+        Self {
+            max_failures: security.max_failures(),
+            block_after_failures: security.block_after_failures(),
+            window: Duration::from_secs(security.failures_window()),
+            block_duration: Duration::from_secs(security.block_duration()),
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn new(
+        max_failures: u32,
+        block_after_failures: u32,
+        window: Duration,
+        block_duration: Duration,
+    ) -> Self {
+        Self {
+            limits: RwLock::new(Limits {
+                max_failures,
+                block_after_failures,
+                window,
+                block_duration,
+            }),
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Limits of `security` configs
+    pub fn from_configs() -> Self {
+        let limits = Limits::from_configs(&Configs::current().unwrap().security());
+        Self::new(
+            limits.max_failures,
+            limits.block_after_failures,
+            limits.window,
+            limits.block_duration,
+        )
+    }
+
+    /// Replace the limits by those of `security` configs, counted failures are kept
+    pub fn set_limits(&self, security: &SecurityConfigs) {
+        *self.limits.write().unwrap() = Limits::from_configs(security);
+    }
+
+    /// Time until `ip_addr` is allowed again, `None` when it's allowed now
+    pub fn retry_after(&self, ip_addr: &str) -> Option<Duration> {
+        let limits = *self.limits.read().unwrap();
+        if limits.max_failures == 0 {
+            return None;
+        }
+        let now = Instant::now();
+        let mut failures = self.failures.lock().unwrap();
+        let failures = failures.get_mut(ip_addr)?;
+        // Clients that cannot be blocked, i.e. local ones, are throttled instead
+        if let Some(until) = failures.blocked_until.filter(|until| *until > now) {
+            return Some(until - now);
+        }
+        Self::prune(failures, now, limits.window);
+        let failures = &failures.at;
+
+        // It's allowed again when the failures in the window are fewer than `max_failures`
+        let count = failures.len();
+        let max = limits.max_failures as usize;
+        (count >= max).then(|| limits.window - now.duration_since(failures[count - max]))
+    }
+
+    /// Count a failure of `ip_addr`, returns whether it should be blocked.
+    /// Failures of a blocked IP address are kept until its block expires.
+    pub fn fail(&self, ip_addr: &str) -> bool {
+        let limits = *self.limits.read().unwrap();
+        let now = Instant::now();
+        let mut failures = self.failures.lock().unwrap();
+        // Forget IP addresses that didn't fail lately
+        failures.retain(|_, f| {
+            Self::prune(f, now, limits.window);
+            !f.at.is_empty()
+        });
+
+        let ip_failures = failures.entry(ip_addr.to_owned()).or_default();
+        ip_failures.at.push_back(now);
+        if limits.block_after_failures > 0
+            && ip_failures.at.len() >= limits.block_after_failures as usize
+            && !ip_failures.is_blocked(now)
+        {
+            ip_failures.blocked_until = Some(now + limits.block_duration);
+            return true;
+        }
+        false
+    }
+
+    /// Forget failures of `ip_addr`, e.g. after it logged in
+    pub fn reset(&self, ip_addr: &str) {
+        self.failures.lock().unwrap().remove(ip_addr);
+    }
+
+    /// Forget failures that left the window, unless the IP address is blocked
+    fn prune(failures: &mut Failures, now: Instant, window: Duration) {
+        if failures.is_blocked(now) {
+            return;
+        }
+        failures.blocked_until = None;
+        while let Some(at) = failures.at.front() {
+            if now.duration_since(*at) < window {
+                break;
+            }
+            failures.at.pop_front();
+        }
+    }
+}
+
+#[test]
+async fn limit_failures_in_window() {
+    let window = Duration::from_millis(200);
+    let limiter = RateLimiter::new(2, 3, window, Duration::from_millis(400));
+    assert_eq!(limiter.retry_after("1.1.1.1"), None);
+
+    assert!(!limiter.fail("1.1.1.1"));
+    assert_eq!(limiter.retry_after("1.1.1.1"), None);
+    assert!(!limiter.fail("1.1.1.1"));
+    let retry_after = limiter.retry_after("1.1.1.1").unwrap();
+    assert!(retry_after > Duration::ZERO && retry_after <= window);
+    // Other IP addresses aren't affected
+    assert_eq!(limiter.retry_after("2.2.2.2"), None);
+
+    // Failures leave the window
+    std::thread::sleep(Duration::from_millis(250));
+    assert_eq!(limiter.retry_after("1.1.1.1"), None);
+
+    assert!(!limiter.fail("1.1.1.1"));
+    assert!(!limiter.fail("1.1.1.1"));
+    assert!(limiter.fail("1.1.1.1"));
+    // Failures are kept until the block expires, and it isn't blocked again meanwhile
+    assert!(limiter.retry_after("1.1.1.1").unwrap() > window);
+    std::thread::sleep(Duration::from_millis(250));
+    assert!(!limiter.fail("1.1.1.1"));
+    assert!(limiter.retry_after("1.1.1.1").is_some());
+    std::thread::sleep(Duration::from_millis(200));
+    assert_eq!(limiter.retry_after("1.1.1.1"), None);
+
+    limiter.fail("2.2.2.2");
+    limiter.fail("2.2.2.2");
+    limiter.reset("2.2.2.2");
+    assert_eq!(limiter.retry_after("2.2.2.2"), None);
+
+    // New limits apply to failures that were already counted
+    limiter.fail("3.3.3.3");
+    limiter.set_limits(&SecurityConfigs::from(true));
+    assert_eq!(limiter.retry_after("3.3.3.3"), None);
+    let security = serde_json::from_str(r#"{ "max_failures": 1 }"#).unwrap();
+    limiter.set_limits(&security);
+    assert!(limiter.retry_after("3.3.3.3").is_some());
+}
@@ -0,0 +1,222 @@
+use crate::api::services::federation;
+use actix_web::http::Method;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// What a key is allowed to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Read traffic, interfaces, configuration and daemon status
+    Read,
+    /// Edit and roll back vnStat configuration
+    Config,
+    /// Stop and restart vnStat daemon
+    Daemon,
+    /// Manage users and keys
+    Admin,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 4] = [Scope::Read, Scope::Config, Scope::Daemon, Scope::Admin];
+
+    /// Scope a request needs, requests that aren't known to change anything need `admin`.
+    ///
+    /// `path` must be the decoded path that routes are matched against, not the raw one,
+    /// e.g. `/api/%75sers` is routed to `/api/users`.
+    pub fn required(method: &Method, path: &str) -> Self {
+        // Proxied requests of `/api/federation/{host}/{tail}` are forwarded to `/api/{tail}`,
+        // so they need its scope. Merged traffic (`GET /api/federation/traffic/{interval}`)
+        // needs `read` like any GET.
+        if let Some(rest) = path.strip_prefix("/api/federation/") {
+            let tail = rest
+                .split_once('/')
+                .map(|(_, tail)| tail)
+                .unwrap_or_default();
+            // The upstream path of such a tail isn't known, it may be normalized to any endpoint
+            if !federation::is_forwardable(tail) {
+                return Self::Admin;
+            }
+            return Self::required(method, &format!("/api/{tail}"));
+        }
+        let is_under = |prefix: &str| path == prefix || path.starts_with(&format!("{prefix}/"));
+
+        if is_under("/api/users") || is_under("/api/auth/keys") {
+            return Self::Admin;
+        }
+        match *method {
+            Method::GET | Method::HEAD | Method::OPTIONS => Self::Read,
+            _ if is_under("/api/config") => Self::Config,
+            _ if is_under("/api/daemon") => Self::Daemon,
+            _ => Self::Admin,
+        }
+    }
+
+    /// Whether the scope changes vnStat, i.e. it's refused in read-only mode
+    pub fn is_mutating(&self) -> bool {
+        matches!(self, Self::Config | Self::Daemon)
+    }
+
+    /// Parse comma separated scopes, e.g. `read,config`
+    pub fn parse_list(scopes: &str) -> Result<Vec<Self>, String> {
+        scopes
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(Self::from_str)
+            .collect()
+    }
+
+    pub fn join(scopes: &[Self]) -> String {
+        scopes
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "read" => Ok(Self::Read),
+            "config" => Ok(Self::Config),
+            "daemon" => Ok(Self::Daemon),
+            "admin" => Ok(Self::Admin),
+            _ => Err(format!(
+                "Invalid scope: '{s}', it must be one of 'read', 'config', 'daemon' or 'admin'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read => write!(f, "read"),
+            Self::Config => write!(f, "config"),
+            Self::Daemon => write!(f, "daemon"),
+            Self::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+/// Role of a user, it grants the scopes keys of the user can have
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Operator,
+    Admin,
+}
+
+impl Role {
+    pub fn scopes(&self) -> &'static [Scope] {
+        match self {
+            Self::Viewer => &[Scope::Read],
+            Self::Operator => &[Scope::Read, Scope::Config, Scope::Daemon],
+            Self::Admin => &Scope::ALL,
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "viewer" => Ok(Self::Viewer),
+            "operator" => Ok(Self::Operator),
+            "admin" => Ok(Self::Admin),
+            _ => Err(format!(
+                "Invalid role: '{s}', it must be one of 'viewer', 'operator' or 'admin'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Viewer => write!(f, "viewer"),
+            Self::Operator => write!(f, "operator"),
+            Self::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+#[test]
+async fn scopes_required_by_requests() {
+    let required = |method: Method, path: &str| Scope::required(&method, path);
+    assert_eq!(required(Method::GET, "/api/traffic/day"), Scope::Read);
+    assert_eq!(required(Method::GET, "/api/config/history"), Scope::Read);
+    assert_eq!(required(Method::PUT, "/api/config"), Scope::Config);
+    assert_eq!(
+        required(Method::POST, "/api/config/rollback/2"),
+        Scope::Config
+    );
+    assert_eq!(required(Method::POST, "/api/daemon/stop"), Scope::Daemon);
+    assert_eq!(required(Method::GET, "/api/users"), Scope::Admin);
+    assert_eq!(required(Method::DELETE, "/api/users/grafana"), Scope::Admin);
+    assert_eq!(required(Method::GET, "/api/auth/keys"), Scope::Admin);
+    assert_eq!(required(Method::DELETE, "/api/auth/keys/3"), Scope::Admin);
+    // Unknown requests that may change something
+    assert_eq!(required(Method::POST, "/api/configs"), Scope::Admin);
+    // Proxied requests
+    assert_eq!(
+        required(Method::GET, "/api/federation/traffic/day"),
+        Scope::Read
+    );
+    assert_eq!(
+        required(Method::POST, "/api/federation/home/daemon/stop"),
+        Scope::Daemon
+    );
+    assert_eq!(
+        required(Method::PUT, "/api/federation/home/config"),
+        Scope::Config
+    );
+    assert_eq!(
+        required(Method::GET, "/api/federation/home/info"),
+        Scope::Read
+    );
+    assert_eq!(
+        required(Method::GET, "/api/federation/home/users"),
+        Scope::Admin
+    );
+    assert_eq!(
+        required(Method::GET, "/api/federation/home/auth/keys"),
+        Scope::Admin
+    );
+    assert_eq!(
+        required(Method::POST, "/api/federation/traffic/daemon/stop"),
+        Scope::Daemon
+    );
+    // Tails whose upstream path isn't known
+    assert_eq!(
+        required(Method::GET, "/api/federation/home/traffic/../users"),
+        Scope::Admin
+    );
+    assert_eq!(
+        required(Method::PUT, "/api/federation/home/./config"),
+        Scope::Admin
+    );
+    assert_eq!(
+        required(Method::GET, "/api/federation/home/traffic%2F..%2Fusers"),
+        Scope::Admin
+    );
+}
+
+#[test]
+async fn scopes_granted_by_roles() {
+    assert_eq!("Viewer".parse::<Role>().unwrap().scopes(), &[Scope::Read]);
+    assert!(!Role::Operator.scopes().contains(&Scope::Admin));
+    assert_eq!(Role::Admin.scopes(), &Scope::ALL);
+    assert!("root".parse::<Role>().is_err());
+
+    assert_eq!(
+        Scope::parse_list(" read, Config ").unwrap(),
+        vec![Scope::Read, Scope::Config]
+    );
+    assert!(Scope::parse_list("read,write").is_err());
+    assert_eq!(Scope::join(&[Scope::Read, Scope::Daemon]), "read,daemon");
+}
@@ -0,0 +1,155 @@
+use crate::api::{
+    auth::{
+        database::{DatabasePool, Keys},
+        scope::Scope,
+        LOCAL_PEER_ADDR,
+    },
+    services::users::error_response,
+};
+use crate::http::response::*;
+use actix_web::{delete, dev::ConnectionInfo, get, post, web, HttpResponse};
+use log::{error, info};
+use serde_derive::Deserialize;
+use serde_json::json;
+use utils::duration::parse_duration;
+
+#[derive(Deserialize)]
+pub struct KeysQuery {
+    /// Only keys of this connection
+    conn_uuid: Option<String>,
+}
+
+/// Keys that aren't expired, their values are masked
+#[get("")]
+pub async fn get_keys(query: web::Query<KeysQuery>, db: web::Data<DatabasePool>) -> HttpResponse {
+    let db = match db.get() {
+        Ok(db) => db,
+        Err(err) => {
+            error!("Cannot connect to authentication database: {err}");
+            return HttpResponse::InternalServerError().json(ResponseError::new().build());
+        }
+    };
+    let keys = Keys::active(&db, query.conn_uuid.as_deref())
+        .into_iter()
+        .map(Keys::masked)
+        .collect::<Vec<_>>();
+    HttpResponse::Ok().json(
+        Response::new()
+            .status(ResponseStatus::Success)
+            .data(&keys)
+            .build(),
+    )
+}
+
+#[derive(Deserialize)]
+pub struct Payload {
+    label: String,
+    /// `read` only when they're omitted
+    scopes: Option<Vec<Scope>>,
+    /// e.g. `90d`, a year when it's omitted
+    expires_in: Option<String>,
+}
+
+/// Issue a service key, owned by the user of the key that issues it and limited to its scopes
+#[post("")]
+pub async fn create_key(
+    payload: web::Json<Payload>,
+    issuer: web::ReqData<Keys>,
+    conn: ConnectionInfo,
+    db: web::Data<DatabasePool>,
+) -> HttpResponse {
+    let valid_for = match payload.expires_in.as_deref().map(parse_duration) {
+        Some(Ok(d)) => match chrono::Duration::from_std(d) {
+            Ok(d) => Some(d),
+            Err(_) => return bad_request("Duration is too long"),
+        },
+        Some(Err(err)) => return bad_request(&err),
+        None => None,
+    };
+    let db = match db.get() {
+        Ok(db) => db,
+        Err(err) => {
+            error!("Cannot connect to authentication database: {err}");
+            return HttpResponse::InternalServerError().json(ResponseError::new().build());
+        }
+    };
+    match Keys::issue(
+        &db,
+        &payload.label,
+        Some(&issuer),
+        payload.scopes.as_deref().unwrap_or(&[Scope::Read]),
+        valid_for,
+        // Forwarded headers can be forged, so the key is recorded by the peer address
+        conn.peer_addr().unwrap_or(LOCAL_PEER_ADDR),
+    ) {
+        Ok(key) => {
+            info!("Service key {} '{}' was issued", key.id, payload.label);
+            HttpResponse::Created().json(
+                Response::new()
+                    .status(ResponseStatus::Success)
+                    .data(&key)
+                    .build(),
+            )
+        }
+        Err(err) => error_response(err),
+    }
+}
+
+/// Revoke every key of a connection
+#[delete("")]
+pub async fn revoke_keys(
+    query: web::Query<KeysQuery>,
+    db: web::Data<DatabasePool>,
+) -> HttpResponse {
+    let conn_uuid = match &query.conn_uuid {
+        Some(conn_uuid) => conn_uuid,
+        None => return bad_request("'conn_uuid' is required"),
+    };
+    let db = match db.get() {
+        Ok(db) => db,
+        Err(err) => {
+            error!("Cannot connect to authentication database: {err}");
+            return HttpResponse::InternalServerError().json(ResponseError::new().build());
+        }
+    };
+    match Keys::revoke_connection(&db, conn_uuid) {
+        Ok(revoked) => {
+            let details = format!("{revoked} key(s) of connection '{conn_uuid}' were revoked");
+            info!("{details}");
+            HttpResponse::Ok().json(
+                Response::new()
+                    .status(ResponseStatus::Success)
+                    .data(json!({ "details": details }))
+                    .build(),
+            )
+        }
+        Err(err) => error_response(err),
+    }
+}
+
+#[delete("/{id}")]
+pub async fn revoke_key(id: web::Path<i32>, db: web::Data<DatabasePool>) -> HttpResponse {
+    let db = match db.get() {
+        Ok(db) => db,
+        Err(err) => {
+            error!("Cannot connect to authentication database: {err}");
+            return HttpResponse::InternalServerError().json(ResponseError::new().build());
+        }
+    };
+    match Keys::revoke(&db, *id) {
+        Ok(()) => {
+            info!("Key {id} was revoked");
+            HttpResponse::Ok().json(
+                Response::new()
+                    .status(ResponseStatus::Success)
+                    .data(json!({ "details": format!("Key {id} was revoked") }))
+                    .build(),
+            )
+        }
+        Err(err) => error_response(err),
+    }
+}
+
+fn bad_request(details: &str) -> HttpResponse {
+    HttpResponse::BadRequest().json(ResponseError::new().code(400).details(details).build())
+}
@@ -1,36 +1,82 @@
-use crate::api::auth::Auth;
-use crate::http::response::*;
+pub mod keys;
+
+use crate::api::auth::{
+    database::DatabasePool, rate_limit::RateLimiter, scope::Scope, Auth, AuthErrors,
+    LOCAL_PEER_ADDR,
+};
+use crate::http::{metrics::Metrics, response::*};
 use actix_web::{
     dev::ConnectionInfo, http::header::USER_AGENT, post, web, HttpRequest, HttpResponse,
 };
+use log::error;
 use serde_derive::Deserialize;
 
 #[derive(Deserialize)]
 pub struct Payload {
+    /// Log in as a user, or by the password of `auth` configs when it's omitted
+    username: Option<String>,
     password: String,
+    /// Limit the key to some of the scopes granted by the role of the user
+    scopes: Option<Vec<Scope>>,
 }
 #[post("/login")]
 pub async fn login(
     payload: web::Json<Payload>,
     conn: ConnectionInfo,
     req: HttpRequest,
+    db: web::Data<DatabasePool>,
+    limiter: web::Data<RateLimiter>,
 ) -> HttpResponse {
+    let db = match db.get() {
+        Ok(db) => db,
+        Err(err) => {
+            error!("Cannot connect to authentication database: {err}");
+            return HttpResponse::InternalServerError().json(ResponseError::new().build());
+        }
+    };
+    let ip_addr = conn.peer_addr().unwrap_or(LOCAL_PEER_ADDR);
+    if let Some(retry_after) = limiter.retry_after(ip_addr) {
+        Metrics::auth_failure();
+        Auth::on_failure(&db, &limiter, ip_addr);
+        return Auth::too_many_requests(retry_after);
+    }
     match Auth::login(
+        &db,
+        payload.username.as_deref(),
         &payload.password,
-        conn.realip_remote_addr().unwrap(),
-        req.headers().get(USER_AGENT).unwrap().to_str().unwrap(),
+        payload.scopes.as_deref(),
+        ip_addr,
+        req.headers()
+            .get(USER_AGENT)
+            .and_then(|h| h.to_str().ok())
+            .unwrap_or("UNKNOWN"),
     ) {
-        Ok(result) => HttpResponse::Ok().json(
-            Response::new()
-                .status(ResponseStatus::Success)
-                .data(&result)
-                .build(),
-        ),
-        Err(err) => HttpResponse::Unauthorized().json(
+        Ok(result) => {
+            limiter.reset(ip_addr);
+            HttpResponse::Ok().json(
+                Response::new()
+                    .status(ResponseStatus::Success)
+                    .data(&result)
+                    .build(),
+            )
+        }
+        Err(err @ AuthErrors::ScopeNotGranted(_)) => HttpResponse::BadRequest().json(
             ResponseError::new()
-                .code(401)
+                .code(400)
                 .details(err.message().as_str())
                 .build(),
         ),
+        Err(err) => {
+            Metrics::auth_failure();
+            if let AuthErrors::IncorrectPassword = err {
+                Auth::on_failure(&db, &limiter, ip_addr);
+            }
+            HttpResponse::Unauthorized().json(
+                ResponseError::new()
+                    .code(401)
+                    .details(err.message().as_str())
+                    .build(),
+            )
+        }
     }
 }
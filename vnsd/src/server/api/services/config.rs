@@ -1,14 +1,16 @@
+use crate::api::auth::database::{ConfigHistory, DatabasePool, Keys};
 use crate::http::response::*;
-use actix_web::{get, put, web, HttpResponse};
-use app::config::Configs;
-use libvnstat::VnStat;
+use actix_web::{get, post, put, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use libvnstat::{ConfigValue, VnStat};
 use log::{error, info};
 use serde_derive::{Deserialize, Serialize};
 use serde_json::json;
+use std::io::{self, ErrorKind};
 
 #[get("/config")]
-pub async fn get_config() -> HttpResponse {
-    match VnStat.config().get_props() {
+pub async fn get_config(vnstat: web::Data<VnStat>) -> HttpResponse {
+    match vnstat.config().get_props() {
         Ok(result) => HttpResponse::Ok().json(
             Response::new()
                 .status(ResponseStatus::Success)
@@ -25,43 +27,143 @@ pub async fn get_config() -> HttpResponse {
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Payload {
     pub prop: String,
-    pub value: String,
+    /// A string, an integer or a boolean, like values of `GET /api/config`
+    pub value: ConfigValue,
 }
 
+/// Every edit is applied all at once, or not at all, and recorded as a new revision
 #[put("/config")]
-pub async fn edit_config(payload: web::Json<Vec<Payload>>) -> HttpResponse {
-    if Configs::init().unwrap().security().read_only() {
-        return HttpResponse::Forbidden().json(
-            ResponseError::new()
-                .code(403)
-                .details("Cannot do this operation: read-only mode was activated.")
+pub async fn edit_config(
+    payload: web::Json<Vec<Payload>>,
+    credentials: BearerAuth,
+    vnstat: web::Data<VnStat>,
+    db: web::Data<DatabasePool>,
+) -> HttpResponse {
+    let db = match db.get() {
+        Ok(db) => db,
+        Err(err) => {
+            error!("Cannot connect to authentication database: {err}");
+            return HttpResponse::InternalServerError().json(ResponseError::new().build());
+        }
+    };
+    let values = payload
+        .iter()
+        .map(|p| p.value.to_string())
+        .collect::<Vec<_>>();
+    let props = payload
+        .iter()
+        .zip(values.iter())
+        .map(|(p, value)| (p.prop.as_str(), Some(value.as_str())))
+        .collect::<Vec<_>>();
+    match conn_uuid(&db, &credentials)
+        .and_then(|uuid| ConfigHistory::apply(&db, &vnstat.config(), &uuid, &props))
+    {
+        Ok(revision) => {
+            if let Some(revision) = revision {
+                info!(
+                    "vnStat configuration edited, revision {}: {}",
+                    revision.revision,
+                    json!(revision.changes)
+                );
+            }
+            HttpResponse::Ok().json(
+                Response::new()
+                    .status(ResponseStatus::Success)
+                    .data(json!(payload))
+                    .build(),
+            )
+        }
+        Err(err) => error_response(err),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    pub limit: Option<usize>,
+}
+
+#[get("/config/history")]
+pub async fn get_config_history(
+    query: web::Query<HistoryQuery>,
+    db: web::Data<DatabasePool>,
+) -> HttpResponse {
+    match db
+        .get()
+        .map_err(anyhow::Error::from)
+        .and_then(|db| ConfigHistory::revisions(&db, query.limit))
+    {
+        Ok(revisions) => HttpResponse::Ok().json(
+            Response::new()
+                .status(ResponseStatus::Success)
+                .data(&revisions)
                 .build(),
-        );
+        ),
+        Err(err) => error_response(err),
     }
-    for p in payload.iter() {
-        match VnStat.config().set_prop(&p.prop, &p.value).await {
-            Ok(exit_status) => {
-                info!("{exit_status}");
-                if !exit_status.success() {
-                    error!(
-                        "Cannot set '{}' to '{}': {}",
-                        p.prop,
-                        p.value,
-                        exit_status.to_string()
-                    );
-                    return HttpResponse::InternalServerError().json(ResponseError::new().build());
-                }
-            }
-            Err(err) => {
-                error!("{err}");
-                return HttpResponse::InternalServerError().json(ResponseError::new().build());
-            }
+}
+
+/// Restore the configuration as it was at `revision`, `data` is null when nothing has changed since then
+#[post("/config/rollback/{revision}")]
+pub async fn rollback_config(
+    revision: web::Path<i32>,
+    credentials: BearerAuth,
+    vnstat: web::Data<VnStat>,
+    db: web::Data<DatabasePool>,
+) -> HttpResponse {
+    let db = match db.get() {
+        Ok(db) => db,
+        Err(err) => {
+            error!("Cannot connect to authentication database: {err}");
+            return HttpResponse::InternalServerError().json(ResponseError::new().build());
+        }
+    };
+    let revision = revision.into_inner();
+    match ConfigHistory::latest_revision(&db) {
+        Ok(latest) if revision < 0 || revision > latest => {
+            return HttpResponse::NotFound().json(
+                ResponseError::new()
+                    .code(404)
+                    .details(&format!("Revision {revision} is not found"))
+                    .build(),
+            )
+        }
+        Ok(_) => (),
+        Err(err) => return error_response(err),
+    }
+    match conn_uuid(&db, &credentials)
+        .and_then(|uuid| ConfigHistory::rollback(&db, &vnstat.config(), &uuid, revision))
+    {
+        Ok(result) => {
+            info!("vnStat configuration rolled back to revision {revision}");
+            HttpResponse::Ok().json(
+                Response::new()
+                    .status(ResponseStatus::Success)
+                    .data(&result)
+                    .build(),
+            )
+        }
+        Err(err) => error_response(err),
+    }
+}
+
+/// Uuid of the connection the key of the request belongs to
+fn conn_uuid(db: &diesel::SqliteConnection, credentials: &BearerAuth) -> anyhow::Result<String> {
+    Ok(Keys::find_by_value(db, credentials.token())?
+        .map(|k| k.conn_uuid)
+        .unwrap_or_else(|| Keys::default().conn_uuid))
+}
+
+fn error_response(err: anyhow::Error) -> HttpResponse {
+    match err.downcast_ref::<io::Error>() {
+        Some(e) if e.kind() == ErrorKind::InvalidInput => HttpResponse::BadRequest().json(
+            ResponseError::new()
+                .code(400)
+                .details(&e.to_string())
+                .build(),
+        ),
+        _ => {
+            error!("{err}");
+            HttpResponse::InternalServerError().json(ResponseError::new().build())
         }
     }
-    HttpResponse::Ok().json(
-        Response::new()
-            .status(ResponseStatus::Success)
-            .data(json!(payload))
-            .build(),
-    )
 }
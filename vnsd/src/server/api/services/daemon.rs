@@ -1,14 +1,13 @@
 use crate::http::response::*;
-use app::Configs;
 use libvnstat::VnStat;
 
-use actix_web::{get, post, HttpResponse};
+use actix_web::{get, post, web, HttpResponse};
 use log::error;
 use serde_json::json;
 
 #[get("/daemon")]
-pub async fn get_daemon_status() -> HttpResponse {
-    match VnStat.daemon().is_active() {
+pub async fn get_daemon_status(vnstat: web::Data<VnStat>) -> HttpResponse {
+    match vnstat.daemon().is_active() {
         Ok(is_active) => HttpResponse::Ok().json(
             Response::new()
                 .status(ResponseStatus::Success)
@@ -23,16 +22,8 @@ pub async fn get_daemon_status() -> HttpResponse {
 }
 
 #[post("/daemon/restart")]
-pub async fn restart_daemon() -> HttpResponse {
-    if Configs::init().unwrap().security().read_only() {
-        return HttpResponse::Forbidden().json(
-            ResponseError::new()
-                .code(403)
-                .details("Cannot do this operation: read-only mode was activated.")
-                .build(),
-        );
-    }
-    match VnStat.daemon().restart() {
+pub async fn restart_daemon(vnstat: web::Data<VnStat>) -> HttpResponse {
+    match vnstat.daemon().restart() {
         Ok(exit_status) => match exit_status.success() {
             true => HttpResponse::Ok().json(
                 Response::new()
@@ -54,17 +45,8 @@ pub async fn restart_daemon() -> HttpResponse {
     }
 }
 #[post("/daemon/stop")]
-pub async fn stop_daemon() -> HttpResponse {
-    if Configs::init().unwrap().security().read_only() {
-        return HttpResponse::Forbidden().json(
-            ResponseError::new()
-                .code(403)
-                .details("Cannot do this operation: read-only mode was activated.")
-                .build(),
-        );
-    }
-
-    match VnStat.daemon().stop() {
+pub async fn stop_daemon(vnstat: web::Data<VnStat>) -> HttpResponse {
+    match vnstat.daemon().stop() {
         Ok(exit_status) => match exit_status.success() {
             true => HttpResponse::Ok().json(
                 Response::new()
@@ -0,0 +1,386 @@
+use super::traffic::TrafficQuery;
+use crate::http::response::{Response, ResponseError, ResponseStatus};
+use actix_web::{
+    error::ErrorBadGateway,
+    get,
+    http::{header::CONTENT_TYPE, StatusCode},
+    route, web, HttpRequest, HttpResponse,
+};
+use app::{config::federation::FederationHost, Configs};
+use futures::{future::join_all, TryStreamExt};
+use libvnstat::TrafficInterval;
+use log::{error, warn};
+use reqwest::{Client, Method};
+use serde_derive::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+
+/// Traffic rows of every upstream host, each row is tagged by its `host`
+#[derive(Serialize, Debug, Default)]
+pub struct FederatedTraffic {
+    pub traffic: Vec<Value>,
+    /// Hosts that couldn't be queried
+    pub failures: Vec<HostFailure>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct HostFailure {
+    pub host: String,
+    pub details: String,
+}
+
+#[get("/federation/traffic/{interval}")]
+pub async fn get_federated_traffic(
+    interval: web::Path<String>,
+    // Validated here to not send an invalid query to every host
+    query: web::Query<TrafficQuery>,
+    req: HttpRequest,
+    client: web::Data<Client>,
+) -> HttpResponse {
+    let configs = Configs::current().unwrap().federation();
+    if !configs.enabled() {
+        return disabled();
+    }
+    if !TrafficInterval::new(interval.as_str()).is_validated() {
+        return HttpResponse::NotFound().json(
+            ResponseError::new()
+                .code(404)
+                .details("Interval isn't found.")
+                .build(),
+        );
+    }
+
+    let result = federated_traffic(
+        &client,
+        &configs.hosts(),
+        &interval,
+        req.query_string(),
+        Duration::from_secs(configs.timeout()),
+    )
+    .await;
+    let descending = query
+        .order
+        .as_ref()
+        .map(|o| o.eq_ignore_ascii_case("desc"))
+        .unwrap_or(false);
+
+    HttpResponse::Ok().json(
+        Response::new()
+            .status(ResponseStatus::Success)
+            .data(&sort_by_date(result, descending))
+            .build(),
+    )
+}
+
+/// Forward a request to `/api/{tail}` of an upstream host
+#[route(
+    "/federation/{host}/{tail:.*}",
+    method = "GET",
+    method = "POST",
+    method = "PUT",
+    method = "DELETE"
+)]
+pub async fn proxy(
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+    body: web::Bytes,
+    client: web::Data<Client>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let configs = Configs::current().unwrap().federation();
+    if !configs.enabled() {
+        return Ok(disabled());
+    }
+    let (name, tail) = path.into_inner();
+    if !is_forwardable(&tail) {
+        return Ok(HttpResponse::BadRequest().json(
+            ResponseError::new()
+                .code(400)
+                .details("Path of the upstream endpoint isn't valid.")
+                .build(),
+        ));
+    }
+    let host = match configs.host(&name) {
+        Some(host) => host,
+        None => {
+            return Ok(HttpResponse::NotFound().json(
+                ResponseError::new()
+                    .code(404)
+                    .details("Host isn't found.")
+                    .build(),
+            ))
+        }
+    };
+
+    let mut upstream = client
+        .request(
+            Method::from_bytes(req.method().as_str().as_bytes()).unwrap(),
+            upstream_url(&host, &tail, req.query_string()),
+        )
+        .bearer_auth(&host.key)
+        .timeout(Duration::from_secs(configs.timeout()))
+        .body(body.to_vec());
+    if let Some(content_type) = req.headers().get(CONTENT_TYPE) {
+        upstream = upstream.header(CONTENT_TYPE.as_str(), content_type.as_bytes());
+    }
+
+    let res = match upstream.send().await {
+        Ok(res) => res,
+        Err(err) => {
+            warn!("Cannot reach federation host '{}': {err}", host.name);
+            return Ok(HttpResponse::BadGateway().json(
+                ResponseError::new()
+                    .code(502)
+                    .details(&format!("Cannot reach host '{}'.", host.name))
+                    .build(),
+            ));
+        }
+    };
+
+    let mut proxied = HttpResponse::build(
+        StatusCode::from_u16(res.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY),
+    );
+    if let Some(content_type) = res.headers().get(CONTENT_TYPE.as_str()) {
+        proxied.insert_header((CONTENT_TYPE, content_type.as_bytes()));
+    }
+    // Streamed, so `/live` events are forwarded as they come
+    Ok(proxied.streaming(res.bytes_stream().map_err(ErrorBadGateway)))
+}
+
+/// Query `/api/traffic/{interval}` of every host concurrently
+pub async fn federated_traffic(
+    client: &Client,
+    hosts: &[FederationHost],
+    interval: &str,
+    query: &str,
+    timeout: Duration,
+) -> FederatedTraffic {
+    let tail = format!("traffic/{interval}");
+    let responses = join_all(
+        hosts
+            .iter()
+            .map(|host| fetch(client, host, &tail, query, timeout)),
+    )
+    .await;
+
+    let mut result = FederatedTraffic::default();
+    for (host, response) in hosts.iter().zip(responses) {
+        match response {
+            Ok(rows) => result.traffic.extend(rows.into_iter().map(|mut row| {
+                if let Value::Object(row) = &mut row {
+                    row.insert("host".to_owned(), Value::String(host.name.clone()));
+                }
+                row
+            })),
+            Err(details) => {
+                error!("Federation host '{}': {details}", host.name);
+                result.failures.push(HostFailure {
+                    host: host.name.clone(),
+                    details,
+                })
+            }
+        }
+    }
+    result
+}
+
+async fn fetch(
+    client: &Client,
+    host: &FederationHost,
+    tail: &str,
+    query: &str,
+    timeout: Duration,
+) -> Result<Vec<Value>, String> {
+    let res = client
+        .get(upstream_url(host, tail, query))
+        .bearer_auth(&host.key)
+        .timeout(timeout)
+        .send()
+        .await
+        .map_err(|e| format!("Cannot reach host: {e}"))?;
+    let status = res.status();
+    let body = res
+        .json::<Value>()
+        .await
+        .map_err(|e| format!("Invalid response: {e}"))?;
+
+    match (status.is_success(), &body["data"]) {
+        (true, Value::Array(rows)) => Ok(rows.clone()),
+        (true, _) => Err("Invalid response: 'data' isn't an array".to_owned()),
+        (false, data) => Err(format!(
+            "[{}] {}",
+            status.as_u16(),
+            data["details"].as_str().unwrap_or("request failed")
+        )),
+    }
+}
+
+/// Whether `tail` is forwarded to the upstream path it names. Dot segments and backslashes
+/// are normalized, escaped characters are decoded and `?` or `#` end the path on the way,
+/// so the endpoint it reaches isn't known.
+pub fn is_forwardable(tail: &str) -> bool {
+    !tail.contains(['%', '?', '#', '\\'])
+        && tail
+            .split('/')
+            .all(|segment| segment != "." && segment != "..")
+}
+
+fn upstream_url(host: &FederationHost, tail: &str, query: &str) -> String {
+    let mut url = format!("{}/api/{}", host.url.trim_end_matches('/'), tail);
+    if !query.is_empty() {
+        url.push('?');
+        url.push_str(query);
+    }
+    url
+}
+
+fn sort_by_date(mut result: FederatedTraffic, descending: bool) -> FederatedTraffic {
+    // Dates are formatted as 'YYYY-MM-DD[ hh:mm[:ss]]', so they're ordered as strings
+    result.traffic.sort_by(|a, b| {
+        let ordering = a["date"].as_str().cmp(&b["date"].as_str());
+        match descending {
+            true => ordering.reverse(),
+            false => ordering,
+        }
+    });
+    result
+}
+
+fn disabled() -> HttpResponse {
+    HttpResponse::NotFound().json(
+        ResponseError::new()
+            .code(404)
+            .details("service not found.")
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::auth::{
+            database::{Connections, Create, InitDatabase, Keys},
+            ip::AllowList,
+            rate_limit::RateLimiter,
+        },
+        server::{Server, ServerAddr, ServerData},
+    };
+    use diesel::{connection::SimpleConnection, Connection, SqliteConnection};
+    use libvnstat::{db::pool::DEFAULT_BUSY_TIMEOUT, VnStat, VnStatPool};
+    use std::{env::temp_dir, fs, net::TcpListener, sync::RwLock};
+
+    /// vnStat database with one interface and one day of traffic
+    fn fixture_database(name: &str, interface: &str, rx: i64) -> String {
+        let path = temp_dir().join(format!("vnsd-federation-{name}.db"));
+        let path = path.to_str().unwrap().to_owned();
+        let _ = fs::remove_file(&path);
+        SqliteConnection::establish(&path)
+            .unwrap()
+            .batch_execute(&format!(
+                "CREATE TABLE info(id INTEGER PRIMARY KEY, name TEXT, value TEXT);
+                INSERT INTO info(name, value) VALUES ('dbversion', '1');
+                CREATE TABLE interface(id INTEGER PRIMARY KEY, name TEXT, alias TEXT, active INTEGER,
+                    created DATE, updated DATE, rxcounter INTEGER, txcounter INTEGER, rxtotal INTEGER, txtotal INTEGER);
+                INSERT INTO interface VALUES (1, '{interface}', NULL, 1, '2022-07-01 00:00:00', '2022-07-02 00:00:00', 0, 0, {rx}, 0);
+                CREATE TABLE day(id INTEGER PRIMARY KEY, interface INTEGER, date DATE, rx INTEGER, tx INTEGER);
+                INSERT INTO day VALUES (1, 1, '2022-07-01', {rx}, 0);"
+            ))
+            .unwrap();
+        path
+    }
+
+    fn free_port() -> u16 {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    #[test]
+    async fn merge_traffic_of_hosts() {
+        let auth_db = InitDatabase::pool().unwrap();
+        let key = {
+            let db = auth_db.get().unwrap();
+            let conn = Connections::new("127.0.0.1", "federation-test")
+                .create(&db)
+                .unwrap();
+            Keys::generate_new_key(&db, &conn.uuid())
+                .create(&db)
+                .unwrap()
+                .value()
+        };
+
+        let mut hosts = vec![];
+        for (name, interface, rx) in [("first", "eth0", 100), ("second", "wlan0", 200)] {
+            let data = ServerData {
+                vnstat: web::Data::new(VnStat::new(VnStatPool::new(
+                    &fixture_database(name, interface, rx),
+                    1,
+                    DEFAULT_BUSY_TIMEOUT,
+                ))),
+                auth_db: web::Data::new(auth_db.clone()),
+                http_client: web::Data::new(Client::new()),
+                rate_limiter: web::Data::new(RateLimiter::from_configs()),
+                allow_list: web::Data::new(RwLock::new(AllowList::default())),
+                tls: None,
+            };
+            let port = free_port();
+            let server = Server::new(ServerAddr::new("127.0.0.1", port), data).unwrap();
+            actix_web::rt::spawn(async move { server.run().await });
+            hosts.push(FederationHost {
+                name: name.to_owned(),
+                url: format!("http://127.0.0.1:{port}"),
+                key: key.clone(),
+            });
+        }
+        hosts.push(FederationHost {
+            name: "unreachable".to_owned(),
+            url: format!("http://127.0.0.1:{}", free_port()),
+            key,
+        });
+
+        let result =
+            federated_traffic(&Client::new(), &hosts, "day", "", Duration::from_secs(5)).await;
+
+        assert_eq!(result.traffic.len(), 2);
+        assert_eq!(result.traffic[0]["host"], "first");
+        assert_eq!(result.traffic[0]["rx"], 100);
+        assert_eq!(result.traffic[1]["host"], "second");
+        assert_eq!(result.traffic[1]["rx"], 200);
+        assert_eq!(result.failures.len(), 1);
+        assert_eq!(result.failures[0].host, "unreachable");
+    }
+
+    #[test]
+    async fn forward_tails_without_dot_segments_or_escapes() {
+        assert!(is_forwardable("traffic/day"));
+        assert!(is_forwardable("config/rollback/2"));
+        assert!(is_forwardable("interface/eth0.100"));
+        assert!(!is_forwardable("traffic/../users"));
+        assert!(!is_forwardable("../auth/keys"));
+        assert!(!is_forwardable("./config"));
+        assert!(!is_forwardable("traffic/.."));
+        assert!(!is_forwardable("traffic%2F..%2Fusers"));
+        assert!(!is_forwardable("users?limit=1"));
+        assert!(!is_forwardable("users#traffic"));
+        assert!(!is_forwardable("traffic\\..\\users"));
+    }
+
+    #[test]
+    async fn build_upstream_url() {
+        let host = FederationHost {
+            name: "edge".to_owned(),
+            url: "http://10.0.0.2:8080/".to_owned(),
+            key: "key".to_owned(),
+        };
+        assert_eq!(
+            upstream_url(&host, "traffic/day", "limit=1"),
+            "http://10.0.0.2:8080/api/traffic/day?limit=1"
+        );
+        assert_eq!(
+            upstream_url(&host, "interface", ""),
+            "http://10.0.0.2:8080/api/interface"
+        );
+    }
+}
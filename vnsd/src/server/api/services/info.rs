@@ -1,11 +1,11 @@
 use crate::http::response::{Response, ResponseError, ResponseStatus};
-use actix_web::{get, HttpResponse};
+use actix_web::{get, web, HttpResponse};
 use libvnstat::{db::models::Info, VnStat};
 use log::error;
 
 #[get("/info")]
-pub async fn get_info() -> HttpResponse {
-    match VnStat.info().get() {
+pub async fn get_info(vnstat: web::Data<VnStat>) -> HttpResponse {
+    match vnstat.info().get() {
         Ok(mut result) => {
             result.push(Info {
                 id: result.len() as i32 + 1,
@@ -1,20 +1,77 @@
 use log::error;
 
-use crate::http::response::{Response, ResponseError, ResponseStatus};
-use actix_web::{get, HttpResponse};
-use libvnstat::VnStat;
+use super::traffic::{error_response, TrafficQuery};
+use crate::http::{
+    export::{self, ExportFormat},
+    response::ResponseError,
+};
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use libvnstat::{TrafficInterval, VnStat};
 #[get("/interface")]
-pub async fn get_interface() -> HttpResponse {
-    match VnStat.interface().get() {
-        Ok(result) => HttpResponse::Ok().json(
-            Response::new()
-                .status(ResponseStatus::Success)
-                .data(&result)
-                .build(),
-        ),
+pub async fn get_interface(vnstat: web::Data<VnStat>, req: HttpRequest) -> HttpResponse {
+    let format = match ExportFormat::negotiate(&req) {
+        Ok(format) => format,
+        Err(details) => return export::invalid_format(&details),
+    };
+    match vnstat.interface().get() {
+        Ok(result) => export::respond(format, result, "interfaces"),
         Err(err) => {
             error!("{err}");
             HttpResponse::InternalServerError().json(ResponseError::new().build())
         }
     }
 }
+
+#[get("/interface/{name}/traffic/{interval}")]
+pub async fn get_interface_traffic(
+    path: web::Path<(String, String)>,
+    query: web::Query<TrafficQuery>,
+    vnstat: web::Data<VnStat>,
+    req: HttpRequest,
+) -> HttpResponse {
+    let format = match ExportFormat::negotiate(&req) {
+        Ok(format) => format,
+        Err(details) => return export::invalid_format(&details),
+    };
+    let (name, interval) = path.into_inner();
+
+    if !TrafficInterval::new(&interval).is_validated() {
+        return HttpResponse::NotFound().json(
+            ResponseError::new()
+                .code(404)
+                .details("Interval isn't found.")
+                .build(),
+        );
+    }
+    match vnstat.interface().find(&name) {
+        Ok(Some(_)) => (),
+        Ok(None) => {
+            return HttpResponse::NotFound().json(
+                ResponseError::new()
+                    .code(404)
+                    .details("Interface isn't found.")
+                    .build(),
+            )
+        }
+        Err(err) => {
+            error!("{err}");
+            return HttpResponse::InternalServerError().json(ResponseError::new().build());
+        }
+    }
+
+    let mut traffic = vnstat.traffic(&interval);
+    traffic.interface(&name);
+    if let Err(details) = query.apply(&mut traffic) {
+        return HttpResponse::BadRequest()
+            .json(ResponseError::new().code(400).details(&details).build());
+    }
+
+    match traffic
+        .pages_with_interface_name(export::PAGE_SIZE)
+        .and_then(|pages| {
+            export::respond_pages(format, pages, &format!("{name}-traffic-{interval}"))
+        }) {
+        Ok(res) => res,
+        Err(err) => error_response(err),
+    }
+}
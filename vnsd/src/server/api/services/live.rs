@@ -0,0 +1,74 @@
+use crate::http::response::ResponseError;
+use actix_web::{
+    get,
+    http::header::{CACHE_CONTROL, CONTENT_TYPE},
+    web::{self, Bytes},
+    HttpResponse,
+};
+use app::Configs;
+use futures::stream;
+use libvnstat::VnStat;
+use log::error;
+use serde_json::json;
+use std::time::Duration;
+use tokio::{
+    sync::watch,
+    time::{interval, MissedTickBehavior},
+};
+
+/// Stream rx/tx rates of interfaces as Server-Sent Events, until the server stops
+#[get("/live")]
+pub async fn live(
+    vnstat: web::Data<VnStat>,
+    closing: web::Data<watch::Sender<()>>,
+) -> HttpResponse {
+    let configs = Configs::current().unwrap().live();
+    if !configs.enabled() {
+        return HttpResponse::Forbidden().json(
+            ResponseError::new()
+                .code(403)
+                .details("Cannot do this operation: live streaming was disabled.")
+                .build(),
+        );
+    }
+
+    let mut sampler = vnstat.live(configs.interfaces());
+    // Take initial counters, rates are computed starting from the next sample
+    if let Err(err) = sampler.sample() {
+        error!("{err}");
+        return HttpResponse::InternalServerError().json(ResponseError::new().build());
+    }
+
+    let mut ticker = interval(Duration::from_secs(configs.interval()));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // The first tick completes immediately
+    ticker.tick().await;
+
+    let state = (sampler, ticker, closing.subscribe());
+    let events = stream::unfold(state, |(mut sampler, mut ticker, mut closed)| async move {
+        // It's ended when the server stops, so the stream doesn't hold its shutdown
+        tokio::select! {
+            _ = ticker.tick() => (),
+            _ = closed.changed() => return None,
+        }
+        let event = match sampler.sample() {
+            Ok(sample) => format!("event: rates\ndata: {}\n\n", json!(sample)),
+            Err(err) => {
+                error!("Cannot sample interfaces: {err}");
+                format!(
+                    "event: error\ndata: {}\n\n",
+                    json!(ResponseError::new().build())
+                )
+            }
+        };
+        Some((
+            Ok::<_, actix_web::Error>(Bytes::from(event)),
+            (sampler, ticker, closed),
+        ))
+    });
+
+    HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, "text/event-stream"))
+        .insert_header((CACHE_CONTROL, "no-cache"))
+        .streaming(events)
+}
@@ -0,0 +1,197 @@
+use crate::{
+    api::auth::{
+        database::{DatabasePool, Keys},
+        password,
+    },
+    http::{
+        metrics::{Exposition, Metrics},
+        response::ResponseError,
+    },
+};
+use actix_web::{get, http::header::AUTHORIZATION, web, HttpRequest, HttpResponse};
+use app::{config::metrics::MetricsAuth, Configs};
+use libvnstat::{db::models::Interface, VnStat};
+use log::{error, warn};
+
+#[get("/metrics")]
+pub async fn get_metrics(req: HttpRequest, vnstat: web::Data<VnStat>) -> HttpResponse {
+    let configs = Configs::current().unwrap().metrics();
+    if !configs.enabled() {
+        return HttpResponse::NotFound().json(
+            ResponseError::new()
+                .code(404)
+                .details("service not found.")
+                .build(),
+        );
+    }
+    if !is_authorized(&req, configs.auth(), configs.token()) {
+        Metrics::auth_failure();
+        warn!(
+            "Metrics authentication failed \n\t IP address: {}",
+            req.connection_info()
+                .realip_remote_addr()
+                .unwrap_or("UNKNOWN")
+        );
+        return HttpResponse::Unauthorized().json(
+            ResponseError::new()
+                .code(401)
+                .details("Unauthorized")
+                .build(),
+        );
+    }
+
+    match render(&vnstat) {
+        Ok(body) => HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4; charset=utf-8")
+            .body(body),
+        Err(err) => {
+            error!("{err}");
+            HttpResponse::InternalServerError().json(ResponseError::new().build())
+        }
+    }
+}
+
+fn is_authorized(req: &HttpRequest, auth: MetricsAuth, token: Option<String>) -> bool {
+    let bearer = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(|t| t.trim().to_owned());
+
+    match (auth, bearer) {
+        (MetricsAuth::Open, _) => true,
+        (MetricsAuth::Token, Some(bearer)) => {
+            token.is_some_and(|t| password::matches_plain(&bearer, &t))
+        }
+        (MetricsAuth::Bearer, Some(bearer)) => {
+            match req.app_data::<web::Data<DatabasePool>>().unwrap().get() {
+                Ok(db) => Keys::is_valid(&db, &bearer),
+                Err(err) => {
+                    error!("{err}");
+                    false
+                }
+            }
+        }
+        (_, None) => false,
+    }
+}
+
+/// Name, type, help and value of a metric family of every interface
+type InterfaceFamily = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&Interface) -> i64,
+);
+
+fn render(vnstat: &VnStat) -> anyhow::Result<String> {
+    let mut exposition = Exposition::new();
+    let interfaces = vnstat.interface().get()?;
+
+    let families: [InterfaceFamily; 5] = [
+        (
+            "vnstat_interface_rx_bytes_total",
+            "counter",
+            "Total received bytes recorded by vnStat.",
+            |i| i.rxtotal,
+        ),
+        (
+            "vnstat_interface_tx_bytes_total",
+            "counter",
+            "Total transmitted bytes recorded by vnStat.",
+            |i| i.txtotal,
+        ),
+        (
+            "vnstat_interface_rx_counter_bytes",
+            "gauge",
+            "Last kernel rx counter seen by vnStat.",
+            |i| i.rxcounter,
+        ),
+        (
+            "vnstat_interface_tx_counter_bytes",
+            "gauge",
+            "Last kernel tx counter seen by vnStat.",
+            |i| i.txcounter,
+        ),
+        (
+            "vnstat_interface_active",
+            "gauge",
+            "Whether vnStat is monitoring the interface.",
+            |i| i.active as i64,
+        ),
+    ];
+    for (name, kind, help, value) in families {
+        exposition.family(name, kind, help);
+        for i in interfaces.iter() {
+            exposition.sample(name, &[("interface", &i.name)], value(i));
+        }
+    }
+
+    // Rates of the latest five minutes recorded by vnStat
+    let rates = vnstat
+        .traffic("fiveminute")
+        .latest()?
+        .into_iter()
+        .map(|latest| {
+            (
+                latest.interface_name,
+                latest.traffic.rx as f64 / 300.0,
+                latest.traffic.tx as f64 / 300.0,
+            )
+        })
+        .collect::<Vec<_>>();
+    exposition.family(
+        "vnstat_interface_rx_rate_bytes",
+        "gauge",
+        "Average received bytes per second of the latest five minutes.",
+    );
+    for (name, rx, _) in rates.iter() {
+        exposition.sample("vnstat_interface_rx_rate_bytes", &[("interface", name)], rx);
+    }
+    exposition.family(
+        "vnstat_interface_tx_rate_bytes",
+        "gauge",
+        "Average transmitted bytes per second of the latest five minutes.",
+    );
+    for (name, _, tx) in rates.iter() {
+        exposition.sample("vnstat_interface_tx_rate_bytes", &[("interface", name)], tx);
+    }
+
+    let daemon_up = match vnstat.daemon().is_active() {
+        Ok(is_active) => is_active as u8,
+        Err(err) => {
+            error!("{err}");
+            0
+        }
+    };
+    exposition
+        .family(
+            "vnstat_daemon_up",
+            "gauge",
+            "Whether vnStat daemon is running.",
+        )
+        .sample("vnstat_daemon_up", &[], daemon_up);
+
+    exposition.family(
+        "vnsd_http_requests_total",
+        "counter",
+        "HTTP requests served by vnsd by status code.",
+    );
+    for (status, count) in Metrics::requests() {
+        exposition.sample(
+            "vnsd_http_requests_total",
+            &[("status", &status.to_string())],
+            count,
+        );
+    }
+    exposition
+        .family(
+            "vnsd_auth_failures_total",
+            "counter",
+            "Failed logins and requests rejected by authentication.",
+        )
+        .sample("vnsd_auth_failures_total", &[], Metrics::auth_failures());
+
+    Ok(exposition.build())
+}
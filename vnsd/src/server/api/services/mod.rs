@@ -1,7 +1,11 @@
 pub mod auth;
 pub mod config;
 pub mod daemon;
+pub mod federation;
 pub mod info;
 pub mod interface;
+pub mod live;
+pub mod metrics;
 pub mod not_found;
 pub mod traffic;
+pub mod users;
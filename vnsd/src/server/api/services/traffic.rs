@@ -1,30 +1,203 @@
-use crate::http::response::{Response, ResponseError, ResponseStatus};
-use actix_web::{get, web, HttpResponse};
-use libvnstat::VnStat;
+use crate::http::{
+    export::{self, ExportFormat},
+    response::ResponseError,
+};
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use chrono::{NaiveDate, NaiveDateTime};
+use libvnstat::{TrafficInterval, TrafficOrder, VnStat, VnStatTraffic};
 use log::error;
+use serde_derive::Deserialize;
+use std::str::FromStr;
+
+/// Query string accepted by traffic endpoints
+///
+/// ### Example
+/// `/api/traffic/hour?from=2022-07-01&to=2022-07-02 12:00&order=desc&limit=24&offset=0`
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct TrafficQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub order: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+impl TrafficQuery {
+    /// Validate the query string and apply it on `traffic`
+    pub fn apply(&self, traffic: &mut VnStatTraffic) -> Result<(), String> {
+        if let Some(from) = &self.from {
+            traffic.from(parse_date(from).map_err(|e| format!("Invalid 'from' date: {e}"))?);
+        }
+        if let Some(to) = &self.to {
+            traffic.to(parse_date(to).map_err(|e| format!("Invalid 'to' date: {e}"))?);
+        }
+        if let Some(order) = &self.order {
+            traffic
+                .order(TrafficOrder::from_str(order).map_err(|e| format!("Invalid order: {e}"))?);
+        }
+        if let Some(limit) = self.limit {
+            traffic.limit(limit);
+        }
+        if let Some(offset) = self.offset {
+            traffic.offset(offset);
+        }
+        Ok(())
+    }
+}
+
+/// Query string accepted by traffic summary endpoint
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SummaryQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// Summarize only the interface with this name
+    pub interface: Option<String>,
+}
+
+/// Parse a date given in query string, a date without time means its midnight.
+fn parse_date(date: &str) -> Result<NaiveDateTime, String> {
+    let date = date.trim();
+    for format in [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(date, format) {
+            return Ok(date);
+        }
+    }
+    match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        Ok(date) => Ok(date.and_hms_opt(0, 0, 0).unwrap()),
+        Err(_) => Err(format!(
+            "'{date}' doesn't match 'YYYY-MM-DD' or 'YYYY-MM-DD hh:mm[:ss]'"
+        )),
+    }
+}
 
 #[get("/traffic/{interval}")]
-pub async fn get_traffic(interval: web::Path<String>) -> HttpResponse {
-    match VnStat.traffic(interval.as_str()).get() {
-        Ok(result) => HttpResponse::Ok().json(
-            Response::new()
-                .status(ResponseStatus::Success)
-                .data(&result)
+pub async fn get_traffic(
+    interval: web::Path<String>,
+    query: web::Query<TrafficQuery>,
+    vnstat: web::Data<VnStat>,
+    req: HttpRequest,
+) -> HttpResponse {
+    let format = match ExportFormat::negotiate(&req) {
+        Ok(format) => format,
+        Err(details) => return export::invalid_format(&details),
+    };
+    if !TrafficInterval::new(interval.as_str()).is_validated() {
+        return HttpResponse::NotFound().json(
+            ResponseError::new()
+                .code(404)
+                .details("Interval isn't found.")
+                .build(),
+        );
+    }
+
+    let mut traffic = vnstat.traffic(interval.as_str());
+    if let Err(details) = query.apply(&mut traffic) {
+        return HttpResponse::BadRequest()
+            .json(ResponseError::new().code(400).details(&details).build());
+    }
+
+    match traffic
+        .pages(export::PAGE_SIZE)
+        .and_then(|pages| export::respond_pages(format, pages, &format!("traffic-{interval}")))
+    {
+        Ok(res) => res,
+        Err(err) => error_response(err),
+    }
+}
+
+#[get("/traffic/{interval}/summary")]
+pub async fn get_traffic_summary(
+    interval: web::Path<String>,
+    query: web::Query<SummaryQuery>,
+    vnstat: web::Data<VnStat>,
+    req: HttpRequest,
+) -> HttpResponse {
+    let format = match ExportFormat::negotiate(&req) {
+        Ok(format) => format,
+        Err(details) => return export::invalid_format(&details),
+    };
+    if !TrafficInterval::new(interval.as_str()).is_validated() {
+        return HttpResponse::NotFound().json(
+            ResponseError::new()
+                .code(404)
+                .details("Interval isn't found.")
                 .build(),
-        ),
-        Err(err) => {
-            if let Some(err) = err.root_cause().downcast_ref::<std::io::Error>() {
+        );
+    }
+
+    let mut traffic = vnstat.traffic(interval.as_str());
+    if let Some(name) = &query.interface {
+        match vnstat.interface().find(name) {
+            Ok(Some(_)) => (),
+            Ok(None) => {
+                return HttpResponse::NotFound().json(
+                    ResponseError::new()
+                        .code(404)
+                        .details("Interface isn't found.")
+                        .build(),
+                )
+            }
+            Err(err) => {
                 error!("{err}");
-                if err.kind() == std::io::ErrorKind::InvalidInput {
-                    return HttpResponse::NotFound().json(
-                        ResponseError::new()
-                            .code(404)
-                            .details("Interval isn't found.")
-                            .build(),
-                    );
-                }
+                return HttpResponse::InternalServerError().json(ResponseError::new().build());
             }
-            HttpResponse::BadRequest().json(ResponseError::new().build())
         }
+        traffic.interface(name);
+    }
+    let bounds = TrafficQuery {
+        from: query.from.clone(),
+        to: query.to.clone(),
+        ..Default::default()
+    };
+    if let Err(details) = bounds.apply(&mut traffic) {
+        return HttpResponse::BadRequest()
+            .json(ResponseError::new().code(400).details(&details).build());
+    }
+
+    match traffic.summary() {
+        Ok(result) => export::respond(format, result, &format!("summary-{interval}")),
+        Err(err) => error_response(err),
+    }
+}
+
+/// Map errors of traffic queries to their HTTP responses
+pub fn error_response(err: anyhow::Error) -> HttpResponse {
+    if let Some(err) = err.root_cause().downcast_ref::<std::io::Error>() {
+        error!("{err}");
+        if err.kind() == std::io::ErrorKind::InvalidInput {
+            return HttpResponse::BadRequest().json(
+                ResponseError::new()
+                    .code(400)
+                    .details(&err.to_string())
+                    .build(),
+            );
+        }
+    }
+    HttpResponse::BadRequest().json(ResponseError::new().build())
+}
+
+#[test]
+async fn parse_date_from_query_string() {
+    assert_eq!(
+        parse_date("2022-07-01").unwrap(),
+        NaiveDate::from_ymd_opt(2022, 7, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    );
+    assert_eq!(
+        parse_date("2022-07-01 13:05").unwrap(),
+        NaiveDate::from_ymd_opt(2022, 7, 1)
+            .unwrap()
+            .and_hms_opt(13, 5, 0)
+            .unwrap()
+    );
+    for invalid in ["", "yesterday", "2022-13-01", "01-07-2022"] {
+        assert!(parse_date(invalid).is_err());
     }
 }
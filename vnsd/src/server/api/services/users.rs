@@ -0,0 +1,100 @@
+use crate::api::auth::{
+    database::{DatabasePool, Statements, Users},
+    scope::Role,
+};
+use crate::http::response::*;
+use actix_web::{delete, get, post, web, HttpResponse};
+use log::{error, info};
+use serde_derive::Deserialize;
+use serde_json::json;
+use std::io::{self, ErrorKind};
+
+#[get("/users")]
+pub async fn get_users(db: web::Data<DatabasePool>) -> HttpResponse {
+    let db = match db.get() {
+        Ok(db) => db,
+        Err(err) => {
+            error!("Cannot connect to authentication database: {err}");
+            return HttpResponse::InternalServerError().json(ResponseError::new().build());
+        }
+    };
+    HttpResponse::Ok().json(
+        Response::new()
+            .status(ResponseStatus::Success)
+            .data(&Users::select(&db, |_| true))
+            .build(),
+    )
+}
+
+#[derive(Deserialize)]
+pub struct Payload {
+    username: String,
+    password: String,
+    role: Role,
+}
+
+#[post("/users")]
+pub async fn add_user(payload: web::Json<Payload>, db: web::Data<DatabasePool>) -> HttpResponse {
+    let db = match db.get() {
+        Ok(db) => db,
+        Err(err) => {
+            error!("Cannot connect to authentication database: {err}");
+            return HttpResponse::InternalServerError().json(ResponseError::new().build());
+        }
+    };
+    match Users::add(&db, &payload.username, &payload.password, payload.role) {
+        Ok(user) => {
+            info!("User '{}' was added as {}", user.username, user.role);
+            HttpResponse::Created().json(
+                Response::new()
+                    .status(ResponseStatus::Success)
+                    .data(&user)
+                    .build(),
+            )
+        }
+        Err(err) => error_response(err),
+    }
+}
+
+/// Remove a user, its keys are revoked with it
+#[delete("/users/{username}")]
+pub async fn remove_user(username: web::Path<String>, db: web::Data<DatabasePool>) -> HttpResponse {
+    let db = match db.get() {
+        Ok(db) => db,
+        Err(err) => {
+            error!("Cannot connect to authentication database: {err}");
+            return HttpResponse::InternalServerError().json(ResponseError::new().build());
+        }
+    };
+    match Users::remove(&db, &username) {
+        Ok(()) => {
+            info!("User '{username}' was removed");
+            HttpResponse::Ok().json(
+                Response::new()
+                    .status(ResponseStatus::Success)
+                    .data(json!({ "details": format!("User '{username}' was removed") }))
+                    .build(),
+            )
+        }
+        Err(err) => error_response(err),
+    }
+}
+
+pub fn error_response(err: anyhow::Error) -> HttpResponse {
+    let (mut response, code) = match err.downcast_ref::<io::Error>().map(|e| e.kind()) {
+        Some(ErrorKind::InvalidInput) => (HttpResponse::BadRequest(), 400),
+        Some(ErrorKind::PermissionDenied) => (HttpResponse::Forbidden(), 403),
+        Some(ErrorKind::AlreadyExists) => (HttpResponse::Conflict(), 409),
+        Some(ErrorKind::NotFound) => (HttpResponse::NotFound(), 404),
+        _ => {
+            error!("{err}");
+            return HttpResponse::InternalServerError().json(ResponseError::new().build());
+        }
+    };
+    response.json(
+        ResponseError::new()
+            .code(code)
+            .details(&err.to_string())
+            .build(),
+    )
+}
@@ -0,0 +1,245 @@
+use super::response::{Response, ResponseError, ResponseStatus};
+use actix_web::{
+    error::ErrorInternalServerError,
+    http::header::{ACCEPT, CONTENT_DISPOSITION},
+    web::Bytes,
+    HttpRequest, HttpResponse,
+};
+use futures::{stream, StreamExt, TryStreamExt};
+use log::error;
+use serde::Serialize;
+use serde_json::Value;
+use std::iter;
+
+/// Rows fetched at a time while CSV and NDJSON bodies are streamed
+pub const PAGE_SIZE: u32 = 500;
+
+/// Formats rows can be exported in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// Rows in `Response` envelope
+    Json,
+    /// Comma separated values with a header line, nested fields are flattened (e.g. `peak.rx`)
+    Csv,
+    /// A JSON document per line
+    Ndjson,
+}
+
+impl ExportFormat {
+    /// Take the format from `format` in query string, otherwise from `Accept` header.
+    /// JSON is used when neither of them asks for another format.
+    pub fn negotiate(req: &HttpRequest) -> Result<Self, String> {
+        let format = req
+            .query_string()
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == "format")
+            .map(|(_, value)| value.to_lowercase());
+        if let Some(format) = format {
+            return match format.as_str() {
+                "json" => Ok(Self::Json),
+                "csv" => Ok(Self::Csv),
+                "ndjson" => Ok(Self::Ndjson),
+                _ => Err(format!(
+                    "Invalid format: '{format}', it must be one of 'json', 'csv' or 'ndjson'"
+                )),
+            };
+        }
+
+        let accept = req
+            .headers()
+            .get(ACCEPT)
+            .and_then(|h| h.to_str().ok())
+            .unwrap_or_default()
+            .to_lowercase();
+        Ok(
+            match accept
+                .split(',')
+                .map(|media| media.split(';').next().unwrap_or_default().trim())
+                .find(|media| {
+                    ["text/csv", "application/x-ndjson", "application/ndjson"].contains(media)
+                }) {
+                Some("text/csv") => Self::Csv,
+                Some(_) => Self::Ndjson,
+                None => Self::Json,
+            },
+        )
+    }
+
+    pub fn content_type(&self) -> &str {
+        match self {
+            Self::Json => "application/json",
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Ndjson => "application/x-ndjson",
+        }
+    }
+}
+
+/// Respond with `rows` in `format`.
+///
+/// `name` is the file name suggested to clients for CSV downloads, without extension.
+pub fn respond<T>(format: ExportFormat, rows: Vec<T>, name: &str) -> HttpResponse
+where
+    T: Serialize + 'static,
+{
+    match format {
+        ExportFormat::Json => HttpResponse::Ok().json(
+            Response::new()
+                .status(ResponseStatus::Success)
+                .data(&rows)
+                .build(),
+        ),
+        _ => stream_pages(format, rows, iter::empty(), name),
+    }
+}
+
+/// Respond with rows of `pages` in `format`, CSV and NDJSON bodies are streamed row by row
+/// while the next pages are fetched. JSON bodies are built at once, since rows are wrapped
+/// in the `Response` envelope.
+///
+/// The first page is fetched before responding, so its error is returned to be responded
+/// with. An error of a later page ends the streamed body.
+pub fn respond_pages<T, P>(
+    format: ExportFormat,
+    mut pages: P,
+    name: &str,
+) -> anyhow::Result<HttpResponse>
+where
+    T: Serialize + 'static,
+    P: Iterator<Item = anyhow::Result<Vec<T>>> + 'static,
+{
+    let first = pages.next().transpose()?.unwrap_or_default();
+    match format {
+        ExportFormat::Json => {
+            let mut rows = first;
+            for page in pages {
+                rows.extend(page?);
+            }
+            Ok(respond(format, rows, name))
+        }
+        _ => Ok(stream_pages(format, first, pages, name)),
+    }
+}
+
+fn stream_pages<T, P>(format: ExportFormat, first: Vec<T>, rest: P, name: &str) -> HttpResponse
+where
+    T: Serialize + 'static,
+    P: Iterator<Item = anyhow::Result<Vec<T>>> + 'static,
+{
+    let pages = iter::once(Ok(first)).chain(rest).map(|page| {
+        page.map_err(|err| {
+            error!("Cannot fetch rows to export: {err}");
+            ErrorInternalServerError(err)
+        })
+    });
+    let rows = stream::iter(pages)
+        .map_ok(|rows| stream::iter(rows.into_iter().map(Ok::<_, actix_web::Error>)))
+        .try_flatten()
+        .enumerate();
+
+    match format {
+        ExportFormat::Csv => HttpResponse::Ok()
+            .content_type(format.content_type())
+            .insert_header((
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{name}.csv\""),
+            ))
+            .streaming(rows.map(|(i, row)| {
+                let row = flatten(serde_json::to_value(&row?)?);
+                let mut chunk = vec![];
+                // Columns are taken from the first row
+                if i == 0 {
+                    chunk.extend(csv_line(row.iter().map(|(k, _)| k.as_str()))?);
+                }
+                chunk.extend(csv_line(row.iter().map(|(_, v)| v.as_str()))?);
+                Ok::<_, actix_web::Error>(Bytes::from(chunk))
+            })),
+        // JSON bodies aren't streamed
+        _ => HttpResponse::Ok()
+            .content_type(format.content_type())
+            .streaming(rows.map(|(_, row)| {
+                let mut line = serde_json::to_vec(&row?)?;
+                line.push(b'\n');
+                Ok::<_, actix_web::Error>(Bytes::from(line))
+            })),
+    }
+}
+
+/// Respond with `400` for an invalid format
+pub fn invalid_format(details: &str) -> HttpResponse {
+    HttpResponse::BadRequest().json(ResponseError::new().code(400).details(details).build())
+}
+
+/// Flatten nested objects of `value` to `parent.child` columns
+fn flatten(value: Value) -> Vec<(String, String)> {
+    fn walk(prefix: &str, value: Value, columns: &mut Vec<(String, String)>) {
+        match value {
+            Value::Object(fields) => {
+                for (key, value) in fields {
+                    let key = match prefix.is_empty() {
+                        true => key,
+                        false => format!("{prefix}.{key}"),
+                    };
+                    walk(&key, value, columns);
+                }
+            }
+            Value::Null => columns.push((prefix.to_owned(), String::new())),
+            Value::String(s) => columns.push((prefix.to_owned(), s)),
+            value => columns.push((prefix.to_owned(), value.to_string())),
+        }
+    }
+    let mut columns = vec![];
+    walk("", value, &mut columns);
+    columns
+}
+
+fn csv_line<'a>(fields: impl Iterator<Item = &'a str>) -> std::io::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(fields)?;
+    writer.into_inner().map_err(|e| e.into_error())
+}
+
+#[test]
+async fn flatten_nested_rows_to_csv() {
+    let row = serde_json::json!({
+        "interface_name": "eth0, \"uplink\"",
+        "alias": null,
+        "rx": 100,
+        "peak": { "date": "2022-07-01", "total": 300 },
+    });
+    let columns = flatten(row);
+    assert_eq!(
+        columns.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>(),
+        vec!["interface_name", "alias", "rx", "peak.date", "peak.total"]
+    );
+    assert_eq!(
+        String::from_utf8(csv_line(columns.iter().map(|(_, v)| v.as_str())).unwrap()).unwrap(),
+        "\"eth0, \"\"uplink\"\"\",,100,2022-07-01,300\n"
+    );
+}
+
+#[test]
+async fn negotiate_export_format() {
+    use actix_web::test::TestRequest;
+
+    let format = |req: TestRequest| ExportFormat::negotiate(&req.to_http_request());
+    assert_eq!(format(TestRequest::get()), Ok(ExportFormat::Json));
+    assert_eq!(
+        format(TestRequest::get().insert_header((ACCEPT, "text/csv;q=0.9, */*"))),
+        Ok(ExportFormat::Csv)
+    );
+    assert_eq!(
+        format(TestRequest::get().insert_header((ACCEPT, "application/x-ndjson"))),
+        Ok(ExportFormat::Ndjson)
+    );
+    // Query string takes precedence over `Accept` header
+    assert_eq!(
+        format(
+            TestRequest::get()
+                .uri("/api/traffic/day?limit=1&format=ndjson")
+                .insert_header((ACCEPT, "text/csv"))
+        ),
+        Ok(ExportFormat::Ndjson)
+    );
+    assert!(format(TestRequest::get().uri("/api/traffic/day?format=xml")).is_err());
+}
@@ -0,0 +1,111 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+static REQUESTS: Mutex<BTreeMap<u16, u64>> = Mutex::new(BTreeMap::new());
+static AUTH_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+/// Counters of vnsd itself exposed in `/metrics`
+pub struct Metrics;
+
+impl Metrics {
+    /// Count a served request by its response status code
+    pub fn request(status: u16) {
+        *REQUESTS.lock().unwrap().entry(status).or_insert(0) += 1;
+    }
+    pub fn auth_failure() {
+        AUTH_FAILURES.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn requests() -> BTreeMap<u16, u64> {
+        REQUESTS.lock().unwrap().clone()
+    }
+    pub fn auth_failures() -> u64 {
+        AUTH_FAILURES.load(Ordering::Relaxed)
+    }
+}
+
+/// Builder of Prometheus text exposition format
+#[derive(Default)]
+pub struct Exposition {
+    body: String,
+}
+
+impl Exposition {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a metric family, `kind` is `counter` or `gauge`
+    pub fn family(&mut self, name: &str, kind: &str, help: &str) -> &mut Self {
+        writeln!(self.body, "# HELP {name} {help}").unwrap();
+        writeln!(self.body, "# TYPE {name} {kind}").unwrap();
+        self
+    }
+
+    pub fn sample<V: std::fmt::Display>(
+        &mut self,
+        name: &str,
+        labels: &[(&str, &str)],
+        value: V,
+    ) -> &mut Self {
+        let labels = labels
+            .iter()
+            .map(|(k, v)| format!("{k}=\"{}\"", escape_label(v)))
+            .collect::<Vec<String>>();
+        match labels.is_empty() {
+            true => writeln!(self.body, "{name} {value}").unwrap(),
+            false => writeln!(self.body, "{name}{{{}}} {value}", labels.join(",")).unwrap(),
+        };
+        self
+    }
+
+    pub fn build(&self) -> String {
+        self.body.clone()
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[test]
+async fn render_exposition_format() {
+    let body = Exposition::new()
+        .family(
+            "vnsd_auth_failures_total",
+            "counter",
+            "Failed authentications.",
+        )
+        .sample("vnsd_auth_failures_total", &[], 3)
+        .family(
+            "vnstat_interface_rx_bytes_total",
+            "counter",
+            "Received bytes.",
+        )
+        .sample(
+            "vnstat_interface_rx_bytes_total",
+            &[("interface", "eth\"0")],
+            10,
+        )
+        .build();
+
+    assert_eq!(
+        body,
+        "# HELP vnsd_auth_failures_total Failed authentications.
+# TYPE vnsd_auth_failures_total counter
+vnsd_auth_failures_total 3
+# HELP vnstat_interface_rx_bytes_total Received bytes.
+# TYPE vnstat_interface_rx_bytes_total counter
+vnstat_interface_rx_bytes_total{interface=\"eth\\\"0\"} 10
+"
+    );
+}
@@ -1 +1,3 @@
+pub mod export;
+pub mod metrics;
 pub mod response;
@@ -0,0 +1,262 @@
+use actix_tls::accept::rustls_0_21::TlsStream;
+use actix_web::{
+    dev::Extensions,
+    rt::net::{TcpStream, UnixStream},
+};
+use app::config::server::ListenerConfigs;
+use std::{
+    any::Any,
+    fmt, fs,
+    io::{self, ErrorKind},
+    net::{SocketAddr, ToSocketAddrs},
+    os::unix::{fs::FileTypeExt, net::UnixListener},
+    path::PathBuf,
+};
+
+/// Where a listener accepts connections
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListenAddr {
+    /// A hostname or an IP address, `::` accepts IPv4 clients too (dual stack)
+    Tcp {
+        host: String,
+        port: u16,
+    },
+    Unix(PathBuf),
+}
+
+impl ListenAddr {
+    /// Parse `<ip>:<port>`, `[<ipv6>]:<port>` or `unix:<path>`
+    pub fn parse(address: &str) -> Result<Self, String> {
+        let address = address.trim();
+        if let Some(path) = address.strip_prefix("unix:") {
+            return match path.is_empty() {
+                true => Err(format!("Invalid listen address '{address}': no path")),
+                false => Ok(Self::Unix(PathBuf::from(path))),
+            };
+        }
+        let (host, port) = match address.rsplit_once(':') {
+            // IPv6 addresses are enclosed in brackets, otherwise their last part is the port
+            Some((host, port)) if host.starts_with('[') && host.ends_with(']') => {
+                (&host[1..host.len() - 1], port)
+            }
+            Some((host, port)) if !host.contains(':') => (host, port),
+            _ => ("", ""),
+        };
+        match host.is_empty() {
+            false => Ok(Self::Tcp {
+                host: host.to_owned(),
+                port: port
+                    .parse()
+                    .map_err(|_| format!("Invalid listen address '{address}': invalid port"))?,
+            }),
+            true => Err(format!(
+                "Invalid listen address '{address}': '<ip>:<port>' or 'unix:<path>' is expected"
+            )),
+        }
+    }
+
+    /// Whether only clients of this host can connect
+    pub fn is_local(&self) -> bool {
+        match self {
+            Self::Tcp { host, .. } => crate::utils::is_loopback(host),
+            Self::Unix(_) => true,
+        }
+    }
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Tcp { host, port } if host.contains(':') => write!(f, "[{host}]:{port}"),
+            Self::Tcp { host, port } => write!(f, "{host}:{port}"),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listener {
+    pub addr: ListenAddr,
+    /// Whether requests of `config` and `daemon` scopes are accepted
+    pub mutating: bool,
+}
+
+impl Listener {
+    pub fn new(addr: ListenAddr, mutating: bool) -> Self {
+        Self { addr, mutating }
+    }
+
+    pub fn from_configs(configs: &ListenerConfigs) -> Result<Self, String> {
+        Ok(Self::new(
+            ListenAddr::parse(&configs.address)?,
+            configs.mutating,
+        ))
+    }
+
+    pub fn is_unix(&self) -> bool {
+        matches!(self.addr, ListenAddr::Unix(_))
+    }
+
+    /// e.g. `https://0.0.0.0:8080` or `unix:/run/vnstat-server/api.sock`, unix sockets are
+    /// served by plain HTTP
+    pub fn url(&self, tls: bool) -> String {
+        match (&self.addr, tls) {
+            (ListenAddr::Unix(_), _) => self.addr.to_string(),
+            (_, true) => format!("https://{}", self.addr),
+            (_, false) => format!("http://{}", self.addr),
+        }
+    }
+}
+
+/// An address a listener was bound to
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoundAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+/// Listeners by the addresses they're bound to, so the listener of a connection is known
+#[derive(Debug, Clone, Default)]
+pub struct BoundListeners(Vec<(BoundAddr, Listener)>);
+
+impl BoundListeners {
+    /// Resolve addresses of `listeners`, a hostname may be resolved to several addresses
+    pub fn resolve(listeners: &[Listener]) -> io::Result<Self> {
+        let mut bound = vec![];
+        for listener in listeners {
+            match &listener.addr {
+                ListenAddr::Tcp { host, port } => {
+                    for addr in (host.as_str(), *port).to_socket_addrs()? {
+                        bound.push((BoundAddr::Tcp(addr), listener.clone()));
+                    }
+                }
+                ListenAddr::Unix(path) => {
+                    bound.push((BoundAddr::Unix(path.clone()), listener.clone()))
+                }
+            }
+        }
+        Ok(Self(bound))
+    }
+
+    pub fn addrs(&self) -> impl Iterator<Item = &BoundAddr> {
+        self.0.iter().map(|(addr, _)| addr)
+    }
+
+    /// Put the listener of a connection into its data, it's the handler of `HttpServer::on_connect`
+    pub fn on_connect(&self, conn: &dyn Any, data: &mut Extensions) {
+        let local_addr = if let Some(stream) = conn.downcast_ref::<TcpStream>() {
+            stream.local_addr().ok().map(BoundAddr::Tcp)
+        } else if let Some(stream) = conn.downcast_ref::<TlsStream<TcpStream>>() {
+            stream.get_ref().0.local_addr().ok().map(BoundAddr::Tcp)
+        } else if let Some(stream) = conn.downcast_ref::<UnixStream>() {
+            stream
+                .local_addr()
+                .ok()
+                .and_then(|addr| addr.as_pathname().map(PathBuf::from))
+                .map(BoundAddr::Unix)
+        } else {
+            None
+        };
+        if let Some(listener) = local_addr.and_then(|addr| self.find(&addr)) {
+            data.insert(listener.clone());
+        }
+    }
+
+    /// Listener of a local address, listeners of unspecified addresses (`0.0.0.0` or `::`)
+    /// accept connections on every address of their port
+    fn find(&self, local_addr: &BoundAddr) -> Option<&Listener> {
+        let exact = self.0.iter().find(|(addr, _)| addr == local_addr);
+        let unspecified = || {
+            self.0.iter().find(|(addr, _)| match (addr, local_addr) {
+                (BoundAddr::Tcp(addr), BoundAddr::Tcp(local_addr)) => {
+                    addr.ip().is_unspecified() && addr.port() == local_addr.port()
+                }
+                _ => false,
+            })
+        };
+        exact.or_else(unspecified).map(|(_, listener)| listener)
+    }
+}
+
+/// Bind a unix socket, a socket file that was left by a previous run is replaced
+pub fn bind_unix(path: &PathBuf) -> io::Result<UnixListener> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path)?,
+        Ok(_) => {
+            return Err(io::Error::new(
+                ErrorKind::AlreadyExists,
+                format!("'{}' exists and isn't a socket", path.display()),
+            ))
+        }
+        Err(_) => (),
+    }
+    UnixListener::bind(path)
+}
+
+/// Remove the socket file of a unix listener, other files aren't removed
+pub fn remove_unix(path: &PathBuf) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+#[test]
+async fn parse_listen_addresses() {
+    let tcp = |host: &str, port| ListenAddr::Tcp {
+        host: host.to_owned(),
+        port,
+    };
+    assert_eq!(ListenAddr::parse("0.0.0.0:8080"), Ok(tcp("0.0.0.0", 8080)));
+    assert_eq!(ListenAddr::parse("[::]:8080"), Ok(tcp("::", 8080)));
+    assert_eq!(ListenAddr::parse("localhost:80"), Ok(tcp("localhost", 80)));
+    assert_eq!(
+        ListenAddr::parse("unix:/run/vnstat-server/api.sock"),
+        Ok(ListenAddr::Unix(PathBuf::from(
+            "/run/vnstat-server/api.sock"
+        )))
+    );
+    for invalid in [
+        "0.0.0.0",
+        ":8080",
+        "::1",
+        "::1:8080",
+        "[]:8080",
+        "0.0.0.0:http",
+        "0.0.0.0:70000",
+        "unix:",
+    ] {
+        assert!(ListenAddr::parse(invalid).is_err(), "{invalid}");
+    }
+    assert_eq!(tcp("::1", 8080).to_string(), "[::1]:8080");
+    assert!(tcp("::1", 8080).is_local());
+    assert!(!tcp("::", 8080).is_local());
+}
+
+#[test]
+async fn find_listener_of_connection() {
+    let listeners = BoundListeners::resolve(&[
+        Listener::new(ListenAddr::parse("127.0.0.1:8080").unwrap(), true),
+        Listener::new(ListenAddr::parse("[::]:8080").unwrap(), false),
+        Listener::new(ListenAddr::parse("unix:/tmp/vnsd.sock").unwrap(), true),
+    ])
+    .unwrap();
+    let mutating = |addr: BoundAddr| listeners.find(&addr).map(|l| l.mutating);
+
+    assert_eq!(
+        mutating(BoundAddr::Tcp("127.0.0.1:8080".parse().unwrap())),
+        Some(true)
+    );
+    assert_eq!(
+        mutating(BoundAddr::Tcp("[::ffff:10.0.0.2]:8080".parse().unwrap())),
+        Some(false)
+    );
+    assert_eq!(
+        mutating(BoundAddr::Tcp("10.0.0.2:9090".parse().unwrap())),
+        None
+    );
+    assert_eq!(
+        mutating(BoundAddr::Unix(PathBuf::from("/tmp/vnsd.sock"))),
+        Some(true)
+    );
+}
@@ -1,28 +1,44 @@
 pub mod api;
 pub mod http;
+pub mod listener;
+pub mod tls;
 
-use api::{auth::Auth, services};
-use app;
-use http::response::ResponseError;
+use api::{
+    auth::{
+        database::{DatabasePool, InitDatabase},
+        ip::AllowList,
+        rate_limit::RateLimiter,
+        Auth,
+    },
+    services,
+};
+use app::{self, config::server::ListenerConfigs};
+use http::{metrics::Metrics, response::ResponseError};
+use listener::{bind_unix, remove_unix, BoundAddr, BoundListeners, ListenAddr, Listener};
+use tls::Tls;
 
 use actix_server::{Server as ActixServer, ServerHandle as ActixServerHandle};
 use actix_web::{
+    dev::Service,
     error,
     middleware::Logger,
     web::{self, route},
     App, HttpResponse, HttpServer,
 };
 use actix_web_httpauth::middleware::HttpAuthentication;
+use libvnstat::VnStat;
+use log::error;
 use std::{
     error::Error as ErrorTrait,
-    io::Result as IOResult,
+    io::{Error as IOError, ErrorKind::Other, Result as IOResult},
     pin::Pin,
     string::ToString,
     sync::{
         atomic::{AtomicUsize, Ordering},
-        Arc, Mutex,
+        Arc, RwLock,
     },
 };
+use tokio::sync::{watch, Mutex as AsyncMutex, RwLock as AsyncRwLock};
 #[derive(Clone)]
 pub struct ServerHandlingError {
     cause: String,
@@ -79,57 +95,157 @@ impl ErrorTrait for ServerHandlingErrorKind {
     }
 }
 
-type ActixServerRunner = Arc<Mutex<Pin<Box<ActixServer>>>>;
+/// Database pools shared by every worker of the server
+#[derive(Clone)]
+pub struct ServerData {
+    pub vnstat: web::Data<VnStat>,
+    pub auth_db: web::Data<DatabasePool>,
+    /// Client of upstream instances in federation mode
+    pub http_client: web::Data<reqwest::Client>,
+    /// Failures of authentication per IP address
+    pub rate_limiter: web::Data<RateLimiter>,
+    /// Ranges that are only allowed to connect, they're replaced when configs are reloaded
+    pub allow_list: web::Data<RwLock<AllowList>>,
+    /// Certificates of HTTPS, plain HTTP is served when it's `None`
+    pub tls: Option<Arc<Tls>>,
+}
+
+impl ServerData {
+    /// Open the pools and initialize the authentication database, it should be done once at startup
+    pub fn init() -> anyhow::Result<Self> {
+        Ok(Self {
+            vnstat: web::Data::new(VnStat::default()),
+            auth_db: web::Data::new(InitDatabase::pool()?),
+            http_client: web::Data::new(reqwest::Client::new()),
+            rate_limiter: web::Data::new(RateLimiter::from_configs()),
+            allow_list: web::Data::new(RwLock::new(
+                AllowList::from_configs().map_err(anyhow::Error::msg)?,
+            )),
+            tls: Tls::from_configs()?.map(Arc::new),
+        })
+    }
+}
+
+type ActixServerRunner = Arc<AsyncMutex<Pin<Box<ActixServer>>>>;
 #[derive(Clone)]
 pub struct Server {
-    addr: ServerAddr,
-    runner: ActixServerRunner,
-    handler: Arc<ActixServerHandle>,
+    addr: Arc<RwLock<ServerAddr>>,
+    routes: Routes,
+    data: ServerData,
+    /// Replaced when the server is rebound, `run` goes on with the new one
+    runner: Arc<AsyncRwLock<ActixServerRunner>>,
+    handler: Arc<RwLock<ActixServerHandle>>,
     status: ServerStatus,
+    /// Notified when the server is stopped or rebound, so endless responses, e.g. of `/live`, end
+    closing: web::Data<watch::Sender<()>>,
 }
 
 impl Server {
     pub fn default() -> IOResult<Self> {
-        let addr = ServerAddr::from_config_file();
-        let runner = ServerRunner::new(addr.clone())?;
-        Ok(Self {
-            addr,
-            handler: Arc::new(runner.handle()),
-            runner: Arc::new(Mutex::new(Box::pin(runner))),
-            status: ServerStatus::new(ServerStatusState::InActive),
-        })
+        let data = ServerData::init().map_err(|e| IOError::new(Other, e.to_string()))?;
+        let addr = ServerAddr::from_config_file().map_err(|e| IOError::new(Other, e))?;
+        Self::new(addr, data)
+    }
+
+    pub fn new(addr: ServerAddr, data: ServerData) -> IOResult<Self> {
+        Self::with_routes(addr, data, Routes::All)
     }
 
-    pub fn new(addr: ServerAddr) -> IOResult<Self> {
-        let runner = ServerRunner::new(addr.clone())?;
+    pub fn with_routes(addr: ServerAddr, data: ServerData, routes: Routes) -> IOResult<Self> {
+        let closing = web::Data::new(watch::channel(()).0);
+        let runner = ServerRunner::new(addr.clone(), data.clone(), routes, closing.clone())?;
         Ok(Self {
-            addr,
-            handler: Arc::new(runner.handle()),
-            runner: Arc::new(Mutex::new(Box::pin(runner))),
+            addr: Arc::new(RwLock::new(addr)),
+            routes,
+            data,
+            handler: Arc::new(RwLock::new(runner.handle())),
+            runner: Arc::new(AsyncRwLock::new(Arc::new(AsyncMutex::new(Box::pin(runner))))),
             status: ServerStatus::new(ServerStatusState::InActive),
+            closing,
         })
     }
-    pub fn address(&self) -> (String, u16) {
-        self.addr.get_tuple()
+    pub fn addr(&self) -> ServerAddr {
+        self.addr.read().unwrap().clone()
+    }
+
+    pub fn listeners(&self) -> Vec<Listener> {
+        self.addr().listeners().to_vec()
+    }
+
+    pub fn routes(&self) -> Routes {
+        self.routes
+    }
+
+    pub fn data(&self) -> &ServerData {
+        &self.data
     }
 
     pub fn status(&self) -> &ServerStatus {
         &self.status
     }
 
+    fn handler(&self) -> ActixServerHandle {
+        self.handler.read().unwrap().clone()
+    }
+
     pub async fn run(&self) -> IOResult<()> {
         self.status.active();
-        let runner = Arc::clone(&self.runner);
-        let mut guard = runner.lock().unwrap();
-
-        match (&mut *guard).await {
-            Err(e) => {
+        loop {
+            let runner = Arc::clone(&*self.runner.read().await);
+            if let Err(e) = (&mut *runner.lock().await).await {
                 self.status.inactive();
                 return Err(e);
             }
-            _ => (),
+            // It's stopped, unless it was rebound and there's a new runner
+            if Arc::ptr_eq(&runner, &*self.runner.read().await) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Listen on `addr` instead of the current listeners, the server is stopped gracefully
+    /// and run again. The current listeners are kept when `addr` cannot be bound.
+    pub async fn rebind(&self, addr: ServerAddr) -> IOResult<()> {
+        if self.status.is_inactive() {
+            return Err(IOError::new(Other, "server was stopped"));
+        }
+        // `run` waits for the new runner while it's locked
+        let mut runner = self.runner.write().await;
+        // Addresses of both may be the same, so the new one is bound after the current one is stopped
+        self.closing.send_replace(());
+        self.handler().stop(true).await;
+        let bound = ServerRunner::new(
+            addr.clone(),
+            self.data.clone(),
+            self.routes,
+            self.closing.clone(),
+        );
+        let (new_runner, res) = match bound {
+            Ok(new_runner) => {
+                *self.addr.write().unwrap() = addr;
+                (new_runner, Ok(()))
+            }
+            Err(e) => match ServerRunner::new(
+                self.addr(),
+                self.data.clone(),
+                self.routes,
+                self.closing.clone(),
+            ) {
+                Ok(new_runner) => (new_runner, Err(e)),
+                Err(e) => {
+                    self.status.inactive();
+                    return Err(e);
+                }
+            },
         };
-        Ok(())
+        *self.handler.write().unwrap() = new_runner.handle();
+        *runner = Arc::new(AsyncMutex::new(Box::pin(new_runner)));
+        drop(runner);
+
+        if self.status.is_idle() {
+            self.handler().pause().await;
+        }
+        res
     }
 
     pub async fn pause(&self) -> Result<(), ServerHandlingError> {
@@ -146,7 +262,7 @@ impl Server {
             ));
         }
         self.status.idle();
-        self.handler.pause().await;
+        self.handler().pause().await;
         Ok(())
     }
     pub async fn resume(&self) -> Result<(), ServerHandlingError> {
@@ -163,7 +279,7 @@ impl Server {
             ));
         }
         self.status.active();
-        self.handler.resume().await;
+        self.handler().resume().await;
         Ok(())
     }
     pub async fn stop(&self) -> Result<(), ServerHandlingError> {
@@ -175,17 +291,94 @@ impl Server {
         }
         self.status.inactive();
 
-        self.handler.stop(true).await;
+        self.closing.send_replace(());
+        self.handler().stop(true).await;
         Ok(())
     }
 }
 
+/// The public server and the admin server, the admin one is only run when it's enabled.
+/// The public server serves every endpoint when there isn't an admin one.
+#[derive(Clone)]
+pub struct Servers {
+    pub public: Server,
+    pub admin: Option<Server>,
+}
+
+impl Servers {
+    pub fn new(public: ServerAddr, admin: Option<ServerAddr>, data: ServerData) -> IOResult<Self> {
+        Ok(match admin {
+            Some(admin) => Self {
+                public: Server::with_routes(public, data.clone(), Routes::Public)?,
+                admin: Some(Server::with_routes(admin, data, Routes::All)?),
+            },
+            None => Self {
+                public: Server::new(public, data)?,
+                admin: None,
+            },
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Server> {
+        std::iter::once(&self.public).chain(self.admin.as_ref())
+    }
+
+    /// Stop the servers gracefully, open connections are drained for `shutdown_timeout` of
+    /// `server` configs. Socket files of unix listeners are removed.
+    pub async fn stop(&self) {
+        let admin = async {
+            if let Some(admin) = &self.admin {
+                let _ = admin.stop().await;
+            }
+        };
+        let _ = futures::join!(self.public.stop(), admin);
+        for listener in self.iter().flat_map(Server::listeners) {
+            if let ListenAddr::Unix(path) = listener.addr {
+                if let Err(e) = remove_unix(&path) {
+                    error!("Cannot remove unix socket '{}': {e}", path.display());
+                }
+            }
+        }
+    }
+
+    /// Run the servers until both of them are stopped
+    pub async fn run(&self) -> IOResult<()> {
+        let admin = async {
+            match &self.admin {
+                Some(admin) => admin.run().await,
+                None => Ok(()),
+            }
+        };
+        let (public, admin) = futures::join!(self.public.run(), admin);
+        public.and(admin)
+    }
+}
+
 pub struct ServerRunner;
 
 impl ServerRunner {
-    pub fn new(addr: ServerAddr) -> IOResult<ActixServer> {
-        match HttpServer::new(|| {
-            App::new().app_data(web::JsonConfig::default().error_handler(|err,_| {
+    pub fn new(
+        addr: ServerAddr,
+        data: ServerData,
+        routes: Routes,
+        closing: web::Data<watch::Sender<()>>,
+    ) -> IOResult<ActixServer> {
+        let tls = data.tls.clone();
+        let bound = BoundListeners::resolve(addr.listeners())?;
+        let on_connect = bound.clone();
+        let shutdown_timeout = app::Configs::current()
+            .map_err(|e| IOError::new(Other, e.to_string()))?
+            .server()
+            .shutdown_timeout();
+        let mut server = HttpServer::new(move || {
+            let allow_list = data.allow_list.clone();
+            App::new()
+            .app_data(data.vnstat.clone())
+            .app_data(data.auth_db.clone())
+            .app_data(data.http_client.clone())
+            .app_data(data.rate_limiter.clone())
+            .app_data(closing.clone())
+            .app_data(web::JsonConfig::default().error_handler(|err,_| {
                 error::InternalError::from_response(err.to_string().clone(), HttpResponse::BadRequest().json(
                     ResponseError::new()
                         .code(400)
@@ -193,56 +386,176 @@ impl ServerRunner {
                         .build()
                  )).into()
             }))
+            .app_data(web::QueryConfig::default().error_handler(|err,_| {
+                error::InternalError::from_response(err.to_string().clone(), HttpResponse::BadRequest().json(
+                    ResponseError::new()
+                        .code(400)
+                        .details(&err.to_string())
+                        .build()
+                 )).into()
+            }))
+            .wrap_fn(move |req, srv| {
+                // The peer address is checked, since forwarded headers can be forged.
+                // Clients of unix sockets are local, they have no address.
+                let is_unix = req.conn_data::<Listener>().is_some_and(Listener::is_unix);
+                let peer_addr = req.peer_addr().map(|addr| addr.ip().to_string()).unwrap_or_default();
+                let res = (is_unix || allow_list.read().unwrap().allows(&peer_addr)).then(|| srv.call(req));
+                async move {
+                    match res {
+                        Some(res) => res.await,
+                        None => Err(error::InternalError::from_response(
+                            "IP address isn't allowed",
+                            HttpResponse::Forbidden().json(
+                                ResponseError::new()
+                                    .code(403)
+                                    .details("IP address isn't allowed")
+                                    .build(),
+                            ),
+                        )
+                        .into()),
+                    }
+                }
+            })
+            .wrap_fn(|req, srv| {
+                let res = srv.call(req);
+                async move {
+                    let res = res.await;
+                    Metrics::request(match &res {
+                        Ok(res) => res.status(),
+                        Err(err) => err.as_response_error().status_code(),
+                    }.as_u16());
+                    res
+                }
+            })
             .wrap(Logger::new(
                 "[%s] (%r %a) \n  ip: %{r}a\n  time: %Ts,\n  pid: %P,\n  user-agent: %{User-Agent}i,\n  content-type: %{Content-Type}i,\n  size: %bb",
             ))
-            .service(web::scope("/api/auth").service(services::auth::login)
+            .configure(match routes {
+                Routes::All => ServerRunner::all_routes,
+                Routes::Public => ServerRunner::public_routes,
+            })
+            .default_service(route().to(services::not_found::not_found))
+        })
+        .on_connect(move |conn, data| on_connect.on_connect(conn, data))
+        .shutdown_timeout(shutdown_timeout)
+        // vnsd stops the servers by itself when it receives a signal
+        .disable_signals();
+
+        let tls = tls
+            .map(|tls| tls.server_config())
+            .transpose()
+            .map_err(|e| IOError::new(Other, format!("{e:#}")))?;
+        for addr in bound.addrs() {
+            server = match (addr, &tls) {
+                (BoundAddr::Tcp(addr), Some(config)) => {
+                    server.bind_rustls_021(addr, config.clone())?
+                }
+                (BoundAddr::Tcp(addr), None) => server.bind(addr)?,
+                // Unix sockets are local, they're served by plain HTTP
+                (BoundAddr::Unix(path), _) => server.listen_uds(bind_unix(path)?)?,
+            };
+        }
+        Ok(server.run())
+    }
+
+    fn all_routes(cfg: &mut web::ServiceConfig) {
+        cfg.service(
+            web::scope("/api/auth").service(services::auth::login).service(
+                web::scope("/keys")
+                    .service(services::auth::keys::get_keys)
+                    .service(services::auth::keys::create_key)
+                    .service(services::auth::keys::revoke_keys)
+                    .service(services::auth::keys::revoke_key)
+                    .wrap(HttpAuthentication::bearer(Auth::validate)),
+            ),
         )
-            .service(
-                web::scope("/api")
+        .service(
+            web::scope("/api")
+                .service(services::traffic::get_traffic_summary)
                 .service(services::traffic::get_traffic)
                 .service(services::interface::get_interface)
+                .service(services::interface::get_interface_traffic)
                 .service(services::info::get_info)
+                .service(services::live::live)
                 .service(services::config::get_config)
                 .service(services::config::edit_config)
+                .service(services::config::get_config_history)
+                .service(services::config::rollback_config)
                 .service(services::daemon::get_daemon_status)
                 .service(services::daemon::stop_daemon)
                 .service(services::daemon::restart_daemon)
+                .service(services::federation::get_federated_traffic)
+                .service(services::federation::proxy)
+                .service(services::users::get_users)
+                .service(services::users::add_user)
+                .service(services::users::remove_user)
                 .wrap(HttpAuthentication::bearer(Auth::validate)),
-            ).default_service(route().to(services::not_found::not_found))
-        })
-        .bind(addr.get_tuple())
-            {
-                Err(err) => Err(err),
-                Ok(server) => {
-                    Ok(server.run())
-                }
-            }
+        )
+        .service(services::metrics::get_metrics);
+    }
+
+    fn public_routes(cfg: &mut web::ServiceConfig) {
+        cfg.service(web::scope("/api/auth").service(services::auth::login))
+            .service(
+                web::scope("/api")
+                    .service(services::traffic::get_traffic_summary)
+                    .service(services::traffic::get_traffic)
+                    .service(services::interface::get_interface)
+                    .service(services::interface::get_interface_traffic)
+                    .service(services::info::get_info)
+                    .service(services::live::live)
+                    .wrap(HttpAuthentication::bearer(Auth::validate)),
+            )
+            .service(services::metrics::get_metrics);
     }
 }
-#[derive(Clone)]
+
+/// Endpoints that a server serves
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Routes {
+    All,
+    /// Endpoints that read traffic and information of vnStat, and logging in to read them
+    Public,
+}
+
+/// Listeners of the server
+#[derive(Clone, PartialEq)]
 pub struct ServerAddr {
-    ip: String,
-    port: u16,
+    listeners: Vec<Listener>,
 }
 
 impl ServerAddr {
+    /// A mutating listener of `ip` and `port`
     pub fn new(ip: &str, port: u16) -> Self {
-        Self {
-            ip: ip.to_owned(),
-            port,
-        }
+        Self::from_listeners(vec![Listener::new(
+            ListenAddr::Tcp {
+                host: ip.to_owned(),
+                port,
+            },
+            true,
+        )])
     }
 
-    pub fn from_config_file() -> Self {
-        let configs = app::config::Configs::init().unwrap();
-        let (ip, port) = (configs.server().ip(), configs.server().port() as u16);
+    pub fn from_listeners(listeners: Vec<Listener>) -> Self {
+        Self { listeners }
+    }
 
-        Self { ip, port }
+    pub fn from_configs(listeners: &[ListenerConfigs]) -> Result<Self, String> {
+        let listeners = listeners
+            .iter()
+            .map(Listener::from_configs)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::from_listeners(listeners))
     }
 
-    pub fn get_tuple(&self) -> (String, u16) {
-        (self.ip.clone(), self.port)
+    /// `listeners` of `server` configs
+    pub fn from_config_file() -> Result<Self, String> {
+        let configs = app::config::Configs::current().map_err(|e| e.to_string())?;
+        Self::from_configs(&configs.server().listeners())
+    }
+
+    pub fn listeners(&self) -> &[Listener] {
+        &self.listeners
     }
 }
 
@@ -265,15 +578,14 @@ impl From<usize> for ServerStatusState {
     }
 }
 
-impl ToString for ServerStatusState {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for ServerStatusState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use self::ServerStatusState::*;
         match self {
-            Active => "active",
-            Idle => "idle",
-            InActive => "inactive",
+            Active => write!(f, "active"),
+            Idle => write!(f, "idle"),
+            InActive => write!(f, "inactive"),
         }
-        .to_string()
     }
 }
 
@@ -318,3 +630,210 @@ impl ServerStatus {
         self.get_state().eq(&ServerStatusState::Idle)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::{
+        database::{Connections, Create, Keys},
+        scope::Scope,
+    };
+    use std::net::TcpListener;
+
+    fn free_port() -> u16 {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    #[test]
+    async fn serve_read_endpoints_only_on_public_server() {
+        let auth_db = InitDatabase::pool().unwrap();
+        let key = {
+            let db = auth_db.get().unwrap();
+            let conn = Connections::new("127.0.0.1", "routes-test")
+                .create(&db)
+                .unwrap();
+            Keys::generate_new_key(&db, &conn.uuid())
+                .create(&db)
+                .unwrap()
+                .value()
+        };
+        let data = ServerData {
+            vnstat: web::Data::new(VnStat::default()),
+            auth_db: web::Data::new(auth_db),
+            http_client: web::Data::new(reqwest::Client::new()),
+            rate_limiter: web::Data::new(RateLimiter::from_configs()),
+            allow_list: web::Data::new(RwLock::new(AllowList::default())),
+            tls: None,
+        };
+        let (public, admin) = (free_port(), free_port());
+        let servers = Servers::new(
+            ServerAddr::new("127.0.0.1", public),
+            Some(ServerAddr::new("127.0.0.1", admin)),
+            data,
+        )
+        .unwrap();
+        let running = servers.clone();
+        actix_web::rt::spawn(async move { running.run().await });
+
+        let client = reqwest::Client::new();
+        let status = |port: u16, path: &'static str| {
+            let req = client
+                .get(format!("http://127.0.0.1:{port}{path}"))
+                .bearer_auth(&key);
+            async move { req.send().await.unwrap().status().as_u16() }
+        };
+        assert_ne!(status(public, "/api/info").await, 404);
+        assert_ne!(status(public, "/api/traffic/day").await, 404);
+        assert_eq!(status(public, "/api/config").await, 404);
+        assert_eq!(status(public, "/api/users").await, 404);
+        assert_eq!(status(public, "/api/auth/keys").await, 404);
+        assert_ne!(status(admin, "/api/info").await, 404);
+        assert_ne!(status(admin, "/api/config").await, 404);
+        assert_ne!(status(admin, "/api/auth/keys").await, 404);
+
+        // Stopping one of them keeps the other one running
+        servers.admin.as_ref().unwrap().stop().await.ok().unwrap();
+        assert_ne!(status(public, "/api/info").await, 404);
+        servers.public.stop().await.ok().unwrap();
+    }
+
+    /// Status of a request sent as it is, clients would normalize its path
+    async fn raw_status(port: u16, method: &str, path: &str, key: &str) -> u16 {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        let req = format!(
+            "{method} {path} HTTP/1.1\r\nHost: 127.0.0.1\r\nAuthorization: Bearer {key}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        );
+        stream.write_all(req.as_bytes()).await.unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).await.unwrap();
+        res.split(' ').nth(1).unwrap().parse().unwrap()
+    }
+
+    #[test]
+    async fn scope_encoded_and_dot_segment_paths_by_their_route() {
+        let auth_db = InitDatabase::pool().unwrap();
+        let key = {
+            let db = auth_db.get().unwrap();
+            let conn = Connections::new("127.0.0.1", "scopes-test")
+                .create(&db)
+                .unwrap();
+            Keys::generate_new_key(&db, &conn.uuid())
+                .owned_by(None, &[Scope::Read])
+                .create(&db)
+                .unwrap()
+                .value()
+        };
+        let data = ServerData {
+            vnstat: web::Data::new(VnStat::default()),
+            auth_db: web::Data::new(auth_db),
+            http_client: web::Data::new(reqwest::Client::new()),
+            rate_limiter: web::Data::new(RateLimiter::from_configs()),
+            allow_list: web::Data::new(RwLock::new(AllowList::default())),
+            tls: None,
+        };
+        let port = free_port();
+        let server = Server::new(ServerAddr::new("127.0.0.1", port), data).unwrap();
+        let running = server.clone();
+        actix_web::rt::spawn(async move { running.run().await });
+
+        let status =
+            |method: &'static str, path: &'static str| raw_status(port, method, path, &key);
+        assert_ne!(status("GET", "/api/%69nfo").await, 403);
+        assert_eq!(status("GET", "/api/%75sers").await, 403);
+        assert_eq!(status("GET", "/api/auth/%6beys").await, 403);
+        assert_eq!(status("PUT", "/api/%63onfig").await, 403);
+        assert_eq!(status("POST", "/api/%64aemon/stop").await, 403);
+        assert_eq!(
+            status("GET", "/api/federation/home/traffic/../users").await,
+            403
+        );
+        assert_eq!(
+            status("GET", "/api/federation/home/traffic/%2e%2e/users").await,
+            403
+        );
+        assert_eq!(
+            status("GET", "/api/federation/home/traffic%2F..%2Fusers").await,
+            403
+        );
+        server.stop().await.ok().unwrap();
+    }
+
+    #[test]
+    async fn rebind_to_changed_listeners() {
+        let data = ServerData {
+            vnstat: web::Data::new(VnStat::default()),
+            auth_db: web::Data::new(InitDatabase::pool().unwrap()),
+            http_client: web::Data::new(reqwest::Client::new()),
+            rate_limiter: web::Data::new(RateLimiter::from_configs()),
+            allow_list: web::Data::new(RwLock::new(AllowList::default())),
+            tls: None,
+        };
+        let (old, new) = (free_port(), free_port());
+        let server = Server::new(ServerAddr::new("127.0.0.1", old), data).unwrap();
+        let running = server.clone();
+        let run = actix_web::rt::spawn(async move { running.run().await });
+
+        let reachable = |port: u16| async move {
+            reqwest::get(format!("http://127.0.0.1:{port}/api/info"))
+                .await
+                .is_ok()
+        };
+        assert!(reachable(old).await);
+        server
+            .rebind(ServerAddr::new("127.0.0.1", new))
+            .await
+            .unwrap();
+        assert!(reachable(new).await);
+        assert!(!reachable(old).await);
+        assert!(!run.is_finished());
+
+        // An address in use is rejected, the server keeps its listeners
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = taken.local_addr().unwrap().port();
+        assert!(server
+            .rebind(ServerAddr::new("127.0.0.1", port))
+            .await
+            .is_err());
+        assert!(server.addr() == ServerAddr::new("127.0.0.1", new));
+        assert!(reachable(new).await);
+
+        server.stop().await.ok().unwrap();
+        assert!(run.await.unwrap().is_ok());
+    }
+
+    #[test]
+    async fn stop_servers_and_remove_unix_sockets() {
+        let data = ServerData {
+            vnstat: web::Data::new(VnStat::default()),
+            auth_db: web::Data::new(InitDatabase::pool().unwrap()),
+            http_client: web::Data::new(reqwest::Client::new()),
+            rate_limiter: web::Data::new(RateLimiter::from_configs()),
+            allow_list: web::Data::new(RwLock::new(AllowList::default())),
+            tls: None,
+        };
+        let path = std::env::temp_dir().join(format!("vnsd-{}.sock", uuid::Uuid::new_v4()));
+        let addr = ServerAddr::from_listeners(vec![
+            Listener::new(ListenAddr::parse("127.0.0.1:0").unwrap(), true),
+            Listener::new(ListenAddr::Unix(path.clone()), true),
+        ]);
+        let servers = Servers::new(addr, None, data).unwrap();
+        let running = servers.clone();
+        let run = actix_web::rt::spawn(async move { running.run().await });
+        while !servers.public.status().is_active() {
+            tokio::task::yield_now().await;
+        }
+        assert!(path.exists());
+
+        servers.stop().await;
+        assert!(run.await.unwrap().is_ok());
+        assert!(servers.public.status().is_inactive());
+        assert!(!path.exists());
+    }
+}
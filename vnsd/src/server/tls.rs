@@ -0,0 +1,210 @@
+use anyhow::{anyhow, Context, Result};
+use app::config::server::TlsConfigs;
+use log::{error, info};
+use rustls::{
+    server::{AllowAnyAuthenticatedClient, ClientHello, ResolvesServerCert},
+    sign::{self, CertifiedKey},
+    Certificate, PrivateKey, RootCertStore, ServerConfig,
+};
+use rustls_pemfile::Item;
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+/// Certificate and key of the HTTPS server, they can be replaced while it's running
+pub struct Tls {
+    cert_path: String,
+    key_path: String,
+    client_ca_path: Option<String>,
+    reload_interval: u64,
+    resolver: Arc<CertResolver>,
+}
+
+impl Tls {
+    pub fn new(configs: &TlsConfigs) -> Result<Self> {
+        let (cert_path, key_path) = (configs.cert(), configs.key());
+        if cert_path.is_empty() || key_path.is_empty() {
+            return Err(anyhow!("Both 'cert' and 'key' are required by TLS"));
+        }
+        let resolver = CertResolver {
+            key: RwLock::new(Arc::new(load_certified_key(&cert_path, &key_path)?)),
+            modified_at: RwLock::new(modified_at(&cert_path, &key_path)),
+        };
+        Ok(Self {
+            cert_path,
+            key_path,
+            client_ca_path: configs.client_ca(),
+            reload_interval: configs.reload_interval(),
+            resolver: Arc::new(resolver),
+        })
+    }
+
+    /// `tls` of `server` configs, `None` when it's disabled
+    pub fn from_configs() -> Result<Option<Self>> {
+        let configs = app::Configs::current()?.server().tls();
+        match configs.enabled() {
+            true => Self::new(&configs).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    /// Configuration of rustls, certificates are resolved by the current key of `self`
+    pub fn server_config(&self) -> Result<ServerConfig> {
+        let builder = ServerConfig::builder().with_safe_defaults();
+        let builder = match &self.client_ca_path {
+            Some(path) => {
+                let mut roots = RootCertStore::empty();
+                for cert in load_certs(path)? {
+                    roots
+                        .add(&cert)
+                        .with_context(|| format!("Invalid CA certificate in '{path}'"))?;
+                }
+                builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+            }
+            None => builder.with_no_client_auth(),
+        };
+        Ok(builder.with_cert_resolver(self.resolver.clone()))
+    }
+
+    /// Load the certificate and key again if their files were changed, returns whether they were.
+    /// The current ones are kept when new ones are invalid.
+    pub fn reload(&self) -> Result<bool> {
+        let modified_at = modified_at(&self.cert_path, &self.key_path);
+        if modified_at == *self.resolver.modified_at.read().unwrap() {
+            return Ok(false);
+        }
+        let key = load_certified_key(&self.cert_path, &self.key_path)?;
+        *self.resolver.key.write().unwrap() = Arc::new(key);
+        *self.resolver.modified_at.write().unwrap() = modified_at;
+        Ok(true)
+    }
+
+    /// Check the files every `reload_interval` seconds, until the runtime is shut down
+    pub fn watch(self: Arc<Self>) {
+        if self.reload_interval == 0 {
+            return;
+        }
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(self.reload_interval));
+            loop {
+                interval.tick().await;
+                match self.reload() {
+                    Ok(true) => info!("TLS certificate '{}' was reloaded", self.cert_path),
+                    Ok(false) => (),
+                    Err(e) => error!("Cannot reload TLS certificate: {e:#}"),
+                }
+            }
+        });
+    }
+}
+
+struct CertResolver {
+    key: RwLock<Arc<CertifiedKey>>,
+    /// Modification times of the certificate and key files that `key` was loaded from
+    modified_at: RwLock<Option<(SystemTime, SystemTime)>>,
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.key.read().unwrap().clone())
+    }
+}
+
+fn modified_at(cert_path: &str, key_path: &str) -> Option<(SystemTime, SystemTime)> {
+    let modified_at = |path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    Some((modified_at(cert_path)?, modified_at(key_path)?))
+}
+
+fn load_certified_key(cert_path: &str, key_path: &str) -> Result<CertifiedKey> {
+    let certs = load_certs(cert_path)?;
+    if certs.is_empty() {
+        return Err(anyhow!("No certificates in '{cert_path}'"));
+    }
+    let key = load_key(key_path)?;
+    let key = sign::any_supported_type(&key)
+        .map_err(|_| anyhow!("Unsupported private key in '{key_path}'"))?;
+    Ok(CertifiedKey::new(certs, key))
+}
+
+fn load_certs(path: &str) -> Result<Vec<Certificate>> {
+    let mut reader = BufReader::new(
+        File::open(path).with_context(|| format!("Cannot open certificate '{path}'"))?,
+    );
+    Ok(rustls_pemfile::certs(&mut reader)
+        .with_context(|| format!("Cannot read certificates from '{path}'"))?
+        .into_iter()
+        .map(Certificate)
+        .collect())
+}
+
+fn load_key(path: &str) -> Result<PrivateKey> {
+    let mut reader = BufReader::new(
+        File::open(path).with_context(|| format!("Cannot open private key '{path}'"))?,
+    );
+    rustls_pemfile::read_all(&mut reader)
+        .with_context(|| format!("Cannot read private key from '{path}'"))?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("No private key in '{path}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_cert(dir: &std::path::Path, name: &str) -> (String, String) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+        let cert_path = dir
+            .join(format!("{name}.crt"))
+            .to_string_lossy()
+            .to_string();
+        let key_path = dir
+            .join(format!("{name}.key"))
+            .to_string_lossy()
+            .to_string();
+        fs::write(&cert_path, cert.serialize_pem().unwrap()).unwrap();
+        fs::write(&key_path, cert.serialize_private_key_pem()).unwrap();
+        (cert_path, key_path)
+    }
+
+    #[test]
+    async fn load_and_reload_certificates() {
+        let dir = std::env::temp_dir().join(format!("vnsd-tls-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let (cert_path, key_path) = write_cert(&dir, "server");
+
+        let tls = Tls::new(&TlsConfigs::from(&cert_path, &key_path, None)).unwrap();
+        assert!(tls.server_config().is_ok());
+        assert!(!tls.reload().unwrap());
+
+        let old_cert = tls.resolver.key.read().unwrap().cert.clone();
+        std::thread::sleep(Duration::from_millis(10));
+        write_cert(&dir, "server");
+        assert!(tls.reload().unwrap());
+        assert_ne!(tls.resolver.key.read().unwrap().cert, old_cert);
+
+        // An invalid key keeps the current one
+        let current_cert = tls.resolver.key.read().unwrap().cert.clone();
+        std::thread::sleep(Duration::from_millis(10));
+        fs::write(&key_path, "not a key").unwrap();
+        assert!(tls.reload().is_err());
+        assert_eq!(tls.resolver.key.read().unwrap().cert, current_cert);
+
+        // The CA of client certificates
+        let (ca_path, _) = write_cert(&dir, "ca");
+        let (cert_path, key_path) = write_cert(&dir, "server");
+        let tls = Tls::new(&TlsConfigs::from(&cert_path, &key_path, Some(&ca_path))).unwrap();
+        assert!(tls.server_config().is_ok());
+
+        assert!(Tls::new(&TlsConfigs::from(&cert_path, "", None)).is_err());
+        assert!(Tls::new(&TlsConfigs::from(&key_path, &key_path, None)).is_err());
+        assert!(Tls::new(&TlsConfigs::from(&cert_path, &cert_path, None)).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,23 +1,55 @@
 use crate::server::{
-    api::auth::database::{BlockList, Connections, InitDatabase, Statements},
-    Server,
+    api::auth::{
+        database::{
+            migrations, BlockList, ConfigHistory, Connections, Keys, Statements, Users,
+            LOCAL_CONN_UUID,
+        },
+        password,
+        scope::{Role, Scope},
+        LOCAL_PEER_ADDR,
+    },
+    Routes, Server, Servers,
+};
+use app::Configs;
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    SqliteConnection,
 };
 use log::*;
-use std::collections::HashMap;
-use utils::unix_socket::{Commands::*, Request, Response, ServerResponseMessage};
+use utils::{
+    duration::parse_duration,
+    unix_socket::{Commands::*, Request, Response, ServerResponseMessage},
+};
 
 /// Handling request that coming from UNIX socket
 pub struct RequestHandler<'a> {
-    server: &'a Server,
+    servers: &'a Servers,
     req: Request,
+    /// User id of the process that sent the request
+    peer_uid: Option<u32>,
     res: &'a mut Response,
 }
 
 impl<'a> RequestHandler<'a> {
-    pub fn new(server: &'a Server, req: Request, res: &'a mut Response) -> Self {
-        Self { server, req, res }
+    pub fn new(
+        servers: &'a Servers,
+        req: Request,
+        peer_uid: Option<u32>,
+        res: &'a mut Response,
+    ) -> Self {
+        Self {
+            servers,
+            req,
+            peer_uid,
+            res,
+        }
     }
     pub async fn handle(&mut self) {
+        if let Some(action) = self.privileged_action() {
+            if !self.is_privileged_peer(action) {
+                return;
+            }
+        }
         match self.req.command {
             PauseServer => self.on_pause_server().await,
             ResumeServer => self.on_resume_server().await,
@@ -27,14 +59,72 @@ impl<'a> RequestHandler<'a> {
             UnBlockIPs => self.on_unblock_ip_addresses(),
             BlockList => self.on_block_list(),
             ConnectionsList => self.on_connections_list(),
+            ConfigHistory => self.on_config_history(),
+            ConfigRollback => self.on_config_rollback(),
+            UserAdd => self.on_user_add(),
+            UserRemove => self.on_user_remove(),
+            UserList => self.on_user_list(),
+            AuthSetPassword => self.on_auth_set_password(),
+            DbStatus => self.on_db_status(),
+            KeyList => self.on_key_list(),
+            KeyRevoke => self.on_key_revoke(),
+            KeyCreate => self.on_key_create(),
             _ => (),
         }
     }
 
+    /// What the command does when it's privileged, e.g. `create keys`. Privileged commands
+    /// manage credentials or change vnStat configuration.
+    fn privileged_action(&self) -> Option<&'static str> {
+        match self.req.command {
+            ConfigRollback => Some("roll back vnStat configuration"),
+            UserAdd => Some("add users"),
+            UserRemove => Some("remove users"),
+            AuthSetPassword => Some("change the password"),
+            KeyList => Some("list keys"),
+            KeyRevoke => Some("revoke keys"),
+            KeyCreate => Some("create keys"),
+            _ => None,
+        }
+    }
+
+    /// Whether the request is sent by root or by the user vnsd runs as, the socket is writable
+    /// by every local user so privileged commands are refused to the others
+    fn is_privileged_peer(&mut self, action: &str) -> bool {
+        let euid = unsafe { libc::geteuid() };
+        if self.peer_uid.is_some_and(|uid| uid == 0 || uid == euid) {
+            return true;
+        }
+        match self.peer_uid {
+            Some(uid) => warn!("Local user {uid} has been refused to {action}"),
+            None => warn!("Unknown local user has been refused to {action}"),
+        }
+        self.res.push(ServerResponseMessage::failed(&format!(
+            "Only root or the user of vnsd can {action}"
+        )));
+        false
+    }
+
+    /// The admin server when `--admin` is passed, otherwise the public one
+    fn target_server(&mut self) -> Option<&'a Server> {
+        if !self.req.args.iter().any(|arg| arg == "--admin") {
+            return Some(&self.servers.public);
+        }
+        if self.servers.admin.is_none() {
+            self.res
+                .push(ServerResponseMessage::failed("Admin server isn't enabled"));
+        }
+        self.servers.admin.as_ref()
+    }
+
     async fn on_pause_server(&mut self) {
+        let server = match self.target_server() {
+            Some(server) => server,
+            None => return,
+        };
         warn!("Pause server...",);
 
-        if let Err(err) = self.server.pause().await {
+        if let Err(err) = server.pause().await {
             error!("Cannot pause connections: {}", err.clone());
 
             self.res
@@ -47,8 +137,12 @@ impl<'a> RequestHandler<'a> {
         }
     }
     async fn on_resume_server(&mut self) {
+        let server = match self.target_server() {
+            Some(server) => server,
+            None => return,
+        };
         info!("Resume server...",);
-        if let Err(err) = self.server.resume().await {
+        if let Err(err) = server.resume().await {
             error!("Cannot resume connections: {}", err.clone());
 
             self.res
@@ -61,21 +155,37 @@ impl<'a> RequestHandler<'a> {
         }
     }
     fn on_status_server(&mut self) {
-        let (ip, port) = self.server.address();
-        let mut body = HashMap::new();
-
-        body.insert("ip", ip);
-        body.insert("port", port.to_string());
-        body.insert("status", self.server.status().get_state().to_string());
+        let status = |server: &Server| {
+            let tls = server.data().tls.is_some();
+            // The public server has no mutating endpoints while there's an admin server
+            let mutating = server.routes() == Routes::All;
+            let listeners = server
+                .listeners()
+                .iter()
+                .map(|l| {
+                    serde_json::json!({ "address": l.url(tls), "mutating": mutating && l.mutating })
+                })
+                .collect::<Vec<_>>();
+            serde_json::json!({
+                "status": server.status().get_state().to_string(),
+                "listeners": listeners,
+            })
+        };
+        let mut body = status(&self.servers.public);
+        body["admin"] = self.servers.admin.as_ref().map(status).into();
 
         self.res
-            .push(ServerResponseMessage::success(&format!("{body:?}")));
+            .push(ServerResponseMessage::success(&body.to_string()));
     }
 
     async fn on_shutdown_server(&mut self) {
+        let server = match self.target_server() {
+            Some(server) => server,
+            None => return,
+        };
         warn!("Shutdown server...");
 
-        if let Err(err) = self.server.stop().await {
+        if let Err(err) = server.stop().await {
             error!("Cannot stop server: {}", err.clone());
             self.res
                 .push(ServerResponseMessage::failed(&format!("{err}")));
@@ -87,17 +197,54 @@ impl<'a> RequestHandler<'a> {
         }
     }
 
+    /// Args are addresses, optionally preceded by `--for <DURATION>` and `--reason <REASON>`
     fn on_block_ip_addresses(&mut self) {
-        let db = InitDatabase::connect().unwrap();
-        db.init().unwrap();
-        for addr in self.req.args.iter() {
-            match BlockList::block(db.conn(), addr) {
+        let (mut duration, mut reason) = (None, None);
+        let mut args = self.req.args.iter().peekable();
+        while let Some(option) = args.next_if(|a| a.starts_with("--")) {
+            match (option.as_str(), args.next()) {
+                ("--for", Some(value)) => match parse_duration(value) {
+                    Ok(d) => duration = Some(d),
+                    Err(err) => {
+                        self.res.push(ServerResponseMessage::failed(&err));
+                        return;
+                    }
+                },
+                ("--reason", Some(value)) => reason = Some(value.as_str()),
+                _ => {
+                    self.res.push(ServerResponseMessage::failed(&format!(
+                        "Invalid option: '{option}'"
+                    )));
+                    return;
+                }
+            }
+        }
+        let addresses = args.cloned().collect::<Vec<_>>();
+        let reason = reason.map(str::to_owned);
+        let until = match duration.map(chrono::Duration::from_std) {
+            Some(Ok(d)) => Some(d),
+            Some(Err(_)) => {
+                self.res
+                    .push(ServerResponseMessage::failed("Duration is too long"));
+                return;
+            }
+            None => None,
+        };
+
+        let db = match self.auth_db() {
+            Some(db) => db,
+            None => return,
+        };
+        for addr in addresses.iter() {
+            match BlockList::block_for(&db, addr, until, reason.as_deref()) {
                 Ok(_) => {
-                    info!("{addr} has been blocked");
+                    let message = match duration {
+                        Some(d) => format!("{addr} has been blocked for {}s", d.as_secs()),
+                        None => format!("{addr} has been blocked"),
+                    };
+                    info!("{message}");
 
-                    self.res.push(ServerResponseMessage::success(&format!(
-                        "{addr} has been blocked"
-                    )));
+                    self.res.push(ServerResponseMessage::success(&message));
                 }
                 Err(err) => {
                     error!("Cannot block \"{addr}\": {err}");
@@ -111,11 +258,15 @@ impl<'a> RequestHandler<'a> {
     }
 
     fn on_unblock_ip_addresses(&mut self) {
-        let db = InitDatabase::connect().unwrap();
-        db.init().unwrap();
+        let db = match self.auth_db() {
+            Some(db) => db,
+            None => return,
+        };
         for addr in self.req.args.iter() {
-            match BlockList::unblock(db.conn(), addr) {
+            match BlockList::unblock(&db, addr) {
                 Ok(_) => {
+                    // Its failures are kept by the rate limiter until the block expires
+                    self.servers.public.data().rate_limiter.reset(addr);
                     info!("{addr} has been unblocked");
                     self.res.push(ServerResponseMessage::success(&format!(
                         "{addr} has been unblocked"
@@ -132,9 +283,11 @@ impl<'a> RequestHandler<'a> {
         }
     }
     fn on_block_list(&mut self) {
-        let db = InitDatabase::connect().unwrap();
-        db.init().unwrap();
-        let block_list = BlockList::select(db.conn(), |_| true);
+        let db = match self.auth_db() {
+            Some(db) => db,
+            None => return,
+        };
+        let block_list = BlockList::select(&db, |b| !b.is_expired());
 
         self.res.push(ServerResponseMessage::success(&format!(
             "{}",
@@ -142,13 +295,353 @@ impl<'a> RequestHandler<'a> {
         )));
     }
     fn on_connections_list(&mut self) {
-        let db = InitDatabase::connect().unwrap();
-        db.init().unwrap();
-        let connections_list = Connections::select(db.conn(), |_| true);
+        let db = match self.auth_db() {
+            Some(db) => db,
+            None => return,
+        };
+        let connections_list = Connections::select(&db, |_| true);
 
         self.res.push(ServerResponseMessage::success(&format!(
             "{}",
             serde_json::json!(connections_list)
         )));
     }
+
+    fn on_config_history(&mut self) {
+        let db = match self.auth_db() {
+            Some(db) => db,
+            None => return,
+        };
+        let limit = self.req.args.first().and_then(|l| l.parse().ok());
+        match ConfigHistory::revisions(&db, limit) {
+            Ok(revisions) => self.res.push(ServerResponseMessage::success(&format!(
+                "{}",
+                serde_json::json!(revisions)
+            ))),
+            Err(err) => {
+                error!("Cannot get configuration history: {err}");
+                self.res.push(ServerResponseMessage::failed(&format!(
+                    "Cannot get configuration history: {err}"
+                )))
+            }
+        };
+    }
+
+    fn on_config_rollback(&mut self) {
+        let revision = match self.req.args.first().map(|r| r.parse::<i32>()) {
+            Some(Ok(revision)) => revision,
+            _ => {
+                self.res
+                    .push(ServerResponseMessage::failed("Invalid revision number"));
+                return;
+            }
+        };
+        let db = match self.auth_db() {
+            Some(db) => db,
+            None => return,
+        };
+        match ConfigHistory::rollback(
+            &db,
+            &self.servers.public.data().vnstat.config(),
+            LOCAL_CONN_UUID,
+            revision,
+        ) {
+            Ok(Some(rollback)) => {
+                let message = format!(
+                    "Configuration has been rolled back to revision {revision} as revision {}",
+                    rollback.revision
+                );
+                info!("{message}");
+                self.res.push(ServerResponseMessage::success(&message));
+            }
+            Ok(None) => {
+                self.res.push(ServerResponseMessage::success(&format!(
+                    "Configuration didn't change since revision {revision}"
+                )));
+            }
+            Err(err) => {
+                error!("Cannot roll back configuration to revision {revision}: {err}");
+                self.res.push(ServerResponseMessage::failed(&format!(
+                    "Cannot roll back configuration to revision {revision}: {err}"
+                )));
+            }
+        }
+    }
+
+    /// Args are the username, the role and the password
+    fn on_user_add(&mut self) {
+        let (username, role, password) = match &self.req.args[..] {
+            [username, role, password] => (username.clone(), role, password),
+            _ => {
+                self.res.push(ServerResponseMessage::failed(
+                    "Username, role and password are required",
+                ));
+                return;
+            }
+        };
+        let role = match role.parse::<Role>() {
+            Ok(role) => role,
+            Err(err) => {
+                self.res.push(ServerResponseMessage::failed(&err));
+                return;
+            }
+        };
+        let password = password.clone();
+        let db = match self.auth_db() {
+            Some(db) => db,
+            None => return,
+        };
+        match Users::add(&db, &username, &password, role) {
+            Ok(_) => {
+                let message = format!("User '{username}' has been added as {role}");
+                info!("{message}");
+                self.res.push(ServerResponseMessage::success(&message));
+            }
+            Err(err) => {
+                error!("Cannot add user '{username}': {err}");
+                self.res.push(ServerResponseMessage::failed(&format!(
+                    "Cannot add user '{username}': {err}"
+                )));
+            }
+        }
+    }
+
+    fn on_user_remove(&mut self) {
+        let db = match self.auth_db() {
+            Some(db) => db,
+            None => return,
+        };
+        for username in self.req.args.iter() {
+            match Users::remove(&db, username) {
+                Ok(()) => {
+                    info!("User '{username}' has been removed");
+                    self.res.push(ServerResponseMessage::success(&format!(
+                        "User '{username}' has been removed"
+                    )));
+                }
+                Err(err) => {
+                    error!("Cannot remove user '{username}': {err}");
+                    self.res.push(ServerResponseMessage::failed(&format!(
+                        "Cannot remove user '{username}': {err}"
+                    )));
+                }
+            }
+        }
+    }
+
+    fn on_user_list(&mut self) {
+        let db = match self.auth_db() {
+            Some(db) => db,
+            None => return,
+        };
+        let users = Users::select(&db, |_| true);
+
+        self.res.push(ServerResponseMessage::success(&format!(
+            "{}",
+            serde_json::json!(users)
+        )));
+    }
+
+    /// Store the hash of the new password in `auth` configs and revoke keys issued by the old one
+    fn on_auth_set_password(&mut self) {
+        let password = match self.req.args.first() {
+            Some(password) if !password.is_empty() => password.clone(),
+            _ => {
+                self.res
+                    .push(ServerResponseMessage::failed("Password can't be empty"));
+                return;
+            }
+        };
+        let mut configs = match Configs::init() {
+            Ok(configs) => configs,
+            Err(err) => {
+                error!("Cannot read configuration file: {err}");
+                self.res.push(ServerResponseMessage::failed(&format!(
+                    "Cannot read configuration file: {err}"
+                )));
+                return;
+            }
+        };
+        let mut auth = configs.auth();
+        auth.set_password_hash(&password::hash(&password));
+        configs.set_auth(auth);
+        if let Err(err) = configs.save() {
+            error!("Cannot write configuration file: {err}");
+            self.res.push(ServerResponseMessage::failed(&format!(
+                "Cannot write configuration file: {err}"
+            )));
+            return;
+        }
+        // The new password is in force right away, other changes of the file wait for a reload
+        if let Ok(current) = Configs::current() {
+            let mut current = (*current).clone();
+            current.set_auth(configs.auth());
+            Configs::set_current(current);
+        }
+
+        let db = match self.auth_db() {
+            Some(db) => db,
+            None => return,
+        };
+        match Keys::revoke_password_keys(&db) {
+            Ok(revoked) => {
+                let message = format!("Password has been changed, {revoked} key(s) revoked");
+                info!("{message}");
+                self.res.push(ServerResponseMessage::success(&message));
+            }
+            Err(err) => {
+                error!("Cannot revoke keys of the old password: {err}");
+                self.res.push(ServerResponseMessage::failed(&format!(
+                    "Password has been changed, but keys of the old password cannot be revoked: {err}"
+                )));
+            }
+        }
+    }
+
+    fn on_db_status(&mut self) {
+        let db = match self.auth_db() {
+            Some(db) => db,
+            None => return,
+        };
+        match migrations::status(&db) {
+            Ok(status) => self.res.push(ServerResponseMessage::success(&format!(
+                "{}",
+                serde_json::json!(status)
+            ))),
+            Err(err) => {
+                error!("Cannot get status of migrations: {err}");
+                self.res.push(ServerResponseMessage::failed(&format!(
+                    "Cannot get status of migrations: {err}"
+                )))
+            }
+        };
+    }
+
+    /// Arg is a connection uuid to list only its keys
+    fn on_key_list(&mut self) {
+        let db = match self.auth_db() {
+            Some(db) => db,
+            None => return,
+        };
+        let keys = Keys::active(&db, self.req.args.first().map(String::as_str))
+            .into_iter()
+            .map(Keys::masked)
+            .collect::<Vec<_>>();
+
+        self.res.push(ServerResponseMessage::success(&format!(
+            "{}",
+            serde_json::json!(keys)
+        )));
+    }
+
+    /// Args are ids of keys, or `--conn <UUID>` to revoke every key of a connection
+    fn on_key_revoke(&mut self) {
+        let db = match self.auth_db() {
+            Some(db) => db,
+            None => return,
+        };
+        if let [option, conn_uuid] = &self.req.args[..] {
+            if option == "--conn" {
+                match Keys::revoke_connection(&db, conn_uuid) {
+                    Ok(revoked) => {
+                        let message = format!(
+                            "{revoked} key(s) of connection '{conn_uuid}' have been revoked"
+                        );
+                        info!("{message}");
+                        self.res.push(ServerResponseMessage::success(&message));
+                    }
+                    Err(err) => {
+                        error!("Cannot revoke keys of connection '{conn_uuid}': {err}");
+                        self.res.push(ServerResponseMessage::failed(&format!(
+                            "Cannot revoke keys of connection '{conn_uuid}': {err}"
+                        )));
+                    }
+                }
+                return;
+            }
+        }
+        for id in self.req.args.iter() {
+            let result = match id.parse::<i32>() {
+                Ok(id) => Keys::revoke(&db, id),
+                Err(_) => Err(anyhow::anyhow!("Invalid key id")),
+            };
+            match result {
+                Ok(()) => {
+                    info!("Key {id} has been revoked");
+                    self.res.push(ServerResponseMessage::success(&format!(
+                        "Key {id} has been revoked"
+                    )));
+                }
+                Err(err) => {
+                    error!("Cannot revoke key {id}: {err}");
+                    self.res.push(ServerResponseMessage::failed(&format!(
+                        "Cannot revoke key {id}: {err}"
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Args are the label, optionally preceded by `--scopes <SCOPES>` and `--for <DURATION>`
+    fn on_key_create(&mut self) {
+        let (mut scopes, mut valid_for) = (vec![Scope::Read], None);
+        let mut args = self.req.args.iter().peekable();
+        while let Some(option) = args.next_if(|a| a.starts_with("--")) {
+            let parsed = match (option.as_str(), args.next()) {
+                ("--scopes", Some(value)) => Scope::parse_list(value).map(|s| scopes = s),
+                ("--for", Some(value)) => parse_duration(value).and_then(|d| {
+                    chrono::Duration::from_std(d)
+                        .map(|d| valid_for = Some(d))
+                        .map_err(|_| "Duration is too long".to_owned())
+                }),
+                _ => Err(format!("Invalid option: '{option}'")),
+            };
+            if let Err(err) = parsed {
+                self.res.push(ServerResponseMessage::failed(&err));
+                return;
+            }
+        }
+        let label = match args.next() {
+            Some(label) => label.clone(),
+            None => {
+                self.res
+                    .push(ServerResponseMessage::failed("Label is required"));
+                return;
+            }
+        };
+
+        let db = match self.auth_db() {
+            Some(db) => db,
+            None => return,
+        };
+        match Keys::issue(&db, &label, None, &scopes, valid_for, LOCAL_PEER_ADDR) {
+            Ok(key) => {
+                info!("Service key {} '{label}' has been created", key.id);
+                self.res.push(ServerResponseMessage::success(&format!(
+                    "Key {} '{label}' has been created, it expires at {}:\n{}",
+                    key.id, key.expires_at, key.value
+                )));
+            }
+            Err(err) => {
+                error!("Cannot create key '{label}': {err}");
+                self.res.push(ServerResponseMessage::failed(&format!(
+                    "Cannot create key '{label}': {err}"
+                )));
+            }
+        }
+    }
+
+    /// Connection of the authentication database, failure is pushed to the response
+    fn auth_db(&mut self) -> Option<PooledConnection<ConnectionManager<SqliteConnection>>> {
+        match self.servers.public.data().auth_db.get() {
+            Ok(db) => Some(db),
+            Err(err) => {
+                error!("Cannot connect to authentication database: {err}");
+                self.res.push(ServerResponseMessage::failed(&format!(
+                    "Cannot connect to authentication database: {err}"
+                )));
+                None
+            }
+        }
+    }
 }
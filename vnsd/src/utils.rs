@@ -1,27 +1,30 @@
 use anyhow::Result;
-use log::info;
-use tokio::{
-    signal::unix::{signal, SignalKind},
-    spawn,
-};
+use std::net::IpAddr;
+use tokio::signal::unix::{signal, Signal, SignalKind};
 
-/// Terminate vnsd process by UNIX Signals
-pub async fn terminate_process() -> Result<()> {
-    let signals = Vec::from([
-        SignalKind::terminate(),
-        SignalKind::interrupt(),
-        SignalKind::quit(),
-    ]);
+/// Wait for a UNIX signal that asks vnsd to shut down, returns its name
+pub async fn terminate_signal() -> Result<&'static str> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut quit = signal(SignalKind::quit())?;
 
-    for sig in signals {
-        spawn(async move {
-            if let Some(_) = signal(sig)?.recv().await {
-                info!("{:?} recevied, the process is shutdown now.", sig);
-                std::process::exit(1);
-            }
-            Result::<()>::Ok(())
-        });
-    }
+    Ok(tokio::select! {
+        _ = terminate.recv() => "SIGTERM",
+        _ = interrupt.recv() => "SIGINT",
+        _ = quit.recv() => "SIGQUIT",
+    })
+}
+
+/// SIGHUP asks vnsd to reload its configs, it doesn't terminate the process once it's listened to
+pub fn reload_signal() -> Result<Signal> {
+    Ok(signal(SignalKind::hangup())?)
+}
 
-    Ok(())
+/// Whether the server is only reachable from this host
+pub fn is_loopback(ip: &str) -> bool {
+    ip.eq_ignore_ascii_case("localhost")
+        || ip
+            .parse::<IpAddr>()
+            .map(|ip| ip.is_loopback())
+            .unwrap_or(false)
 }